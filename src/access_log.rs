@@ -0,0 +1,492 @@
+// 访问日志（[access_log]）：每个请求一行，写入独立文件，与 tracing 日志互不影响
+// - AccessLogLayer：记录对端地址、请求行、状态码、响应字节数、耗时、Referer、User-Agent 与请求 ID
+// - 格式：common（Common Log Format）、combined（CLF 加 Referer 与 User-Agent）、json（每行一个 JSON 对象）
+// - 写入：请求路径上只格式化并放入有界队列，由专用线程写文件；队列满时丢弃并计数，不阻塞请求
+// - 轮转：文件超过 max_size_bytes 或日期（UTC）变化时，path 改名为 path.1，原 path.N 依次改为 path.N+1，
+//   超过 keep 份的旧文件删除
+// 响应字节数取自 Content-Length（流式响应未知时为 "-"），耗时为生成响应头所用时间
+use std::{
+    ffi::OsString,
+    fmt::Write as _,
+    fs::{self, File, OpenOptions},
+    future::Future,
+    io::{self, BufWriter, Write},
+    net::IpAddr,
+    path::{Path, PathBuf},
+    pin::Pin,
+    sync::{
+        atomic::{AtomicU64, Ordering},
+        mpsc::{self, Receiver, SyncSender, TrySendError},
+        Arc,
+    },
+    task::{Context, Poll},
+    time::{Duration, Instant, SystemTime, UNIX_EPOCH},
+};
+
+use axum::{
+    body::HttpBody,
+    extract::{ConnectInfo, Request},
+    http::{header, HeaderMap, Version},
+    response::Response,
+};
+use serde_json::json;
+use tower::{Layer, Service};
+use tracing::warn;
+
+use crate::{
+    config::{AccessLogConfig, AccessLogFormat},
+    request_id::RequestId,
+    server::RemoteAddr,
+};
+
+// 写入线程队列容量（行数）
+const QUEUE_CAPACITY: usize = 8192;
+
+// 等待写入线程刷新文件的最长时间
+const FLUSH_TIMEOUT: Duration = Duration::from_secs(5);
+
+enum Message {
+    Line(String, SystemTime),
+    Flush(SyncSender<()>),
+}
+
+pub struct AccessLog {
+    format: AccessLogFormat,
+    tx: SyncSender<Message>,
+    dropped: AtomicU64,
+}
+
+impl AccessLog {
+    // 未配置 path 时返回 None；文件无法打开时返回错误（启动时即失败）
+    pub fn open(cfg: &AccessLogConfig) -> io::Result<Option<Arc<Self>>> {
+        let Some(path) = &cfg.path else {
+            return Ok(None);
+        };
+        let mut file = RotatingFile::open(path, cfg.max_size_bytes, cfg.daily, cfg.keep)?;
+        let (tx, rx) = mpsc::sync_channel(QUEUE_CAPACITY);
+        std::thread::Builder::new()
+            .name("access-log".into())
+            .spawn(move || file.run(rx))?;
+        Ok(Some(Arc::new(Self {
+            format: cfg.format,
+            tx,
+            dropped: AtomicU64::new(0),
+        })))
+    }
+
+    pub fn format(&self) -> AccessLogFormat {
+        self.format
+    }
+
+    // 写入队列已满而丢弃的行数
+    pub fn dropped(&self) -> u64 {
+        self.dropped.load(Ordering::Relaxed)
+    }
+
+    pub fn log(&self, entry: &Entry) {
+        let line = entry.format(self.format);
+        if let Err(TrySendError::Full(_)) = self.tx.try_send(Message::Line(line, entry.time)) {
+            self.dropped.fetch_add(1, Ordering::Relaxed);
+        }
+    }
+
+    // 等待已入队的行写入文件（关闭前调用）；写入线程已退出或超时时返回 false
+    pub fn flush(&self) -> bool {
+        let (ack, done) = mpsc::sync_channel(1);
+        self.tx.send(Message::Flush(ack)).is_ok() && done.recv_timeout(FLUSH_TIMEOUT).is_ok()
+    }
+}
+
+// 一个请求的访问日志内容
+#[derive(Debug, Clone)]
+pub struct Entry {
+    pub time: SystemTime,
+    pub remote: Option<IpAddr>,
+    pub method: String,
+    pub uri: String,
+    pub version: Version,
+    pub status: u16,
+    pub bytes: Option<u64>,
+    pub duration: Duration,
+    pub referer: Option<String>,
+    pub user_agent: Option<String>,
+    pub request_id: Option<String>,
+}
+
+impl Entry {
+    // 格式化为一行（不含换行符）
+    pub fn format(&self, format: AccessLogFormat) -> String {
+        match format {
+            AccessLogFormat::Common => self.common(),
+            AccessLogFormat::Combined => {
+                let quoted = |v: &Option<String>| v.as_deref().map_or("-".into(), escape);
+                format!(
+                    "{} \"{}\" \"{}\"",
+                    self.common(),
+                    quoted(&self.referer),
+                    quoted(&self.user_agent)
+                )
+            }
+            AccessLogFormat::Json => json!({
+                "time": rfc3339(self.time),
+                "remote_addr": self.remote.map(|ip| ip.to_string()),
+                "method": self.method,
+                "uri": self.uri,
+                "protocol": format!("{:?}", self.version),
+                "status": self.status,
+                "bytes": self.bytes,
+                "duration_ms": self.duration.as_secs_f64() * 1000.0,
+                "referer": self.referer,
+                "user_agent": self.user_agent,
+                "request_id": self.request_id,
+            })
+            .to_string(),
+        }
+    }
+
+    // host ident authuser [date] "request" status bytes
+    fn common(&self) -> String {
+        format!(
+            "{} - - [{}] \"{} {} {:?}\" {} {}",
+            self.remote.map_or("-".into(), |ip| ip.to_string()),
+            clf_time(self.time),
+            escape(&self.method),
+            escape(&self.uri),
+            self.version,
+            self.status,
+            self.bytes.map_or("-".into(), |b| b.to_string()),
+        )
+    }
+}
+
+// 引号内的字段：转义 " 与 \，控制字符与非 ASCII 字节写作 \xHH（同 Apache httpd），避免伪造日志行
+fn escape(s: &str) -> String {
+    let mut out = String::with_capacity(s.len());
+    for &b in s.as_bytes() {
+        match b {
+            b'"' => out.push_str("\\\""),
+            b'\\' => out.push_str("\\\\"),
+            0x20..=0x7e => out.push(b as char),
+            _ => {
+                let _ = write!(out, "\\x{b:02x}");
+            }
+        }
+    }
+    out
+}
+
+// UTC 时间拆分为 (年, 月, 日, 时, 分, 秒, 毫秒)
+fn civil(t: SystemTime) -> (i64, u32, u32, u64, u64, u64, u32) {
+    let d = t.duration_since(UNIX_EPOCH).unwrap_or_default();
+    let secs = d.as_secs();
+    let (y, m, day) = civil_from_days((secs / 86400) as i64);
+    let s = secs % 86400;
+    (y, m, day, s / 3600, s / 60 % 60, s % 60, d.subsec_millis())
+}
+
+// 距 1970-01-01 的天数换算为公历日期（Howard Hinnant 的 civil_from_days 算法）
+fn civil_from_days(z: i64) -> (i64, u32, u32) {
+    let z = z + 719468;
+    let era = z.div_euclid(146097);
+    let doe = z.rem_euclid(146097);
+    let yoe = (doe - doe / 1460 + doe / 36524 - doe / 146096) / 365;
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    let mp = (5 * doy + 2) / 153;
+    let d = (doy - (153 * mp + 2) / 5 + 1) as u32;
+    let m = if mp < 10 { mp + 3 } else { mp - 9 } as u32;
+    let y = yoe + era * 400 + i64::from(m <= 2);
+    (y, m, d)
+}
+
+// CLF 时间：15/Oct/2026:14:30:02 +0000
+fn clf_time(t: SystemTime) -> String {
+    const MONTHS: [&str; 12] = [
+        "Jan", "Feb", "Mar", "Apr", "May", "Jun", "Jul", "Aug", "Sep", "Oct", "Nov", "Dec",
+    ];
+    let (y, m, d, hh, mm, ss, _) = civil(t);
+    format!(
+        "{d:02}/{}/{y}:{hh:02}:{mm:02}:{ss:02} +0000",
+        MONTHS[m as usize - 1]
+    )
+}
+
+// RFC 3339（毫秒精度，UTC）：2026-10-15T14:30:02.123Z
+pub(crate) fn rfc3339(t: SystemTime) -> String {
+    let (y, m, d, hh, mm, ss, ms) = civil(t);
+    format!("{y}-{m:02}-{d:02}T{hh:02}:{mm:02}:{ss:02}.{ms:03}Z")
+}
+
+// 距 1970-01-01 的天数（UTC），用于按日轮转
+fn day_of(t: SystemTime) -> u64 {
+    t.duration_since(UNIX_EPOCH).unwrap_or_default().as_secs() / 86400
+}
+
+// 由写入线程独占的日志文件
+struct RotatingFile {
+    path: PathBuf,
+    max_size: u64,
+    daily: bool,
+    keep: usize,
+    file: BufWriter<File>,
+    size: u64,
+    day: u64,
+}
+
+impl RotatingFile {
+    // 已有文件的日期取其修改时间，使重启后仍能按日轮转
+    fn open(path: &Path, max_size: u64, daily: bool, keep: usize) -> io::Result<Self> {
+        let file = append(path)?;
+        let meta = file.metadata()?;
+        let day = day_of(meta.modified().unwrap_or_else(|_| SystemTime::now()));
+        Ok(Self {
+            path: path.to_path_buf(),
+            max_size,
+            daily,
+            keep,
+            file: BufWriter::new(file),
+            size: meta.len(),
+            day,
+        })
+    }
+
+    // 逐条写入；队列暂时为空时刷新缓冲，发送方全部释放后退出
+    fn run(&mut self, rx: Receiver<Message>) {
+        while let Ok(mut msg) = rx.recv() {
+            loop {
+                match msg {
+                    Message::Line(line, time) => {
+                        if let Err(e) = self.write(&line, time) {
+                            warn!("failed to write access log {:?}: {}", self.path, e);
+                        }
+                    }
+                    Message::Flush(ack) => {
+                        let _ = self.file.flush();
+                        let _ = ack.send(());
+                    }
+                }
+                match rx.try_recv() {
+                    Ok(next) => msg = next,
+                    Err(_) => break,
+                }
+            }
+            let _ = self.file.flush();
+        }
+    }
+
+    fn write(&mut self, line: &str, now: SystemTime) -> io::Result<()> {
+        let len = line.len() as u64 + 1;
+        let day = day_of(now);
+        let oversize = self.max_size > 0 && self.size > 0 && self.size + len > self.max_size;
+        if oversize || (self.daily && day != self.day && self.size > 0) {
+            self.rotate()?;
+        }
+        self.day = day;
+        self.file.write_all(line.as_bytes())?;
+        self.file.write_all(b"\n")?;
+        self.size += len;
+        Ok(())
+    }
+
+    fn rotate(&mut self) -> io::Result<()> {
+        self.file.flush()?;
+        if self.keep == 0 {
+            fs::remove_file(&self.path)?;
+        } else {
+            ignore_missing(fs::remove_file(self.rotated(self.keep)))?;
+            for n in (1..self.keep).rev() {
+                ignore_missing(fs::rename(self.rotated(n), self.rotated(n + 1)))?;
+            }
+            fs::rename(&self.path, self.rotated(1))?;
+        }
+        self.file = BufWriter::new(append(&self.path)?);
+        self.size = 0;
+        Ok(())
+    }
+
+    // 第 n 份已轮转文件：path.n
+    fn rotated(&self, n: usize) -> PathBuf {
+        let mut name = OsString::from(self.path.as_os_str());
+        name.push(format!(".{n}"));
+        name.into()
+    }
+}
+
+fn append(path: &Path) -> io::Result<File> {
+    OpenOptions::new().create(true).append(true).open(path)
+}
+
+fn ignore_missing(res: io::Result<()>) -> io::Result<()> {
+    match res {
+        Err(e) if e.kind() == io::ErrorKind::NotFound => Ok(()),
+        res => res,
+    }
+}
+
+#[derive(Clone)]
+pub struct AccessLogLayer {
+    log: Arc<AccessLog>,
+}
+
+impl AccessLogLayer {
+    pub fn new(log: Arc<AccessLog>) -> Self {
+        Self { log }
+    }
+}
+
+impl<S> Layer<S> for AccessLogLayer {
+    type Service = AccessLogService<S>;
+
+    fn layer(&self, inner: S) -> Self::Service {
+        AccessLogService {
+            inner,
+            log: self.log.clone(),
+        }
+    }
+}
+
+#[derive(Clone)]
+pub struct AccessLogService<S> {
+    inner: S,
+    log: Arc<AccessLog>,
+}
+
+impl<S, B> Service<Request> for AccessLogService<S>
+where
+    S: Service<Request, Response = Response<B>> + Send + 'static,
+    S::Future: Send + 'static,
+    B: HttpBody,
+{
+    type Response = S::Response;
+    type Error = S::Error;
+    type Future = Pin<Box<dyn Future<Output = Result<Self::Response, Self::Error>> + Send>>;
+
+    fn poll_ready(&mut self, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        self.inner.poll_ready(cx)
+    }
+
+    fn call(&mut self, req: Request) -> Self::Future {
+        let time = SystemTime::now();
+        let start = Instant::now();
+        let headers = req.headers();
+        let mut entry = Entry {
+            time,
+            remote: req
+                .extensions()
+                .get::<ConnectInfo<RemoteAddr>>()
+                .and_then(|ConnectInfo(addr)| addr.ip()),
+            method: req.method().to_string(),
+            uri: req.uri().to_string(),
+            version: req.version(),
+            status: 0,
+            bytes: None,
+            duration: Duration::ZERO,
+            referer: header_str(headers, header::REFERER),
+            user_agent: header_str(headers, header::USER_AGENT),
+            request_id: req
+                .extensions()
+                .get::<RequestId>()
+                .map(|id| id.as_str().to_string()),
+        };
+        let log = self.log.clone();
+        let fut = self.inner.call(req);
+        Box::pin(async move {
+            let res = fut.await?;
+            entry.status = res.status().as_u16();
+            entry.bytes = res
+                .headers()
+                .get(header::CONTENT_LENGTH)
+                .and_then(|v| v.to_str().ok()?.parse().ok())
+                .or_else(|| res.body().size_hint().exact());
+            entry.duration = start.elapsed();
+            log.log(&entry);
+            Ok(res)
+        })
+    }
+}
+
+fn header_str(headers: &HeaderMap, name: header::HeaderName) -> Option<String> {
+    headers
+        .get(name)
+        .map(|v| String::from_utf8_lossy(v.as_bytes()).into_owned())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn entry() -> Entry {
+        Entry {
+            // 2026-10-15T14:30:02.250Z
+            time: UNIX_EPOCH + Duration::from_millis(1_792_074_602_250),
+            remote: Some("127.0.0.1".parse().unwrap()),
+            method: "GET".into(),
+            uri: "/sum?nums=1,2".into(),
+            version: Version::HTTP_11,
+            status: 200,
+            bytes: Some(42),
+            duration: Duration::from_micros(1500),
+            referer: None,
+            user_agent: Some("curl/8.0 \"x\"\n".into()),
+            request_id: Some("req-1".into()),
+        }
+    }
+
+    #[test]
+    fn formats_lines() {
+        let e = entry();
+        assert_eq!(
+            e.format(AccessLogFormat::Common),
+            "127.0.0.1 - - [15/Oct/2026:14:30:02 +0000] \"GET /sum?nums=1,2 HTTP/1.1\" 200 42"
+        );
+        assert_eq!(
+            e.format(AccessLogFormat::Combined),
+            "127.0.0.1 - - [15/Oct/2026:14:30:02 +0000] \"GET /sum?nums=1,2 HTTP/1.1\" 200 42 \
+             \"-\" \"curl/8.0 \\\"x\\\"\\x0a\""
+        );
+        let v: serde_json::Value = serde_json::from_str(&e.format(AccessLogFormat::Json)).unwrap();
+        assert_eq!(v["time"], "2026-10-15T14:30:02.250Z");
+        assert_eq!(v["protocol"], "HTTP/1.1");
+        assert_eq!(v["duration_ms"], 1.5);
+        assert_eq!(v["referer"], serde_json::Value::Null);
+        assert_eq!(v["request_id"], "req-1");
+
+        let e = Entry {
+            remote: None,
+            bytes: None,
+            ..entry()
+        };
+        assert!(e.format(AccessLogFormat::Common).starts_with("- - - ["));
+        assert!(e.format(AccessLogFormat::Common).ends_with(" 200 -"));
+        assert_eq!(civil_from_days(-1), (1969, 12, 31));
+        assert_eq!(civil_from_days(11016), (2000, 2, 29));
+    }
+
+    // 按大小与日期轮转，只保留 keep 份旧文件
+    #[test]
+    fn rotates_by_size_and_day() {
+        let dir = std::env::temp_dir().join(format!("rustdemo-access-{}", std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("access.log");
+        let mut file = RotatingFile::open(&path, 20, true, 2).unwrap();
+        let day = UNIX_EPOCH + Duration::from_secs(86400 * 20000);
+        let read = |p: PathBuf| fs::read_to_string(p).unwrap_or_default();
+
+        file.write("aaaaaaaaa", day).unwrap();
+        file.write("bbbbbbbbb", day).unwrap();
+        file.write("ccccccccc", day).unwrap();
+        file.file.flush().unwrap();
+        assert_eq!(read(path.clone()), "ccccccccc\n");
+        assert_eq!(read(file.rotated(1)), "aaaaaaaaa\nbbbbbbbbb\n");
+
+        file.write("dd", day + Duration::from_secs(86400)).unwrap();
+        file.write("ee", day + Duration::from_secs(86400 * 2))
+            .unwrap();
+        file.file.flush().unwrap();
+        assert_eq!(read(path.clone()), "ee\n");
+        assert_eq!(read(file.rotated(1)), "dd\n");
+        assert_eq!(read(file.rotated(2)), "ccccccccc\n");
+        assert!(!file.rotated(3).exists());
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
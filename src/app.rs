@@ -0,0 +1,277 @@
+// 路由构建：注册内置端点、注入状态、挂载中间件
+// - build_app(config)：使用默认设置直接得到 Router
+// - AppBuilder：可追加自定义路由（可访问 AppState）与自定义中间件层
+// - 公共路由（/、/sum、/echo、/parallel、静态文件）与管理路由（/health、/metrics、/admin/*）
+//   可合并为一个 Router（build），也可拆分到不同监听上（build_split）
+use std::{convert::Infallible, sync::Arc, time::Duration};
+
+use axum::{
+    extract::Request,
+    http::StatusCode,
+    response::{IntoResponse, Response},
+    routing::{get, post, MethodRouter, Route},
+    Router,
+};
+use tower::{Layer, Service};
+use tower_http::{
+    cors::{AllowOrigin, CorsLayer},
+    services::ServeDir,
+    timeout::TimeoutLayer,
+    trace::TraceLayer,
+};
+use tracing::Span;
+
+use crate::{
+    access_log::{AccessLog, AccessLogLayer},
+    config::Config,
+    handlers,
+    metrics::MetricsLayer,
+    recent::RecentRequestsLayer,
+    reload::RuntimeConfig,
+    request_id::{RequestId, RequestIdLayer},
+    slow::SlowRequestLayer,
+    state::AppState,
+    statsd::{Statsd, StatsdLayer},
+};
+
+type LayerFn = Box<dyn Fn(Router) -> Router + Send>;
+
+pub struct AppBuilder {
+    state: Arc<AppState>,
+    routes: Router<Arc<AppState>>,
+    admin_routes: Router<Arc<AppState>>,
+    layers: Vec<LayerFn>,
+    static_files: bool,
+    access_log: Option<Arc<AccessLog>>,
+    statsd: Option<Arc<Statsd>>,
+    #[cfg(feature = "otel")]
+    otel: Option<Arc<crate::otel::Telemetry>>,
+}
+
+// 使用给定配置构建完整应用（等价于 AppBuilder::new(config).build()）
+pub fn build_app(config: Config) -> Router {
+    AppBuilder::new(config).build()
+}
+
+impl AppBuilder {
+    pub fn new(config: Config) -> Self {
+        Self::with_runtime_config(Arc::new(RuntimeConfig::new(config, None)))
+    }
+
+    // 共享运行时配置（例如与配置文件热加载任务共用同一份配置）
+    pub fn with_runtime_config(config: Arc<RuntimeConfig>) -> Self {
+        Self {
+            state: Arc::new(AppState::new(config)),
+            routes: Router::new(),
+            admin_routes: Router::new(),
+            layers: Vec::new(),
+            static_files: true,
+            access_log: None,
+            statsd: None,
+            #[cfg(feature = "otel")]
+            otel: None,
+        }
+    }
+
+    pub fn state(&self) -> Arc<AppState> {
+        self.state.clone()
+    }
+
+    // 追加自定义路由，处理器可通过 State<Arc<AppState>> 访问应用状态
+    pub fn route(mut self, path: &str, method_router: MethodRouter<Arc<AppState>>) -> Self {
+        self.routes = self.routes.route(path, method_router);
+        self
+    }
+
+    // 合并一组自定义路由
+    pub fn merge(mut self, router: Router<Arc<AppState>>) -> Self {
+        self.routes = self.routes.merge(router);
+        self
+    }
+
+    // 追加自定义管理路由：拆分监听时只出现在管理监听上
+    pub fn admin_route(mut self, path: &str, method_router: MethodRouter<Arc<AppState>>) -> Self {
+        self.admin_routes = self.admin_routes.route(path, method_router);
+        self
+    }
+
+    // 追加自定义中间件：按调用顺序包裹在内置中间件（CORS/超时/追踪）之外
+    pub fn layer<L>(mut self, layer: L) -> Self
+    where
+        L: Layer<Route> + Clone + Send + 'static,
+        L::Service: Service<Request> + Clone + Send + 'static,
+        <L::Service as Service<Request>>::Response: IntoResponse + 'static,
+        <L::Service as Service<Request>>::Error: Into<Infallible> + 'static,
+        <L::Service as Service<Request>>::Future: Send + 'static,
+    {
+        self.layers
+            .push(Box::new(move |router| router.layer(layer.clone())));
+        self
+    }
+
+    // 是否以 static_dir 作为兜底静态文件服务（默认开启）
+    pub fn static_files(mut self, enabled: bool) -> Self {
+        self.static_files = enabled;
+        self
+    }
+
+    // 记录访问日志（见 access_log 模块）
+    pub fn access_log(mut self, log: Arc<AccessLog>) -> Self {
+        self.access_log = Some(log);
+        self
+    }
+
+    // 推送指标到 StatsD / DogStatsD agent（见 statsd 模块）
+    pub fn statsd(mut self, statsd: Arc<Statsd>) -> Self {
+        self.statsd = Some(statsd);
+        self
+    }
+
+    // 导出请求 span 到 OpenTelemetry collector（otel 特性，见 otel 模块）
+    #[cfg(feature = "otel")]
+    pub fn otel(mut self, telemetry: Arc<crate::otel::Telemetry>) -> Self {
+        self.otel = Some(telemetry);
+        self
+    }
+
+    // 公共路由与管理路由合并为一个 Router（未配置管理监听时使用）
+    // 由 server::serve 服务时，SSE 推送在其优雅关闭开始时结束；以其他方式（如 axum::serve）服务时保持到连接断开
+    pub fn build(self) -> Router {
+        let (public, admin) = self.routes(false);
+        self.finish(public.merge(admin))
+    }
+
+    // 拆分为 (公共 Router, 管理 Router)，两者共享同一份 AppState 与中间件
+    pub fn build_split(self) -> (Router, Router) {
+        let (public, admin) = self.routes(true);
+        (self.finish(public), self.finish(admin))
+    }
+
+    // split：管理路由单独监听；否则修改日志级别（PUT / DELETE /admin/log-level）需 admin.allow_log_level_changes 开启
+    fn routes(&self, split: bool) -> (Router<Arc<AppState>>, Router<Arc<AppState>>) {
+        let public = Router::new()
+            .route("/", get(handlers::root))
+            .route("/sum", get(handlers::sum))
+            .route("/echo", post(handlers::echo))
+            .route("/parallel", get(handlers::parallel))
+            .merge(self.routes.clone());
+        let public = if self.static_files {
+            let dir = self.state.config.read(|c| c.static_dir.clone());
+            public.fallback_service(ServeDir::new(dir))
+        } else {
+            public
+        };
+        let log_level = get(handlers::get_log_level);
+        let log_level = if split || self.state.config.read(|c| c.admin.allow_log_level_changes) {
+            log_level
+                .put(handlers::put_log_level)
+                .delete(handlers::delete_log_level)
+        } else {
+            log_level
+        };
+        let admin = Router::new()
+            .route("/health", get(handlers::health))
+            .route("/metrics", get(handlers::metrics))
+            .route("/metrics/history", get(handlers::metrics_history))
+            .route("/metrics/stream", get(handlers::metrics_stream))
+            .route("/admin/config", get(handlers::admin_config))
+            .route("/debug/requests", get(handlers::debug_requests))
+            .route(
+                "/debug/requests/stream",
+                get(handlers::debug_requests_stream),
+            )
+            .route("/admin/log-level", log_level)
+            .merge(self.admin_routes.clone());
+        (public, admin)
+    }
+
+    // 路由与中间件说明：
+    // - CorsLayer：允许的来源取自 middleware.cors_origins（含 "*" 时放开），每次请求读取以支持热加载
+    // - TimeoutLayer：middleware.timeout_secs 秒内未完成的请求返回 408（0 表示不限制）
+    // - TraceLayer：为每个请求生成 span（method、path、request_id 字段），响应时以 INFO 级别输出 status 与 latency_ms
+    //   （默认日志级别下即可看到每个请求；middleware.trace 控制）
+    // - SlowRequestLayer：超过 slow_requests 阈值（按路由，可热加载）的请求输出 WARN 日志（含 span 树与耗时）并计数
+    // - OtelLayer（otel 特性）：解析 traceparent 生成服务端 span 并导出，trace_id 同时记入 TraceLayer 的 span
+    // - RecentRequestsLayer：记录最近完成的请求（metrics.recent_requests 为 0 时不挂载）
+    // - StatsdLayer：配置了 StatsD 推送时，按路由记录每个请求的耗时样本
+    // - MetricsLayer：按路由模板统计请求数、状态、在途数与耗时（含静态文件兜底与超时 408）
+    // - AccessLogLayer：配置了访问日志时，每个请求写一行（含请求 ID 与对端地址）
+    // - RequestIdLayer：最外层，确定请求 ID 并写入响应头 x-request-id，内层的 span 与错误体均可读取
+    fn finish(&self, routes: Router<Arc<AppState>>) -> Router {
+        let runtime = self.state.config.clone();
+        let cfg = runtime.snapshot();
+
+        let cors =
+            CorsLayer::permissive().allow_origin(AllowOrigin::predicate(move |origin, _| {
+                runtime.read(|c| {
+                    let origins = &c.middleware.cors_origins;
+                    origins
+                        .iter()
+                        .any(|o| o == "*" || o.as_bytes() == origin.as_bytes())
+                })
+            }));
+
+        let app = routes.with_state(self.state.clone()).layer(cors);
+        let app = match cfg.middleware.timeout_secs {
+            0 => app,
+            secs => app.layer(TimeoutLayer::with_status_code(
+                StatusCode::REQUEST_TIMEOUT,
+                Duration::from_secs(secs),
+            )),
+        };
+        let app = if cfg.middleware.trace {
+            app.layer(
+                TraceLayer::new_for_http()
+                    .make_span_with(|req: &Request| {
+                        let id = req.extensions().get::<RequestId>();
+                        let span = tracing::info_span!(
+                            "request",
+                            method = %req.method(),
+                            path = %req.uri().path(),
+                            version = ?req.version(),
+                            request_id = %id.map_or("", RequestId::as_str),
+                            trace_id = tracing::field::Empty,
+                        );
+                        #[cfg(feature = "otel")]
+                        if let Some(cx) = req.extensions().get::<crate::otel::SpanContext>() {
+                            span.record("trace_id", cx.trace_id_hex());
+                        }
+                        span
+                    })
+                    .on_response(|res: &Response, latency: Duration, _: &Span| {
+                        tracing::info!(
+                            status = res.status().as_u16(),
+                            latency_ms = latency.as_secs_f64() * 1000.0,
+                            "finished processing request"
+                        )
+                    }),
+            )
+        } else {
+            app
+        };
+        let app = app.layer(SlowRequestLayer::new(
+            self.state.config.clone(),
+            self.state.metrics.clone(),
+        ));
+        #[cfg(feature = "otel")]
+        let app = match &self.otel {
+            Some(telemetry) => app.layer(crate::otel::OtelLayer::new(telemetry.clone())),
+            None => app,
+        };
+        let app = match cfg.metrics.recent_requests {
+            0 => app,
+            _ => app.layer(RecentRequestsLayer::new(self.state.recent.clone())),
+        };
+        let app = match &self.statsd {
+            Some(statsd) => app.layer(StatsdLayer::new(statsd.clone())),
+            None => app,
+        };
+        let app = app.layer(MetricsLayer::new(self.state.metrics.clone()));
+        let app = match &self.access_log {
+            Some(log) => app.layer(AccessLogLayer::new(log.clone())),
+            None => app,
+        };
+        let app = app.layer(RequestIdLayer);
+        self.layers.iter().fold(app, |app, layer| layer(app))
+    }
+}
@@ -0,0 +1,1270 @@
+// 服务配置：监听地址（TCP / Unix socket、公共与管理监听）、HTTP 连接参数、后台运行、静态目录、日志级别与格式、访问日志、中间件、指标、/parallel 限制、热加载
+// 优先级（由低到高）：默认值 < 配置文件 rustdemo.toml < 环境变量 RUSTDEMO_* < 命令行参数
+use std::{
+    collections::BTreeMap,
+    fmt,
+    net::{IpAddr, Ipv4Addr, SocketAddr},
+    path::{Path, PathBuf},
+    str::FromStr,
+};
+
+use serde::{Serialize, Serializer};
+use thiserror::Error;
+
+use crate::toml;
+
+// 未显式指定 --config 时，若当前目录存在该文件则自动加载
+pub const DEFAULT_CONFIG_FILE: &str = "rustdemo.toml";
+
+// 命令行帮助文本（--help 输出）
+pub const USAGE: &str = "\
+Usage: rustdemo [OPTIONS]
+       rustdemo healthcheck [OPTIONS]
+
+Commands:
+  healthcheck          请求已配置地址上的 /health（配置了管理监听时使用管理监听），健康时退出码为 0，否则为 1
+
+Options:
+  --config <FILE>      配置文件 [env: RUSTDEMO_CONFIG] [default: ./rustdemo.toml（若存在）]
+  --bind <IP>          监听地址 [env: RUSTDEMO_BIND] [default: 127.0.0.1]
+  --port <PORT>        监听端口 [env: RUSTDEMO_PORT] [default: 3000]
+  --listen <ADDRS>     公共监听列表，逗号分隔的 IP:PORT 或 unix:PATH，设置后取代 --bind/--port
+                       [env: RUSTDEMO_LISTEN]
+  --admin-listen <ADDRS>
+                       管理监听列表（/metrics、/health、/admin/*），设置后这些端点不再出现在公共监听上
+                       [env: RUSTDEMO_ADMIN_LISTEN]
+  --drain-timeout <SECS>
+                       优雅关闭时等待在途请求的最长秒数，0 表示不限制 [env: RUSTDEMO_DRAIN_TIMEOUT] [default: 30]
+  --max-connections <N>
+                       同时服务的连接数上限（所有公共与管理监听合计），0 表示不限制
+                       [env: RUSTDEMO_MAX_CONNECTIONS] [default: 0]
+  --daemon             后台运行（仅 Unix）：脱离终端，监听就绪后启动命令返回
+  --pidfile <FILE>     写入进程 PID；文件已被运行中的实例占用时拒绝启动 [env: RUSTDEMO_PIDFILE]
+  --log-file <FILE>    后台运行时日志追加写入的文件 [env: RUSTDEMO_LOG_FILE] [default: 丢弃]
+  --access-log <FILE>  访问日志文件（按大小与日期轮转，见 [access_log]） [env: RUSTDEMO_ACCESS_LOG] [default: 不记录]
+  --access-log-format <FORMAT>
+                       访问日志格式：common、combined 或 json [env: RUSTDEMO_ACCESS_LOG_FORMAT] [default: combined]
+  --metrics-state-file <FILE>
+                       累计计数持久化文件，重启后恢复（见 [metrics]） [env: RUSTDEMO_METRICS_STATE_FILE] [default: 不持久化]
+  --static-dir <DIR>   静态文件目录 [env: RUSTDEMO_STATIC_DIR] [default: 当前目录]
+  --log-level <LEVEL>  日志过滤规则（EnvFilter 语法，如 info,tower_http=debug）
+                       [env: RUSTDEMO_LOG_LEVEL，其次 RUST_LOG] [default: info]
+  --log-format <FORMAT>
+                       日志格式：text 或 json（每个事件一行 JSON） [env: RUSTDEMO_LOG_FORMAT] [default: text]
+  --slow-threshold-ms <MS>
+                       慢请求阈值（毫秒，0 表示不检测；按路由覆盖见 [slow_requests]） [env: RUSTDEMO_SLOW_THRESHOLD_MS] [default: 1000]
+  --statsd <ADDR>      StatsD / DogStatsD agent 地址，HOST:PORT（UDP）或 unix:PATH（见 [statsd]）
+                       [env: RUSTDEMO_STATSD_ADDRESS] [default: 不推送]
+  -h, --help           打印帮助信息
+";
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct Config {
+    pub server: ServerConfig,
+    pub http: HttpConfig,
+    pub static_dir: PathBuf,
+    pub log_level: String,
+    pub log_format: LogFormat,
+    pub middleware: MiddlewareConfig,
+    pub metrics: MetricsConfig,
+    pub parallel: ParallelConfig,
+    pub reload: ReloadConfig,
+    pub admin: AdminConfig,
+    pub process: ProcessConfig,
+    pub access_log: AccessLogConfig,
+    pub slow_requests: SlowRequestsConfig,
+    pub statsd: StatsdConfig,
+}
+
+// 公共监听：默认仅 bind:port；listen 非空时取代 bind/port（可同时监听多个 TCP 地址与 Unix socket）
+// unix_socket_mode：Unix socket 文件权限（八进制，如 660）
+// drain_timeout_secs：优雅关闭时等待在途请求的最长秒数，超时后强制断开（0 表示不限制）
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct ServerConfig {
+    pub bind: IpAddr,
+    pub port: u16,
+    pub listen: Vec<ListenAddr>,
+    #[serde(serialize_with = "serialize_mode")]
+    pub unix_socket_mode: u32,
+    pub drain_timeout_secs: u64,
+}
+
+// HTTP/1.1 连接参数：
+// - keep_alive：是否复用连接
+// - header_read_timeout_secs：读取完整请求头的期限，超时关闭连接（防慢速攻击；0 表示不限制）
+// - max_headers：请求头数量上限（至少为 1），超出返回 431
+// - max_header_bytes：读缓冲上限（即请求行加请求头的最大字节数），超出返回 431，不小于 8192
+// - max_connections：同时服务的连接数上限（0 表示不限制），达到上限后暂停 accept，新连接在内核队列中等待
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct HttpConfig {
+    pub keep_alive: bool,
+    pub header_read_timeout_secs: u64,
+    pub max_headers: usize,
+    pub max_header_bytes: usize,
+    pub max_connections: usize,
+}
+
+// hyper 读缓冲的最小值
+pub const MIN_HEADER_BYTES: usize = 8192;
+
+// parallel.max_tasks 的上限（单个 /parallel 请求最多创建的任务数）
+pub const MAX_PARALLEL_TASKS: usize = 1024;
+
+// 管理监听：非空时 /metrics、/health、/admin/* 仅在这些地址上提供
+// - allow_log_level_changes：未配置管理监听时是否在公共监听上开放 PUT / DELETE /admin/log-level（默认关闭）
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct AdminConfig {
+    pub listen: Vec<ListenAddr>,
+    pub allow_log_level_changes: bool,
+}
+
+// 进程管理：后台运行、PID 文件、后台运行时的日志文件（未设置时丢弃输出）
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct ProcessConfig {
+    pub daemon: bool,
+    pub pidfile: Option<PathBuf>,
+    pub log_file: Option<PathBuf>,
+}
+
+// 访问日志（与 tracing 日志分开写入）：
+// - path：日志文件，未设置时不记录
+// - format：common（CLF）、combined（CLF 加 Referer 与 User-Agent）或 json（每个请求一行 JSON）
+// - max_size_bytes：文件超过该大小时轮转（0 表示不按大小轮转）
+// - daily：日期（UTC）变化时轮转
+// - keep：保留的已轮转文件数（path.1 为最近一份）
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct AccessLogConfig {
+    pub path: Option<PathBuf>,
+    pub format: AccessLogFormat,
+    pub max_size_bytes: u64,
+    pub daily: bool,
+    pub keep: usize,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum AccessLogFormat {
+    Common,
+    Combined,
+    Json,
+}
+
+impl FromStr for AccessLogFormat {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "common" => Ok(AccessLogFormat::Common),
+            "combined" => Ok(AccessLogFormat::Combined),
+            "json" => Ok(AccessLogFormat::Json),
+            _ => Err(format!("未知访问日志格式: {s}")),
+        }
+    }
+}
+
+impl fmt::Display for AccessLogFormat {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            AccessLogFormat::Common => "common",
+            AccessLogFormat::Combined => "combined",
+            AccessLogFormat::Json => "json",
+        })
+    }
+}
+
+impl Serialize for AccessLogFormat {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.collect_str(self)
+    }
+}
+
+// 监听地址："127.0.0.1:3000"、"[::1]:3000" 或 "unix:/run/rustdemo.sock"
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ListenAddr {
+    Tcp(SocketAddr),
+    Unix(PathBuf),
+}
+
+impl FromStr for ListenAddr {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.strip_prefix("unix:") {
+            Some("") => Err("unix socket 路径不能为空".into()),
+            Some(path) => Ok(ListenAddr::Unix(PathBuf::from(path))),
+            None => s
+                .parse()
+                .map(ListenAddr::Tcp)
+                .map_err(|_| format!("无法解析监听地址: {s}")),
+        }
+    }
+}
+
+impl fmt::Display for ListenAddr {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ListenAddr::Tcp(addr) => write!(f, "{addr}"),
+            ListenAddr::Unix(path) => write!(f, "unix:{}", path.display()),
+        }
+    }
+}
+
+impl Serialize for ListenAddr {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.collect_str(self)
+    }
+}
+
+fn serialize_mode<S: Serializer>(mode: &u32, serializer: S) -> Result<S::Ok, S::Error> {
+    serializer.collect_str(&format_args!("{mode:o}"))
+}
+
+// 日志格式：text 为人类可读文本，json 为每个事件一行 JSON 对象（见 logging 模块）
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum LogFormat {
+    Text,
+    Json,
+}
+
+impl FromStr for LogFormat {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "text" => Ok(LogFormat::Text),
+            "json" => Ok(LogFormat::Json),
+            _ => Err(format!("未知日志格式: {s}")),
+        }
+    }
+}
+
+impl fmt::Display for LogFormat {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            LogFormat::Text => "text",
+            LogFormat::Json => "json",
+        })
+    }
+}
+
+impl Serialize for LogFormat {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.collect_str(self)
+    }
+}
+
+// 中间件：CORS 允许的来源（含 "*" 表示放开）、请求追踪开关、请求超时（0 表示不限制）
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct MiddlewareConfig {
+    pub cors_origins: Vec<String>,
+    pub trace: bool,
+    pub timeout_secs: u64,
+}
+
+// 指标：
+// - latency_buckets：Prometheus 耗时直方图的桶上界（秒，严格递增）
+// - window_secs：/metrics 中耗时分位数（p50/p90/p99/max）统计的滑动窗口长度
+// - recent_requests：/debug/requests 保留的最近请求条数（0 表示不记录）
+// - state_file：累计计数的持久化文件，启动时恢复，运行期间与优雅关闭时写入（未设置时不持久化）
+// - state_interval_secs：运行期间写入 state_file 的间隔（0 表示只在关闭时写入）
+// - stream_interval_ms：/metrics/stream 推送增量的间隔（可热加载）
+// - stream_max_subscribers：/metrics/stream 同时连接的订阅者上限（可热加载，0 表示关闭该端点）
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct MetricsConfig {
+    pub latency_buckets: Vec<f64>,
+    pub window_secs: u64,
+    pub recent_requests: usize,
+    pub state_file: Option<PathBuf>,
+    pub state_interval_secs: u64,
+    pub stream_interval_ms: u64,
+    pub stream_max_subscribers: usize,
+}
+
+// /parallel：未指定 n 时的任务数与任务数上限
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct ParallelConfig {
+    pub default_tasks: usize,
+    pub max_tasks: usize,
+}
+
+// 慢请求检测（热加载）：
+// - threshold_ms：耗时超过该值的请求输出一条 WARN 日志（含 span 树与各段耗时）并计入 rustdemo_slow_requests_total；0 表示不检测
+// - routes：按路由模板覆盖阈值，写作 "路由=毫秒"，如 ["/parallel=2000", "/health=0"]
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct SlowRequestsConfig {
+    pub threshold_ms: u64,
+    pub routes: BTreeMap<String, u64>,
+}
+
+impl SlowRequestsConfig {
+    // 路由模板对应的阈值；不检测时为 None
+    pub fn threshold(&self, route: &str) -> Option<std::time::Duration> {
+        let ms = self.routes.get(route).copied().unwrap_or(self.threshold_ms);
+        (ms > 0).then(|| std::time::Duration::from_millis(ms))
+    }
+}
+
+// StatsD 推送（见 statsd 模块）：
+// - address：agent 地址，未设置时不推送
+// - prefix：指标名前缀（以 "." 与指标名相连，空字符串表示不加前缀）
+// - dogstatsd：以 DogStatsD 标签发送路由、方法与状态码；关闭时编入指标名，tags 被忽略
+// - tags：附加到每个指标的固定标签，如 ["env:prod", "region:eu"]
+// - flush_interval_ms：发送间隔
+// - max_packet_bytes：单个数据报的最大字节数（多行指标合并发送）
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct StatsdConfig {
+    pub address: Option<StatsdAddr>,
+    pub prefix: String,
+    pub dogstatsd: bool,
+    pub tags: Vec<String>,
+    pub flush_interval_ms: u64,
+    pub max_packet_bytes: usize,
+}
+
+// StatsD agent 地址："HOST:PORT"（UDP，HOST 可为域名）或 "unix:PATH"（Unix datagram socket）
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum StatsdAddr {
+    Udp(String),
+    Unix(PathBuf),
+}
+
+impl FromStr for StatsdAddr {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.strip_prefix("unix:") {
+            Some("") => Err("unix socket 路径不能为空".into()),
+            Some(path) => Ok(StatsdAddr::Unix(PathBuf::from(path))),
+            None => match s.rsplit_once(':') {
+                Some((host, port)) if !host.is_empty() && port.parse::<u16>().is_ok() => {
+                    Ok(StatsdAddr::Udp(s.to_string()))
+                }
+                _ => Err(format!(
+                    "无法解析 StatsD 地址: {s}（应为 HOST:PORT 或 unix:PATH）"
+                )),
+            },
+        }
+    }
+}
+
+impl fmt::Display for StatsdAddr {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            StatsdAddr::Udp(addr) => write!(f, "{addr}"),
+            StatsdAddr::Unix(path) => write!(f, "unix:{}", path.display()),
+        }
+    }
+}
+
+impl Serialize for StatsdAddr {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.collect_str(self)
+    }
+}
+
+// 配置文件热加载：轮询间隔（秒）
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct ReloadConfig {
+    pub interval_secs: u64,
+}
+
+// 配置错误：参数未知、缺少取值、取值非法、文件读取/解析失败；Help 表示用户请求打印帮助
+#[derive(Error, Debug)]
+pub enum ConfigError {
+    #[error("未知参数: {0}")]
+    UnknownFlag(String),
+    #[error("参数 {0} 缺少取值")]
+    MissingValue(String),
+    #[error("{key} 的取值无效: {value}")]
+    InvalidValue { key: String, value: String },
+    #[error("无法读取配置文件 {path:?}: {source}")]
+    Io {
+        path: PathBuf,
+        source: std::io::Error,
+    },
+    #[error("配置文件 {path:?} 解析失败: {source}")]
+    Parse {
+        path: PathBuf,
+        source: toml::ParseError,
+    },
+    #[error("help requested")]
+    Help,
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        Self {
+            server: ServerConfig {
+                bind: IpAddr::V4(Ipv4Addr::LOCALHOST),
+                port: 3000,
+                listen: Vec::new(),
+                unix_socket_mode: 0o660,
+                drain_timeout_secs: 30,
+            },
+            http: HttpConfig {
+                keep_alive: true,
+                header_read_timeout_secs: 30,
+                max_headers: 100,
+                max_header_bytes: 64 * 1024,
+                max_connections: 0,
+            },
+            static_dir: std::env::current_dir().unwrap_or_default(),
+            log_level: "info".into(),
+            log_format: LogFormat::Text,
+            middleware: MiddlewareConfig {
+                cors_origins: vec!["*".into()],
+                trace: true,
+                timeout_secs: 30,
+            },
+            metrics: MetricsConfig {
+                latency_buckets: vec![
+                    0.005, 0.01, 0.025, 0.05, 0.1, 0.25, 0.5, 1.0, 2.5, 5.0, 10.0,
+                ],
+                window_secs: 60,
+                recent_requests: 100,
+                state_file: None,
+                state_interval_secs: 60,
+                stream_interval_ms: 1000,
+                stream_max_subscribers: 8,
+            },
+            parallel: ParallelConfig {
+                default_tasks: 5,
+                max_tasks: 32,
+            },
+            reload: ReloadConfig { interval_secs: 2 },
+            admin: AdminConfig {
+                listen: Vec::new(),
+                allow_log_level_changes: false,
+            },
+            process: ProcessConfig {
+                daemon: false,
+                pidfile: None,
+                log_file: None,
+            },
+            access_log: AccessLogConfig {
+                path: None,
+                format: AccessLogFormat::Combined,
+                max_size_bytes: 100 * 1024 * 1024,
+                daily: true,
+                keep: 7,
+            },
+            slow_requests: SlowRequestsConfig {
+                threshold_ms: 1000,
+                routes: BTreeMap::new(),
+            },
+            statsd: StatsdConfig {
+                address: None,
+                prefix: "rustdemo".into(),
+                dogstatsd: false,
+                tags: Vec::new(),
+                flush_interval_ms: 10_000,
+                max_packet_bytes: 1432,
+            },
+        }
+    }
+}
+
+// 环境变量与配置键的对应关系（按顺序应用，同一配置键以靠后的为准）
+const ENV_KEYS: &[(&str, &str)] = &[
+    ("RUSTDEMO_BIND", "server.bind"),
+    ("RUSTDEMO_PORT", "server.port"),
+    ("RUSTDEMO_LISTEN", "server.listen"),
+    ("RUSTDEMO_ADMIN_LISTEN", "admin.listen"),
+    ("RUSTDEMO_DRAIN_TIMEOUT", "server.drain_timeout_secs"),
+    ("RUSTDEMO_MAX_CONNECTIONS", "http.max_connections"),
+    ("RUSTDEMO_PIDFILE", "process.pidfile"),
+    ("RUSTDEMO_LOG_FILE", "process.log_file"),
+    ("RUSTDEMO_ACCESS_LOG", "access_log.path"),
+    ("RUSTDEMO_ACCESS_LOG_FORMAT", "access_log.format"),
+    ("RUSTDEMO_METRICS_STATE_FILE", "metrics.state_file"),
+    (
+        "RUSTDEMO_METRICS_STREAM_INTERVAL_MS",
+        "metrics.stream_interval_ms",
+    ),
+    ("RUSTDEMO_STATIC_DIR", "static_dir"),
+    ("RUST_LOG", "log_level"),
+    ("RUSTDEMO_LOG_LEVEL", "log_level"),
+    ("RUSTDEMO_LOG_FORMAT", "log_format"),
+    ("RUSTDEMO_SLOW_THRESHOLD_MS", "slow_requests.threshold_ms"),
+    ("RUSTDEMO_SLOW_ROUTES", "slow_requests.routes"),
+    ("RUSTDEMO_STATSD_ADDRESS", "statsd.address"),
+];
+
+// 命令行参数与配置键的对应关系（--config 由 ConfigLoader 单独处理）
+const FLAG_KEYS: &[(&str, &str)] = &[
+    ("bind", "server.bind"),
+    ("port", "server.port"),
+    ("listen", "server.listen"),
+    ("admin-listen", "admin.listen"),
+    ("drain-timeout", "server.drain_timeout_secs"),
+    ("max-connections", "http.max_connections"),
+    ("daemon", "process.daemon"),
+    ("pidfile", "process.pidfile"),
+    ("log-file", "process.log_file"),
+    ("access-log", "access_log.path"),
+    ("access-log-format", "access_log.format"),
+    ("metrics-state-file", "metrics.state_file"),
+    ("static-dir", "static_dir"),
+    ("log-level", "log_level"),
+    ("log-format", "log_format"),
+    ("slow-threshold-ms", "slow_requests.threshold_ms"),
+    ("statsd", "statsd.address"),
+];
+
+// 不带取值的开关参数（`--daemon` 等价于 `--daemon=true`）
+const SWITCHES: &[&str] = &["daemon"];
+
+// 需要重启才能生效的配置键（其余配置可热加载）
+const RESTART_KEYS: &[&str] = &[
+    "server.bind",
+    "server.port",
+    "server.listen",
+    "server.unix_socket_mode",
+    "server.drain_timeout_secs",
+    "http.keep_alive",
+    "http.header_read_timeout_secs",
+    "http.max_headers",
+    "http.max_header_bytes",
+    "http.max_connections",
+    "admin.listen",
+    "admin.allow_log_level_changes",
+    "process.daemon",
+    "process.pidfile",
+    "process.log_file",
+    "access_log.path",
+    "access_log.format",
+    "access_log.max_size_bytes",
+    "access_log.daily",
+    "access_log.keep",
+    "static_dir",
+    "log_format",
+    "middleware.trace",
+    "middleware.timeout_secs",
+    "metrics.latency_buckets",
+    "metrics.window_secs",
+    "metrics.recent_requests",
+    "metrics.state_file",
+    "metrics.state_interval_secs",
+    "statsd.address",
+    "statsd.prefix",
+    "statsd.dogstatsd",
+    "statsd.tags",
+    "statsd.flush_interval_ms",
+    "statsd.max_packet_bytes",
+];
+
+impl Config {
+    pub fn addr(&self) -> SocketAddr {
+        SocketAddr::new(self.server.bind, self.server.port)
+    }
+
+    // 优雅关闭期限（0 表示不限制）
+    pub fn drain_timeout(&self) -> Option<std::time::Duration> {
+        match self.server.drain_timeout_secs {
+            0 => None,
+            secs => Some(std::time::Duration::from_secs(secs)),
+        }
+    }
+
+    // 实际生效的公共监听地址列表
+    pub fn listeners(&self) -> Vec<ListenAddr> {
+        if self.server.listen.is_empty() {
+            vec![ListenAddr::Tcp(self.addr())]
+        } else {
+            self.server.listen.clone()
+        }
+    }
+
+    // 配置文件覆盖：键为 "表名.键名"，未知键视为错误以便及早发现拼写问题
+    pub fn apply_file(&mut self, doc: &str, path: &Path) -> Result<(), ConfigError> {
+        let map = toml::parse(doc).map_err(|source| ConfigError::Parse {
+            path: path.to_path_buf(),
+            source,
+        })?;
+        for (key, value) in map {
+            self.set(
+                &format!("{}: {key}", path.display()),
+                &key,
+                &value.to_plain_string(),
+            )?;
+        }
+        Ok(())
+    }
+
+    // 环境变量覆盖：通过闭包读取，便于测试时注入
+    pub fn apply_env(&mut self, var: impl Fn(&str) -> Option<String>) -> Result<(), ConfigError> {
+        for (env, key) in ENV_KEYS {
+            if let Some(v) = var(env) {
+                self.set(env, key, &v)?;
+            }
+        }
+        Ok(())
+    }
+
+    // 命令行覆盖：支持 `--port 3000` 与 `--port=3000` 两种写法
+    pub fn apply_args<I>(&mut self, args: I) -> Result<(), ConfigError>
+    where
+        I: IntoIterator,
+        I::Item: Into<String>,
+    {
+        for (flag, value) in parse_flags(args)? {
+            if flag == "config" {
+                continue;
+            }
+            let key = FLAG_KEYS
+                .iter()
+                .find(|(f, _)| *f == flag)
+                .map(|(_, k)| *k)
+                .ok_or_else(|| ConfigError::UnknownFlag(format!("--{flag}")))?;
+            self.set(&format!("--{flag}"), key, &value)?;
+        }
+        Ok(())
+    }
+
+    // 与新配置相比，哪些需要重启的配置项发生了变化
+    pub fn restart_required_changes(&self, new: &Config) -> Vec<&'static str> {
+        RESTART_KEYS
+            .iter()
+            .copied()
+            .filter(|key| match *key {
+                "server.bind" => self.server.bind != new.server.bind,
+                "server.port" => self.server.port != new.server.port,
+                "server.listen" => self.server.listen != new.server.listen,
+                "server.unix_socket_mode" => {
+                    self.server.unix_socket_mode != new.server.unix_socket_mode
+                }
+                "server.drain_timeout_secs" => {
+                    self.server.drain_timeout_secs != new.server.drain_timeout_secs
+                }
+                "http.keep_alive" => self.http.keep_alive != new.http.keep_alive,
+                "http.header_read_timeout_secs" => {
+                    self.http.header_read_timeout_secs != new.http.header_read_timeout_secs
+                }
+                "http.max_headers" => self.http.max_headers != new.http.max_headers,
+                "http.max_header_bytes" => self.http.max_header_bytes != new.http.max_header_bytes,
+                "http.max_connections" => self.http.max_connections != new.http.max_connections,
+                "admin.listen" => self.admin.listen != new.admin.listen,
+                "admin.allow_log_level_changes" => {
+                    self.admin.allow_log_level_changes != new.admin.allow_log_level_changes
+                }
+                "process.daemon" => self.process.daemon != new.process.daemon,
+                "process.pidfile" => self.process.pidfile != new.process.pidfile,
+                "process.log_file" => self.process.log_file != new.process.log_file,
+                "access_log.path" => self.access_log.path != new.access_log.path,
+                "access_log.format" => self.access_log.format != new.access_log.format,
+                "access_log.max_size_bytes" => {
+                    self.access_log.max_size_bytes != new.access_log.max_size_bytes
+                }
+                "access_log.daily" => self.access_log.daily != new.access_log.daily,
+                "access_log.keep" => self.access_log.keep != new.access_log.keep,
+                "static_dir" => self.static_dir != new.static_dir,
+                "log_format" => self.log_format != new.log_format,
+                "middleware.trace" => self.middleware.trace != new.middleware.trace,
+                "middleware.timeout_secs" => {
+                    self.middleware.timeout_secs != new.middleware.timeout_secs
+                }
+                "metrics.latency_buckets" => {
+                    self.metrics.latency_buckets != new.metrics.latency_buckets
+                }
+                "metrics.window_secs" => self.metrics.window_secs != new.metrics.window_secs,
+                "metrics.recent_requests" => {
+                    self.metrics.recent_requests != new.metrics.recent_requests
+                }
+                "metrics.state_file" => self.metrics.state_file != new.metrics.state_file,
+                "metrics.state_interval_secs" => {
+                    self.metrics.state_interval_secs != new.metrics.state_interval_secs
+                }
+                "statsd.address" => self.statsd.address != new.statsd.address,
+                "statsd.prefix" => self.statsd.prefix != new.statsd.prefix,
+                "statsd.dogstatsd" => self.statsd.dogstatsd != new.statsd.dogstatsd,
+                "statsd.tags" => self.statsd.tags != new.statsd.tags,
+                "statsd.flush_interval_ms" => {
+                    self.statsd.flush_interval_ms != new.statsd.flush_interval_ms
+                }
+                "statsd.max_packet_bytes" => {
+                    self.statsd.max_packet_bytes != new.statsd.max_packet_bytes
+                }
+                _ => false,
+            })
+            .collect()
+    }
+
+    // 应用可安全热加载的配置项（日志级别、CORS 来源、/parallel 限制、慢请求阈值、指标推送、轮询间隔）
+    pub fn apply_reloadable(&mut self, new: &Config) {
+        self.log_level = new.log_level.clone();
+        self.middleware.cors_origins = new.middleware.cors_origins.clone();
+        self.metrics.stream_interval_ms = new.metrics.stream_interval_ms;
+        self.metrics.stream_max_subscribers = new.metrics.stream_max_subscribers;
+        self.parallel = new.parallel.clone();
+        self.slow_requests = new.slow_requests.clone();
+        self.reload = new.reload.clone();
+    }
+
+    // 按配置键写入取值；source 用于错误信息（标明来自哪个文件、参数或环境变量）
+    fn set(&mut self, source: &str, key: &str, value: &str) -> Result<(), ConfigError> {
+        let invalid = || ConfigError::InvalidValue {
+            key: source.to_string(),
+            value: value.to_string(),
+        };
+        match key {
+            "server.bind" => self.server.bind = value.parse().map_err(|_| invalid())?,
+            "server.port" => self.server.port = value.parse().map_err(|_| invalid())?,
+            "server.listen" => self.server.listen = parse_listen(value).map_err(|_| invalid())?,
+            "server.unix_socket_mode" => {
+                self.server.unix_socket_mode =
+                    u32::from_str_radix(value, 8).map_err(|_| invalid())?
+            }
+            "server.drain_timeout_secs" => {
+                self.server.drain_timeout_secs = value.parse().map_err(|_| invalid())?
+            }
+            "http.keep_alive" => self.http.keep_alive = value.parse().map_err(|_| invalid())?,
+            "http.header_read_timeout_secs" => {
+                self.http.header_read_timeout_secs = value.parse().map_err(|_| invalid())?
+            }
+            "http.max_headers" => {
+                let n: usize = value.parse().map_err(|_| invalid())?;
+                if n == 0 {
+                    return Err(invalid());
+                }
+                self.http.max_headers = n;
+            }
+            "http.max_header_bytes" => {
+                let bytes: usize = value.parse().map_err(|_| invalid())?;
+                if bytes < MIN_HEADER_BYTES {
+                    return Err(invalid());
+                }
+                self.http.max_header_bytes = bytes;
+            }
+            "http.max_connections" => {
+                self.http.max_connections = value.parse().map_err(|_| invalid())?
+            }
+            "admin.listen" => self.admin.listen = parse_listen(value).map_err(|_| invalid())?,
+            "admin.allow_log_level_changes" => {
+                self.admin.allow_log_level_changes = value.parse().map_err(|_| invalid())?
+            }
+            "process.daemon" => self.process.daemon = value.parse().map_err(|_| invalid())?,
+            "process.pidfile" => self.process.pidfile = optional_path(value),
+            "process.log_file" => self.process.log_file = optional_path(value),
+            "access_log.path" => self.access_log.path = optional_path(value),
+            "access_log.format" => self.access_log.format = value.parse().map_err(|_| invalid())?,
+            "access_log.max_size_bytes" => {
+                self.access_log.max_size_bytes = value.parse().map_err(|_| invalid())?
+            }
+            "access_log.daily" => self.access_log.daily = value.parse().map_err(|_| invalid())?,
+            "access_log.keep" => self.access_log.keep = value.parse().map_err(|_| invalid())?,
+            "static_dir" => self.static_dir = PathBuf::from(value),
+            "log_level" => {
+                if value.trim().is_empty() {
+                    return Err(invalid());
+                }
+                self.log_level = value.to_string();
+            }
+            "log_format" => self.log_format = value.parse().map_err(|_| invalid())?,
+            "middleware.cors_origins" => {
+                self.middleware.cors_origins = value
+                    .split(',')
+                    .map(str::trim)
+                    .filter(|s| !s.is_empty())
+                    .map(String::from)
+                    .collect();
+            }
+            "middleware.trace" => self.middleware.trace = value.parse().map_err(|_| invalid())?,
+            "middleware.timeout_secs" => {
+                self.middleware.timeout_secs = value.parse().map_err(|_| invalid())?
+            }
+            "metrics.latency_buckets" => {
+                let buckets = value
+                    .split(',')
+                    .map(|s| s.trim().parse::<f64>())
+                    .collect::<Result<Vec<_>, _>>()
+                    .map_err(|_| invalid())?;
+                let valid = !buckets.is_empty()
+                    && buckets.iter().all(|b| b.is_finite() && *b > 0.0)
+                    && buckets.windows(2).all(|w| w[0] < w[1]);
+                if !valid {
+                    return Err(invalid());
+                }
+                self.metrics.latency_buckets = buckets;
+            }
+            "metrics.window_secs" => {
+                let secs: u64 = value.parse().map_err(|_| invalid())?;
+                if secs == 0 {
+                    return Err(invalid());
+                }
+                self.metrics.window_secs = secs;
+            }
+            "metrics.recent_requests" => {
+                self.metrics.recent_requests = value.parse().map_err(|_| invalid())?
+            }
+            "metrics.state_file" => self.metrics.state_file = optional_path(value),
+            "metrics.state_interval_secs" => {
+                self.metrics.state_interval_secs = value.parse().map_err(|_| invalid())?
+            }
+            "metrics.stream_interval_ms" => {
+                let ms: u64 = value.parse().map_err(|_| invalid())?;
+                if ms < 100 {
+                    return Err(invalid());
+                }
+                self.metrics.stream_interval_ms = ms;
+            }
+            "metrics.stream_max_subscribers" => {
+                self.metrics.stream_max_subscribers = value.parse().map_err(|_| invalid())?
+            }
+            "statsd.address" => {
+                self.statsd.address = match value {
+                    "" => None,
+                    addr => Some(addr.parse().map_err(|_| invalid())?),
+                }
+            }
+            "statsd.prefix" => self.statsd.prefix = value.trim_end_matches('.').to_string(),
+            "statsd.dogstatsd" => self.statsd.dogstatsd = value.parse().map_err(|_| invalid())?,
+            "statsd.tags" => {
+                self.statsd.tags = value
+                    .split(',')
+                    .map(str::trim)
+                    .filter(|s| !s.is_empty())
+                    .map(String::from)
+                    .collect();
+            }
+            "statsd.flush_interval_ms" => {
+                let ms: u64 = value.parse().map_err(|_| invalid())?;
+                if ms == 0 {
+                    return Err(invalid());
+                }
+                self.statsd.flush_interval_ms = ms;
+            }
+            "statsd.max_packet_bytes" => {
+                let bytes: usize = value.parse().map_err(|_| invalid())?;
+                if bytes < 64 {
+                    return Err(invalid());
+                }
+                self.statsd.max_packet_bytes = bytes;
+            }
+            "slow_requests.threshold_ms" => {
+                self.slow_requests.threshold_ms = value.parse().map_err(|_| invalid())?
+            }
+            "slow_requests.routes" => {
+                self.slow_requests.routes = parse_route_thresholds(value).ok_or_else(invalid)?
+            }
+            "parallel.default_tasks" => {
+                self.parallel.default_tasks = value.parse().map_err(|_| invalid())?
+            }
+            "parallel.max_tasks" => {
+                let n: usize = value.parse().map_err(|_| invalid())?;
+                if n == 0 || n > MAX_PARALLEL_TASKS {
+                    return Err(invalid());
+                }
+                self.parallel.max_tasks = n;
+            }
+            "reload.interval_secs" => {
+                let secs: u64 = value.parse().map_err(|_| invalid())?;
+                if secs == 0 {
+                    return Err(invalid());
+                }
+                self.reload.interval_secs = secs;
+            }
+            _ => return Err(ConfigError::UnknownFlag(source.to_string())),
+        }
+        Ok(())
+    }
+}
+
+// 逗号分隔的按路由阈值
+// "/parallel=2000,/health=0" -> {"/parallel": 2000, "/health": 0}
+fn parse_route_thresholds(value: &str) -> Option<BTreeMap<String, u64>> {
+    value
+        .split(',')
+        .map(str::trim)
+        .filter(|s| !s.is_empty())
+        .map(|item| {
+            let (route, ms) = item.rsplit_once('=')?;
+            Some((route.trim().to_string(), ms.trim().parse().ok()?))
+        })
+        .collect()
+}
+
+// 逗号分隔的监听地址列表
+fn parse_listen(value: &str) -> Result<Vec<ListenAddr>, String> {
+    value
+        .split(',')
+        .map(str::trim)
+        .filter(|s| !s.is_empty())
+        .map(str::parse)
+        .collect()
+}
+
+// 空字符串表示不设置
+fn optional_path(value: &str) -> Option<PathBuf> {
+    (!value.is_empty()).then(|| PathBuf::from(value))
+}
+
+// 将命令行拆分为 (参数名, 取值) 列表
+fn parse_flags<I>(args: I) -> Result<Vec<(String, String)>, ConfigError>
+where
+    I: IntoIterator,
+    I::Item: Into<String>,
+{
+    let mut out = Vec::new();
+    let mut args = args.into_iter().map(Into::into);
+    while let Some(arg) = args.next() {
+        if arg == "-h" || arg == "--help" {
+            return Err(ConfigError::Help);
+        }
+        let Some(flag) = arg.strip_prefix("--") else {
+            return Err(ConfigError::UnknownFlag(arg));
+        };
+        match flag.split_once('=') {
+            Some((n, v)) => out.push((n.to_string(), v.to_string())),
+            None if SWITCHES.contains(&flag) => out.push((flag.to_string(), "true".into())),
+            None => {
+                let v = args
+                    .next()
+                    .ok_or_else(|| ConfigError::MissingValue(arg.clone()))?;
+                out.push((flag.to_string(), v));
+            }
+        }
+    }
+    Ok(out)
+}
+
+// 配置加载器：记录命令行参数与配置文件路径，启动与热加载时按相同规则重新合成配置
+#[derive(Debug, Clone)]
+pub struct ConfigLoader {
+    args: Vec<String>,
+    path: Option<PathBuf>,
+}
+
+impl ConfigLoader {
+    // 从进程参数与环境变量确定配置文件：--config > RUSTDEMO_CONFIG > ./rustdemo.toml（若存在）
+    pub fn new(
+        args: Vec<String>,
+        var: impl Fn(&str) -> Option<String>,
+    ) -> Result<Self, ConfigError> {
+        let explicit = parse_flags(args.clone())?
+            .into_iter()
+            .rev()
+            .find(|(flag, _)| flag == "config")
+            .map(|(_, v)| v)
+            .or_else(|| var("RUSTDEMO_CONFIG"))
+            .map(PathBuf::from);
+        let path = explicit.or_else(|| {
+            let default = PathBuf::from(DEFAULT_CONFIG_FILE);
+            default.exists().then_some(default)
+        });
+        Ok(Self { args, path })
+    }
+
+    pub fn path(&self) -> Option<&Path> {
+        self.path.as_deref()
+    }
+
+    // 按 默认值 < 文件 < 环境变量 < 命令行 的顺序合成配置
+    pub fn load(&self) -> Result<Config, ConfigError> {
+        self.load_with(|k| std::env::var(k).ok())
+    }
+
+    pub fn load_with(&self, var: impl Fn(&str) -> Option<String>) -> Result<Config, ConfigError> {
+        let mut cfg = Config::default();
+        if let Some(path) = &self.path {
+            let doc = std::fs::read_to_string(path).map_err(|source| ConfigError::Io {
+                path: path.clone(),
+                source,
+            })?;
+            cfg.apply_file(&doc, path)?;
+        }
+        cfg.apply_env(var)?;
+        cfg.apply_args(self.args.clone())?;
+        Ok(cfg)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // 优先级：命令行覆盖环境变量，环境变量覆盖默认值
+    #[test]
+    fn args_override_env() {
+        let mut cfg = Config::default();
+        cfg.apply_env(|k| match k {
+            "RUSTDEMO_PORT" => Some("4000".into()),
+            "RUSTDEMO_BIND" => Some("0.0.0.0".into()),
+            _ => None,
+        })
+        .unwrap();
+        cfg.apply_args(["--port=5000", "--static-dir", "/srv/www"])
+            .unwrap();
+        assert_eq!(cfg.addr(), "0.0.0.0:5000".parse().unwrap());
+        assert_eq!(cfg.static_dir, PathBuf::from("/srv/www"));
+        assert_eq!(cfg.log_level, "info");
+    }
+
+    // 错误用例：非法端口、缺少取值、未知参数、超出范围的 parallel.max_tasks
+    #[test]
+    fn invalid_args() {
+        let mut cfg = Config::default();
+        assert!(matches!(
+            cfg.apply_args(["--port", "abc"]),
+            Err(ConfigError::InvalidValue { .. })
+        ));
+        assert!(matches!(
+            cfg.apply_args(["--bind"]),
+            Err(ConfigError::MissingValue(f)) if f == "--bind"
+        ));
+        assert!(matches!(
+            cfg.apply_args(["--nope=1"]),
+            Err(ConfigError::UnknownFlag(f)) if f == "--nope"
+        ));
+        assert!(matches!(cfg.apply_args(["-h"]), Err(ConfigError::Help)));
+        for bad in ["0", "1025"] {
+            let doc = format!("[parallel]\nmax_tasks = {bad}\n");
+            assert!(matches!(
+                cfg.apply_file(&doc, Path::new("t.toml")),
+                Err(ConfigError::InvalidValue { .. })
+            ));
+        }
+        cfg.apply_file("[parallel]\nmax_tasks = 1024\n", Path::new("t.toml"))
+            .unwrap();
+        assert_eq!(cfg.parallel.max_tasks, MAX_PARALLEL_TASKS);
+    }
+
+    // 分层：文件覆盖默认值，环境变量与命令行再覆盖文件
+    #[test]
+    fn layered_file_env_args() {
+        let dir = std::env::temp_dir().join(format!("rustdemo-cfg-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("rustdemo.toml");
+        std::fs::write(
+            &path,
+            "log_level = \"debug\"\n[server]\nport = 4000\n[parallel]\nmax_tasks = 8\n\
+             [middleware]\ncors_origins = [\"http://a.example\"]\n",
+        )
+        .unwrap();
+        let args = vec![
+            "--config".to_string(),
+            path.display().to_string(),
+            "--port=6000".into(),
+        ];
+        let loader = ConfigLoader::new(args, |_| None).unwrap();
+        assert_eq!(loader.path(), Some(path.as_path()));
+        let cfg = loader
+            .load_with(|k| (k == "RUSTDEMO_LOG_LEVEL").then(|| "warn".to_string()))
+            .unwrap();
+        assert_eq!(cfg.server.port, 6000);
+        assert_eq!(cfg.log_level, "warn");
+        assert_eq!(cfg.parallel.max_tasks, 8);
+        assert_eq!(
+            cfg.middleware.cors_origins,
+            vec!["http://a.example".to_string()]
+        );
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    // 日志：RUST_LOG 作为日志级别的后备来源，RUSTDEMO_LOG_LEVEL 优先；日志格式仅接受 text / json
+    #[test]
+    fn log_options() {
+        let mut cfg = Config::default();
+        cfg.apply_env(|k| (k == "RUST_LOG").then(|| "rustdemo=debug".to_string()))
+            .unwrap();
+        assert_eq!(cfg.log_level, "rustdemo=debug");
+        assert_eq!(cfg.log_format, LogFormat::Text);
+        cfg.apply_env(|k| match k {
+            "RUST_LOG" => Some("debug".into()),
+            "RUSTDEMO_LOG_LEVEL" => Some("warn".into()),
+            "RUSTDEMO_LOG_FORMAT" => Some("json".into()),
+            _ => None,
+        })
+        .unwrap();
+        assert_eq!(cfg.log_level, "warn");
+        assert_eq!(cfg.log_format, LogFormat::Json);
+        cfg.apply_args(["--log-format=text"]).unwrap();
+        assert_eq!(cfg.log_format, LogFormat::Text);
+        assert!(matches!(
+            cfg.apply_args(["--log-format", "xml"]),
+            Err(ConfigError::InvalidValue { .. })
+        ));
+    }
+
+    // 监听地址：TCP 与 unix: 前缀，listen 为空时回退到 bind:port
+    #[test]
+    fn listen_addrs() {
+        let mut cfg = Config::default();
+        assert_eq!(cfg.listeners(), vec![ListenAddr::Tcp(cfg.addr())]);
+        cfg.apply_args(["--listen", "0.0.0.0:8080, unix:/tmp/rd.sock"])
+            .unwrap();
+        assert_eq!(
+            cfg.listeners(),
+            vec![
+                ListenAddr::Tcp("0.0.0.0:8080".parse().unwrap()),
+                ListenAddr::Unix(PathBuf::from("/tmp/rd.sock")),
+            ]
+        );
+        assert_eq!(cfg.listeners()[1].to_string(), "unix:/tmp/rd.sock");
+        assert!(cfg.apply_args(["--admin-listen=unix:"]).is_err());
+        assert!(cfg.apply_args(["--admin-listen=localhost"]).is_err());
+        let doc = "[admin]\nallow_log_level_changes = true\n";
+        cfg.apply_file(doc, Path::new("t.toml")).unwrap();
+        assert!(cfg.admin.allow_log_level_changes);
+        assert_eq!(
+            Config::default().restart_required_changes(&cfg),
+            ["server.listen", "admin.allow_log_level_changes"]
+        );
+        let doc = "[server]\nunix_socket_mode = \"600\"\n";
+        cfg.apply_file(doc, Path::new("t.toml")).unwrap();
+        assert_eq!(cfg.server.unix_socket_mode, 0o600);
+    }
+
+    // HTTP 连接参数：文件与命令行覆盖，读缓冲过小视为错误
+    #[test]
+    fn http_options() {
+        let mut cfg = Config::default();
+        let doc = "[http]\nkeep_alive = false\nmax_headers = 20\nmax_header_bytes = 16384\n";
+        cfg.apply_file(doc, Path::new("t.toml")).unwrap();
+        cfg.apply_args(["--max-connections=64"]).unwrap();
+        assert!(!cfg.http.keep_alive);
+        assert_eq!(cfg.http.max_headers, 20);
+        assert_eq!(cfg.http.max_header_bytes, 16384);
+        assert_eq!(cfg.http.max_connections, 64);
+        assert!(cfg
+            .apply_file("[http]\nmax_header_bytes = 1024\n", Path::new("t.toml"))
+            .is_err());
+        assert_eq!(
+            Config::default().restart_required_changes(&cfg),
+            vec![
+                "http.keep_alive",
+                "http.max_headers",
+                "http.max_header_bytes",
+                "http.max_connections"
+            ]
+        );
+    }
+
+    // 耗时直方图桶：须为严格递增的正数
+    #[test]
+    fn latency_buckets() {
+        let mut cfg = Config::default();
+        let doc =
+            "[metrics]\nlatency_buckets = [0.1, 0.5, 2]\nwindow_secs = 30\nrecent_requests = 0\n";
+        cfg.apply_file(doc, Path::new("t.toml")).unwrap();
+        assert_eq!(cfg.metrics.latency_buckets, vec![0.1, 0.5, 2.0]);
+        assert_eq!(cfg.metrics.window_secs, 30);
+        assert_eq!(cfg.metrics.recent_requests, 0);
+        cfg.apply_args(["--metrics-state-file", "/var/lib/rd/metrics.json"])
+            .unwrap();
+        let doc = "[metrics]\nstream_interval_ms = 250\nstream_max_subscribers = 0\n";
+        cfg.apply_file(doc, Path::new("t.toml")).unwrap();
+        assert_eq!(cfg.metrics.stream_interval_ms, 250);
+        assert_eq!(cfg.metrics.stream_max_subscribers, 0);
+        let doc = "[metrics]\nstream_interval_ms = 50\n";
+        assert!(cfg.apply_file(doc, Path::new("t.toml")).is_err());
+        assert_eq!(
+            cfg.metrics.state_file,
+            Some(PathBuf::from("/var/lib/rd/metrics.json"))
+        );
+        for bad in ["[0.5, 0.1]", "[]", "[0, 1]", "[\"x\"]"] {
+            let doc = format!("[metrics]\nlatency_buckets = {bad}\n");
+            assert!(cfg.apply_file(&doc, Path::new("t.toml")).is_err(), "{bad}");
+        }
+    }
+
+    // 开关参数无需取值；空路径表示不设置
+    #[test]
+    fn process_options() {
+        let mut cfg = Config::default();
+        cfg.apply_args(["--daemon", "--pidfile", "/run/rd.pid", "--port=4000"])
+            .unwrap();
+        assert!(cfg.process.daemon);
+        assert_eq!(cfg.process.pidfile, Some(PathBuf::from("/run/rd.pid")));
+        assert_eq!(cfg.server.port, 4000);
+        cfg.apply_args(["--daemon=false", "--pidfile="]).unwrap();
+        assert!(!cfg.process.daemon);
+        assert_eq!(cfg.process.pidfile, None);
+    }
+
+    #[test]
+    fn access_log_options() {
+        let mut cfg = Config::default();
+        assert_eq!(cfg.access_log.path, None);
+        cfg.apply_args([
+            "--access-log",
+            "/var/log/access.log",
+            "--access-log-format=json",
+        ])
+        .unwrap();
+        assert_eq!(
+            cfg.access_log.path,
+            Some(PathBuf::from("/var/log/access.log"))
+        );
+        assert_eq!(cfg.access_log.format, AccessLogFormat::Json);
+        assert!(cfg.apply_args(["--access-log-format", "xml"]).is_err());
+        cfg.apply_file(
+            "[access_log]\nkeep = 3\ndaily = false\nmax_size_bytes = 0\n",
+            Path::new("t.toml"),
+        )
+        .unwrap();
+        assert_eq!((cfg.access_log.keep, cfg.access_log.daily), (3, false));
+        assert_eq!(cfg.access_log.max_size_bytes, 0);
+    }
+
+    // StatsD：地址须为 HOST:PORT 或 unix:PATH；前缀去掉结尾的 "."
+    #[test]
+    fn statsd_options() {
+        let mut cfg = Config::default();
+        assert_eq!(cfg.statsd.address, None);
+        cfg.apply_args(["--statsd", "localhost:8125"]).unwrap();
+        assert_eq!(
+            cfg.statsd.address,
+            Some(StatsdAddr::Udp("localhost:8125".into()))
+        );
+        let doc = "[statsd]\naddress = \"unix:/run/dsd.sock\"\nprefix = \"app.\"\ndogstatsd = true\ntags = [\"env:prod\", \"region:eu\"]\n";
+        cfg.apply_file(doc, Path::new("t.toml")).unwrap();
+        assert_eq!(
+            cfg.statsd.address,
+            Some(StatsdAddr::Unix(PathBuf::from("/run/dsd.sock")))
+        );
+        assert_eq!(cfg.statsd.prefix, "app");
+        assert!(cfg.statsd.dogstatsd);
+        assert_eq!(cfg.statsd.tags, ["env:prod", "region:eu"]);
+        for bad in ["8125", "host:", ":8125", "host:99999", "unix:"] {
+            assert!(cfg.apply_args(["--statsd", bad]).is_err(), "{bad}");
+        }
+        let doc = "[statsd]\nflush_interval_ms = 0\n";
+        assert!(cfg.apply_file(doc, Path::new("t.toml")).is_err());
+    }
+
+    // 慢请求阈值：按路由覆盖，0 表示不检测
+    #[test]
+    fn slow_request_thresholds() {
+        let mut cfg = Config::default();
+        let doc =
+            "[slow_requests]\nthreshold_ms = 500\nroutes = [\"/parallel=2000\", \"/health=0\"]\n";
+        cfg.apply_file(doc, Path::new("t.toml")).unwrap();
+        let ms = |cfg: &Config, route| cfg.slow_requests.threshold(route).map(|d| d.as_millis());
+        assert_eq!(ms(&cfg, "/sum"), Some(500));
+        assert_eq!(ms(&cfg, "/parallel"), Some(2000));
+        assert_eq!(ms(&cfg, "/health"), None);
+        cfg.apply_env(|k| (k == "RUSTDEMO_SLOW_ROUTES").then(|| "/x=abc".into()))
+            .unwrap_err();
+        cfg.apply_args(["--slow-threshold-ms=0"]).unwrap();
+        assert_eq!(ms(&cfg, "/sum"), None);
+    }
+
+    // 热加载：区分可立即生效与需要重启的配置项
+    #[test]
+    fn reloadable_vs_restart() {
+        let old = Config::default();
+        let mut new = old.clone();
+        new.server.port = 9000;
+        new.middleware.timeout_secs = 5;
+        new.parallel.max_tasks = 4;
+        new.log_level = "debug".into();
+        assert_eq!(
+            old.restart_required_changes(&new),
+            vec!["server.port", "middleware.timeout_secs"]
+        );
+        let mut cur = old.clone();
+        cur.apply_reloadable(&new);
+        assert_eq!(cur.parallel.max_tasks, 4);
+        assert_eq!(cur.log_level, "debug");
+        assert_eq!(cur.server.port, 3000);
+    }
+}
@@ -0,0 +1,185 @@
+// 后台运行与 PID 文件：
+// - detach：fork 出子进程并脱离终端（setsid），父进程等待子进程就绪后以退出码 0 返回，子进程失败则返回 1
+// - notify_ready：子进程监听就绪后将标准输入/输出重定向（日志写入 log_file 或丢弃）并通知父进程；
+//   就绪前的错误仍输出到启动终端
+// - Pidfile：写入当前 PID，文件已被运行中的实例占用时拒绝启动，退出时删除（已被新进程接管时保留）
+// detach 必须在创建 Tokio 运行时之前调用（fork 只保留调用线程）
+use std::{
+    fs::{File, OpenOptions},
+    io::{self, Read, Write},
+    path::{Path, PathBuf},
+};
+
+pub struct Pidfile {
+    path: PathBuf,
+    pid: u32,
+}
+
+impl Pidfile {
+    // 写入 PID 文件；已存在时检查其中的 PID：进程仍在运行则报错（takeover 指定的 PID 除外），否则视为遗留文件覆盖
+    pub fn create(path: &Path, takeover: Option<u32>) -> io::Result<Self> {
+        let pid = std::process::id();
+        let mut file = match OpenOptions::new().write(true).create_new(true).open(path) {
+            Ok(file) => file,
+            Err(e) if e.kind() == io::ErrorKind::AlreadyExists => {
+                let owner = read_pid(path)?;
+                if let Some(owner) = owner.filter(|p| Some(*p) != takeover && is_alive(*p)) {
+                    return Err(io::Error::new(
+                        io::ErrorKind::AddrInUse,
+                        format!("PID 文件 {} 已被运行中的进程 {owner} 占用", path.display()),
+                    ));
+                }
+                File::create(path)?
+            }
+            Err(e) => return Err(e),
+        };
+        writeln!(file, "{pid}")?;
+        Ok(Self {
+            path: path.to_path_buf(),
+            pid,
+        })
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+}
+
+// 仅当文件内容仍是本进程 PID 时删除（SIGUSR2 交接后新进程已写入自己的 PID）
+impl Drop for Pidfile {
+    fn drop(&mut self) {
+        if read_pid(&self.path).ok().flatten() == Some(self.pid) {
+            let _ = std::fs::remove_file(&self.path);
+        }
+    }
+}
+
+fn read_pid(path: &Path) -> io::Result<Option<u32>> {
+    match std::fs::read_to_string(path) {
+        Ok(s) => Ok(s.trim().parse().ok()),
+        Err(e) if e.kind() == io::ErrorKind::NotFound => Ok(None),
+        Err(e) => Err(e),
+    }
+}
+
+#[cfg(unix)]
+fn is_alive(pid: u32) -> bool {
+    let Ok(pid) = libc::pid_t::try_from(pid) else {
+        return false;
+    };
+    // SAFETY：信号 0 只检查进程是否存在；EPERM 表示进程存在但属于其他用户
+    let rc = unsafe { libc::kill(pid, 0) };
+    rc == 0 || io::Error::last_os_error().raw_os_error() == Some(libc::EPERM)
+}
+
+// 非 Unix 平台无法可靠探测，保守地视为仍在运行
+#[cfg(not(unix))]
+fn is_alive(_pid: u32) -> bool {
+    true
+}
+
+#[cfg(unix)]
+static READY: std::sync::Mutex<Option<File>> = std::sync::Mutex::new(None);
+
+#[cfg(unix)]
+pub fn detach() -> io::Result<()> {
+    use std::os::fd::{FromRawFd, OwnedFd};
+
+    let mut fds = [0; 2];
+    // SAFETY：pipe 成功时写入两个新的描述符，随后各自转为 File 管理
+    if unsafe { libc::pipe(fds.as_mut_ptr()) } < 0 {
+        return Err(io::Error::last_os_error());
+    }
+    let (rx, tx) = unsafe {
+        (
+            File::from(OwnedFd::from_raw_fd(fds[0])),
+            File::from(OwnedFd::from_raw_fd(fds[1])),
+        )
+    };
+    // SAFETY：此时只有主线程（运行时尚未创建），fork 后子进程可安全继续执行
+    match unsafe { libc::fork() } {
+        -1 => Err(io::Error::last_os_error()),
+        0 => {
+            drop(rx);
+            // SAFETY：子进程不是进程组组长，setsid 必定成功
+            if unsafe { libc::setsid() } < 0 {
+                return Err(io::Error::last_os_error());
+            }
+            *READY.lock().unwrap() = Some(tx);
+            Ok(())
+        }
+        _ => {
+            drop(tx);
+            let mut rx = rx;
+            let mut buf = [0u8; 1];
+            // 子进程退出（未就绪）时写端关闭，read 返回 0
+            let ready = matches!(rx.read(&mut buf), Ok(1));
+            std::process::exit(if ready { 0 } else { 1 });
+        }
+    }
+}
+
+#[cfg(not(unix))]
+pub fn detach() -> io::Result<()> {
+    Err(io::Error::new(
+        io::ErrorKind::Unsupported,
+        "当前平台不支持后台运行",
+    ))
+}
+
+// 非后台运行时什么也不做
+#[cfg(unix)]
+pub fn notify_ready(log_file: Option<&Path>) -> io::Result<()> {
+    use std::os::fd::AsRawFd;
+
+    let Some(mut ready) = READY.lock().unwrap().take() else {
+        return Ok(());
+    };
+    let null = File::options().read(true).write(true).open("/dev/null")?;
+    let out = match log_file {
+        Some(path) => OpenOptions::new().create(true).append(true).open(path)?,
+        None => null.try_clone()?,
+    };
+    for (src, dst) in [(&null, 0), (&out, 1), (&out, 2)] {
+        // SAFETY：dup2 替换标准输入/输出描述符，源描述符在此期间保持打开
+        if unsafe { libc::dup2(src.as_raw_fd(), dst) } < 0 {
+            return Err(io::Error::last_os_error());
+        }
+    }
+    ready.write_all(b"1")
+}
+
+#[cfg(not(unix))]
+pub fn notify_ready(_log_file: Option<&Path>) -> io::Result<()> {
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // 运行中的实例占用时拒绝；遗留文件与 takeover 指定的 PID 可覆盖；退出时删除
+    #[test]
+    fn pidfile_ownership() {
+        let path = std::env::temp_dir().join(format!("rustdemo-{}.pid", std::process::id()));
+        let _ = std::fs::remove_file(&path);
+
+        let first = Pidfile::create(&path, None).unwrap();
+        assert_eq!(read_pid(&path).unwrap(), Some(std::process::id()));
+        let err = Pidfile::create(&path, None).err().unwrap();
+        assert_eq!(err.kind(), io::ErrorKind::AddrInUse);
+        std::mem::forget(first);
+
+        let second = Pidfile::create(&path, Some(std::process::id())).unwrap();
+        drop(second);
+        assert!(!path.exists());
+
+        std::fs::write(&path, "not a pid\n").unwrap();
+        let third = Pidfile::create(&path, None).unwrap();
+        // 文件已被其他进程改写时保留
+        std::fs::write(&path, "1\n").unwrap();
+        drop(third);
+        assert!(path.exists());
+        std::fs::remove_file(&path).unwrap();
+    }
+}
@@ -0,0 +1,54 @@
+// 统一错误模型：处理器返回 Result<_, AppError>，由 IntoResponse 转换为 JSON 错误响应
+use axum::{http::StatusCode, response::IntoResponse, Extension, Json};
+use thiserror::Error;
+
+use crate::{reload::LogLevelError, request_id};
+
+#[derive(Error, Debug)]
+pub enum AppError {
+    #[error("{0}")]
+    BadRequest(String),
+    #[error("{0}")]
+    Internal(String),
+    #[error("{0}")]
+    Unavailable(String),
+    // 请求提取失败（查询串、JSON 请求体），状态码沿用 axum 的判断
+    #[error("{1}")]
+    Rejected(StatusCode, String),
+}
+
+// AppError 响应携带的错误信息（响应扩展）
+#[derive(Debug, Clone)]
+pub struct ErrorMessage(pub String);
+
+impl From<LogLevelError> for AppError {
+    fn from(e: LogLevelError) -> Self {
+        match e {
+            LogLevelError::Invalid(_) => AppError::BadRequest(e.to_string()),
+            LogLevelError::Unavailable => AppError::Internal(e.to_string()),
+        }
+    }
+}
+
+// 将错误统一转换为 JSON 响应：
+// - BadRequest -> 400 {"error":"...","request_id":"..."}
+// - Internal   -> 500 {"error":"...","request_id":"..."}
+// - Unavailable -> 503 {"error":"...","request_id":"..."}（如订阅者已满）
+// - Rejected   -> 4xx {"error":"...","request_id":"..."}（见 extract 模块）
+// request_id 为当前请求 ID（见 request_id 模块），便于与日志对照；不在请求处理期间时省略
+// 错误信息同时以 ErrorMessage 写入响应扩展，供中间件（如最近请求记录）读取
+impl IntoResponse for AppError {
+    fn into_response(self) -> axum::response::Response {
+        let (code, msg) = match self {
+            AppError::BadRequest(m) => (StatusCode::BAD_REQUEST, m),
+            AppError::Internal(m) => (StatusCode::INTERNAL_SERVER_ERROR, m),
+            AppError::Unavailable(m) => (StatusCode::SERVICE_UNAVAILABLE, m),
+            AppError::Rejected(code, m) => (code, m),
+        };
+        let mut body = serde_json::json!({ "error": msg });
+        if let Some(id) = request_id::current() {
+            body["request_id"] = id.as_str().into();
+        }
+        (code, Extension(ErrorMessage(msg)), Json(body)).into_response()
+    }
+}
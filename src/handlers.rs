@@ -0,0 +1,716 @@
+// 端点处理器与请求/响应类型
+use std::{
+    convert::Infallible,
+    fmt::Display,
+    sync::Arc,
+    time::{Duration, Instant, SystemTime, UNIX_EPOCH},
+};
+
+// Axum（路由/提取器/响应类型）：定义 HTTP 端点与参数解析
+use axum::{
+    extract::State,
+    http::{header, HeaderMap, StatusCode},
+    response::{
+        sse::{Event, KeepAlive, Sse},
+        IntoResponse, Response,
+    },
+    Extension,
+};
+// Serde（序列化/反序列化）：类型安全地映射请求/响应 JSON
+use serde::{Deserialize, Serialize};
+// Tokio（异步运行时）：管理并发任务与计时
+use futures_util::{Stream, StreamExt};
+use tokio::{sync::broadcast::error::RecvError, task::JoinSet, time::sleep};
+
+use crate::{
+    access_log::rfc3339,
+    error::AppError,
+    extract::{Json, Query},
+    history::{self, Resolution, RESOLUTIONS},
+    metrics::{self, Encoder, Format},
+    persist::Totals,
+    recent::{Filter, RequestRecord},
+    runtime_metrics::{ProcessStats, RuntimeStats},
+    server::ShutdownSignal,
+    slow::{self, TaskTiming},
+    state::AppState,
+};
+
+// 健康检查返回体（简单 JSON）
+#[derive(Serialize)]
+pub struct Health {
+    pub status: &'static str,
+}
+
+// 求和查询参数：以逗号分隔的整数字符串（如 "1,2,3"）
+#[derive(Deserialize)]
+pub struct SumQuery {
+    pub nums: String,
+}
+
+// 回显请求体（POST JSON）：{"message": "..."}，用于演示 JSON 解析
+#[derive(Deserialize, Serialize)]
+pub struct EchoBody {
+    pub message: String,
+}
+
+// 并发示例返回项：索引、计算值（平方）、模拟耗时毫秒
+#[derive(Serialize)]
+pub struct ParallelResult {
+    pub index: usize,
+    pub value: usize,
+    pub ms: u64,
+}
+
+// 根路径：健康返回（文本 "ok"）
+pub async fn root() -> impl IntoResponse {
+    (StatusCode::OK, "ok")
+}
+
+// 健康检查：返回 {"status":"healthy"}
+pub async fn health() -> impl IntoResponse {
+    Json(Health { status: "healthy" })
+}
+
+// 输入解析（纯函数，便于单元测试与复用）：
+// - 返回总和或携带详细错误的 BadRequest
+pub fn parse_sum_input(s: &str) -> Result<i64, AppError> {
+    let mut total: i64 = 0;
+    for (idx, token) in s.split(',').filter(|t| !t.is_empty()).enumerate() {
+        let t = token.trim();
+        match t.parse::<i64>() {
+            Ok(v) => total += v,
+            Err(_) => {
+                return Err(AppError::BadRequest(format!(
+                    "nums 第 {idx} 项不是有效整数: {t}"
+                )))
+            }
+        }
+    }
+    Ok(total)
+}
+
+// 求和接口：GET /sum?nums=1,2,3
+// - 流程：拆分 -> 去空白 -> 逐项解析 i64 -> 求和 -> 返回 {"total":X}
+// - 错误：任何项非数字则返回 400 与说明（含索引与原值）
+pub async fn sum(Query(q): Query<SumQuery>) -> Result<impl IntoResponse, AppError> {
+    let total = parse_sum_input(&q.nums)?;
+    Ok(Json(serde_json::json!({ "total": total })))
+}
+
+// 回显接口：POST /echo
+// - 请求体：{"message":"..."}；若为空字符串则返回 400 错误
+// - 作用：演示 JSON 反序列化与简单校验
+pub async fn echo(Json(body): Json<EchoBody>) -> Result<impl IntoResponse, AppError> {
+    if body.message.trim().is_empty() {
+        return Err(AppError::BadRequest("message 不能为空".into()));
+    }
+    Ok(Json(body))
+}
+
+// 并发查询参数：
+// - n：并发任务数量（默认 parallel.default_tasks，上限 parallel.max_tasks；避免过度并发）
+#[derive(Deserialize)]
+pub struct ParallelQuery {
+    pub n: Option<usize>,
+}
+
+// 并发示例：GET /parallel?n=8
+// - 任务：为每个 i 启动一个异步任务，sleep 不同毫秒模拟耗时
+// - 结果：返回每个任务的 index/value/ms，按完成顺序收集（非阻塞）
+// - 展示：Tokio 并发与 JoinSet 收集的用法
+pub async fn parallel(
+    State(app): State<Arc<AppState>>,
+    Query(q): Query<ParallelQuery>,
+) -> Result<impl IntoResponse, AppError> {
+    let limits = app.config.read(|c| c.parallel.clone());
+    let n = q.n.unwrap_or(limits.default_tasks).min(limits.max_tasks);
+    // 启用了慢请求检测时记录每个任务的排队与运行耗时
+    let diagnostics = slow::current();
+    let mut tasks = JoinSet::new();
+    for i in 0..n {
+        let diagnostics = diagnostics.clone();
+        let spawned = Instant::now();
+        tasks.spawn(async move {
+            let started = Instant::now();
+            let ms = 50 + (i as u64) * 30;
+            sleep(Duration::from_millis(ms)).await;
+            if let Some(d) = diagnostics {
+                d.record_task(TaskTiming {
+                    name: format!("task{{index={i}}}"),
+                    spawned,
+                    started,
+                    finished: Instant::now(),
+                    expected: Some(Duration::from_millis(ms)),
+                });
+            }
+            ParallelResult {
+                index: i,
+                value: i * i,
+                ms,
+            }
+        });
+    }
+    let mut results = Vec::with_capacity(n);
+    while let Some(res) = tasks.join_next().await {
+        results.push(res.map_err(|e| AppError::Internal(format!("并发任务失败: {e}")))?);
+    }
+    Ok(Json(results))
+}
+
+// 指标端点：默认返回运行时长与各路由（按路由模板动态列出）的请求数、状态类别、在途请求数、
+// 耗时总计与最近 metrics.window_secs 秒内的 p50/p90/p99/max，以及 Tokio 运行时与进程指标（JSON）；
+// Accept 要求 text/plain 或 application/openmetrics-text 时返回 Prometheus / OpenMetrics 文本格式
+pub async fn metrics(State(app): State<Arc<AppState>>, headers: HeaderMap) -> Response {
+    let accept = headers.get(header::ACCEPT).and_then(|v| v.to_str().ok());
+    let format = metrics::negotiate(accept);
+    if format == Format::Json {
+        return metrics_json(&app).into_response();
+    }
+    let routes = app.metrics.snapshot();
+    let mut enc = Encoder::new(format);
+    enc.family(
+        "rustdemo_uptime_seconds",
+        "gauge",
+        "Seconds since the service started.",
+    );
+    enc.sample("rustdemo_uptime_seconds", &[], app.uptime().as_secs_f64());
+    enc.family(
+        "rustdemo_http_requests",
+        "counter",
+        "HTTP requests by route template, method and status.",
+    );
+    for (route, m) in &routes {
+        for ((method, status), n) in m.by_status() {
+            let status = status.to_string();
+            enc.sample(
+                "rustdemo_http_requests_total",
+                &[("route", route), ("method", &method), ("status", &status)],
+                n,
+            );
+        }
+    }
+    enc.family(
+        "rustdemo_http_requests_in_flight",
+        "gauge",
+        "HTTP requests currently being served.",
+    );
+    for (route, m) in &routes {
+        enc.sample(
+            "rustdemo_http_requests_in_flight",
+            &[("route", route)],
+            m.in_flight(),
+        );
+    }
+    enc.family(
+        "rustdemo_slow_requests",
+        "counter",
+        "HTTP requests slower than the slow_requests threshold, by route template.",
+    );
+    for (route, m) in &routes {
+        enc.sample(
+            "rustdemo_slow_requests_total",
+            &[("route", route)],
+            m.slow(),
+        );
+    }
+    enc.family(
+        "rustdemo_http_request_duration_seconds",
+        "histogram",
+        "HTTP request latency by route template.",
+    );
+    for (route, m) in &routes {
+        let latency = m.latency();
+        for (le, n) in latency.cumulative() {
+            let le = if le.is_infinite() {
+                "+Inf".to_string()
+            } else {
+                le.to_string()
+            };
+            enc.sample(
+                "rustdemo_http_request_duration_seconds_bucket",
+                &[("route", route), ("le", &le)],
+                n,
+            );
+        }
+        let labels = [("route", route.as_str())];
+        enc.sample(
+            "rustdemo_http_request_duration_seconds_sum",
+            &labels,
+            latency.sum().as_secs_f64(),
+        );
+        enc.sample(
+            "rustdemo_http_request_duration_seconds_count",
+            &labels,
+            latency.count(),
+        );
+    }
+    encode_lifetime(&mut enc, &app.metrics.totals());
+    if let Some(rt) = RuntimeStats::collect() {
+        encode_runtime(&mut enc, &rt);
+    }
+    encode_process(&mut enc, &ProcessStats::collect());
+    (
+        [
+            (header::CONTENT_TYPE, format.content_type()),
+            (header::VARY, "accept"),
+        ],
+        enc.finish(),
+    )
+        .into_response()
+}
+
+// 累计计数（含 metrics.state_file 恢复的基线），与上面本次启动以来的指标并列输出
+fn encode_lifetime(enc: &mut Encoder, totals: &Totals) {
+    let since = totals
+        .since()
+        .duration_since(UNIX_EPOCH)
+        .unwrap_or_default();
+    scalar(
+        enc,
+        "rustdemo_lifetime_start_time_seconds",
+        "gauge",
+        "Unix time when lifetime totals started accumulating.",
+        Some(since.as_secs()),
+    );
+    enc.family(
+        "rustdemo_http_requests_lifetime",
+        "counter",
+        "HTTP requests by route template, method and status, across restarts.",
+    );
+    for (route, t) in &totals.routes {
+        for (method, statuses) in &t.requests {
+            for (status, n) in statuses {
+                let status = status.to_string();
+                enc.sample(
+                    "rustdemo_http_requests_lifetime_total",
+                    &[("route", route), ("method", method), ("status", &status)],
+                    n,
+                );
+            }
+        }
+    }
+    enc.family(
+        "rustdemo_slow_requests_lifetime",
+        "counter",
+        "Slow HTTP requests by route template, across restarts.",
+    );
+    for (route, t) in &totals.routes {
+        enc.sample(
+            "rustdemo_slow_requests_lifetime_total",
+            &[("route", route)],
+            t.slow_requests,
+        );
+    }
+    enc.family(
+        "rustdemo_http_request_duration_lifetime_seconds",
+        "summary",
+        "HTTP request latency by route template, across restarts.",
+    );
+    for (route, t) in &totals.routes {
+        let labels = [("route", route.as_str())];
+        enc.sample(
+            "rustdemo_http_request_duration_lifetime_seconds_sum",
+            &labels,
+            t.latency_sum_seconds,
+        );
+        enc.sample(
+            "rustdemo_http_request_duration_lifetime_seconds_count",
+            &labels,
+            t.latency_count,
+        );
+    }
+}
+
+// 无标签的单值指标；取值为 None（平台不支持或未启用）时整个指标族省略
+fn scalar(enc: &mut Encoder, name: &str, kind: &str, help: &str, value: Option<impl Display>) {
+    let Some(value) = value else { return };
+    enc.family(name, kind, help);
+    if kind == "counter" {
+        enc.sample(&format!("{name}_total"), &[], value);
+    } else {
+        enc.sample(name, &[], value);
+    }
+}
+
+fn encode_runtime(enc: &mut Encoder, rt: &RuntimeStats) {
+    let gauges = [
+        (
+            "rustdemo_tokio_workers",
+            "Tokio worker threads.",
+            Some(rt.workers),
+        ),
+        (
+            "rustdemo_tokio_alive_tasks",
+            "Tasks alive in the Tokio runtime.",
+            Some(rt.alive_tasks),
+        ),
+        (
+            "rustdemo_tokio_global_queue_depth",
+            "Tasks waiting in the Tokio global (injection) queue.",
+            Some(rt.global_queue_depth),
+        ),
+        (
+            "rustdemo_tokio_blocking_threads",
+            "Threads in the Tokio blocking pool.",
+            rt.blocking_threads,
+        ),
+        (
+            "rustdemo_tokio_idle_blocking_threads",
+            "Idle threads in the Tokio blocking pool.",
+            rt.idle_blocking_threads,
+        ),
+        (
+            "rustdemo_tokio_blocking_queue_depth",
+            "Tasks waiting for a Tokio blocking thread.",
+            rt.blocking_queue_depth,
+        ),
+    ];
+    for (name, help, value) in gauges {
+        scalar(enc, name, "gauge", help, value);
+    }
+    scalar(
+        enc,
+        "rustdemo_tokio_busy_seconds",
+        "counter",
+        "Time Tokio workers spent busy, summed over workers.",
+        Some(rt.busy_seconds),
+    );
+    let counters = [
+        (
+            "rustdemo_tokio_parks",
+            "Times Tokio workers parked, summed over workers.",
+            Some(rt.parks),
+        ),
+        (
+            "rustdemo_tokio_spawned_tasks",
+            "Tasks spawned onto the Tokio runtime.",
+            rt.spawned_tasks,
+        ),
+        (
+            "rustdemo_tokio_polls",
+            "Task polls by Tokio workers, summed over workers.",
+            rt.polls,
+        ),
+    ];
+    for (name, help, value) in counters {
+        scalar(enc, name, "counter", help, value);
+    }
+}
+
+// 进程指标沿用 Prometheus 客户端库的标准名称（process_*）
+fn encode_process(enc: &mut Encoder, p: &ProcessStats) {
+    scalar(
+        enc,
+        "process_cpu_seconds",
+        "counter",
+        "Total user and system CPU time spent in seconds.",
+        p.cpu_seconds,
+    );
+    let gauges = [
+        (
+            "process_resident_memory_bytes",
+            "Resident memory size in bytes.",
+            p.resident_memory_bytes,
+        ),
+        (
+            "process_virtual_memory_bytes",
+            "Virtual memory size in bytes.",
+            p.virtual_memory_bytes,
+        ),
+        (
+            "process_threads",
+            "Number of OS threads in the process.",
+            p.threads,
+        ),
+        (
+            "process_open_fds",
+            "Number of open file descriptors.",
+            p.open_fds,
+        ),
+        (
+            "process_max_fds",
+            "Maximum number of open file descriptors.",
+            p.max_fds,
+        ),
+    ];
+    for (name, help, value) in gauges {
+        scalar(enc, name, "gauge", help, value);
+    }
+}
+
+fn metrics_json(app: &AppState) -> Json<serde_json::Value> {
+    let routes: serde_json::Map<String, serde_json::Value> = app
+        .metrics
+        .snapshot()
+        .into_iter()
+        .map(|(route, m)| {
+            let latency = m.latency();
+            let recent = m.recent().percentiles();
+            let value = serde_json::json!({
+                "requests": m.requests(),
+                "status": m.classes(),
+                "in_flight": m.in_flight(),
+                "slow_requests": m.slow(),
+                "latency": {
+                    "count": latency.count(),
+                    "sum_seconds": latency.sum().as_secs_f64(),
+                    "window_seconds": m.recent().window().as_secs(),
+                    "window_count": recent.count,
+                    "p50_ms": recent.p50_ms,
+                    "p90_ms": recent.p90_ms,
+                    "p99_ms": recent.p99_ms,
+                    "max_ms": recent.max_ms,
+                },
+            });
+            (route, value)
+        })
+        .collect();
+    let totals = app.metrics.totals();
+    let lifetime: serde_json::Map<String, serde_json::Value> = totals
+        .routes
+        .iter()
+        .map(|(route, t)| {
+            let value = serde_json::json!({
+                "requests": t.total_requests(),
+                "by_status": t.requests,
+                "slow_requests": t.slow_requests,
+                "latency_count": t.latency_count,
+                "latency_sum_seconds": t.latency_sum_seconds,
+            });
+            (route.clone(), value)
+        })
+        .collect();
+    Json(serde_json::json!({
+        "uptime_seconds": app.uptime().as_secs(),
+        "routes": routes,
+        "lifetime": {
+            "since": rfc3339(totals.since()),
+            "routes": lifetime,
+        },
+        "runtime": RuntimeStats::collect(),
+        "process": ProcessStats::collect(),
+    }))
+}
+
+// 配置查看：返回当前生效配置、配置文件路径，以及已修改但需重启才能生效的配置项
+pub async fn admin_config(State(app): State<Arc<AppState>>) -> impl IntoResponse {
+    Json(serde_json::json!({
+        "file": app.config.file(),
+        "config": app.config.snapshot(),
+        "pending_restart": app.config.pending_restart(),
+    }))
+}
+
+// 临时日志过滤规则：{"level":"info,rustdemo=debug","ttl_secs":300}，ttl_secs 省略时一直生效直到撤销
+#[derive(Deserialize)]
+pub struct LogLevelBody {
+    pub level: String,
+    pub ttl_secs: Option<u64>,
+}
+
+// 管理端点：GET /admin/log-level 查看生效的日志过滤规则与临时覆盖
+pub async fn get_log_level(State(app): State<Arc<AppState>>) -> impl IntoResponse {
+    Json(app.config.log_level())
+}
+
+// 管理端点：PUT /admin/log-level 临时覆盖日志过滤规则（支持按模块的 EnvFilter 语法），到期后恢复为 log_level
+pub async fn put_log_level(
+    State(app): State<Arc<AppState>>,
+    Json(body): Json<LogLevelBody>,
+) -> Result<impl IntoResponse, AppError> {
+    let ttl = match body.ttl_secs {
+        Some(0) => return Err(AppError::BadRequest("ttl_secs 必须大于 0".into())),
+        ttl => ttl.map(Duration::from_secs),
+    };
+    let status = app.config.set_log_override(body.level.trim(), ttl)?;
+    Ok(Json(status))
+}
+
+// 管理端点：DELETE /admin/log-level 撤销临时覆盖，立即恢复为 log_level
+pub async fn delete_log_level(
+    State(app): State<Arc<AppState>>,
+) -> Result<impl IntoResponse, AppError> {
+    Ok(Json(app.config.clear_log_override()?))
+}
+
+// 指标推送：GET /metrics/stream（SSE）
+// - 首帧 event: snapshot 为各路由截至该序号的累计计数，之后每 metrics.stream_interval_ms 一帧 event: delta 增量
+// - 重连时（浏览器 EventSource 自动携带 Last-Event-ID）补发错过的增量；超出保留范围时重新发送 snapshot
+// - 同时连接数达到 metrics.stream_max_subscribers 时返回 503
+pub async fn metrics_stream(
+    State(app): State<Arc<AppState>>,
+    shutdown: Option<Extension<ShutdownSignal>>,
+    headers: HeaderMap,
+) -> Result<Sse<impl Stream<Item = Result<Event, Infallible>>>, AppError> {
+    let last_event_id = headers.get("last-event-id").and_then(|v| v.to_str().ok());
+    let sub = app.stream.subscribe(last_event_id).ok_or_else(|| {
+        let max = app.config.read(|c| c.metrics.stream_max_subscribers);
+        AppError::Unavailable(format!("指标推送订阅者已达上限（{max}）"))
+    })?;
+    let stream = futures_util::stream::unfold(sub, |mut sub| async move {
+        let frame = sub.next().await?;
+        let event = Event::default()
+            .id(&frame.id)
+            .event(frame.event)
+            .data(&frame.data);
+        Some((Ok(event), sub))
+    });
+    // 优雅关闭开始时结束响应，连接随之关闭
+    let stream = stream.take_until(until_shutdown(shutdown));
+    Ok(Sse::new(stream).keep_alive(KeepAlive::default()))
+}
+
+// 时间序列查询参数：route 为路由模板（省略时合并全部路由），res 为分辨率（1s / 1m / 1h，默认 1m）
+#[derive(Deserialize)]
+pub struct HistoryQuery {
+    pub route: Option<String>,
+    pub res: Option<String>,
+}
+
+// 指标时间序列：GET /metrics/history?route=/sum&res=1m
+// points 覆盖整个保留期（最早的在前，最后一项为当前时间段），每项为该时间段内的：
+// 请求数、5xx 错误数、请求速率（每秒）、错误率（errors / requests）、平均与最大耗时（毫秒）
+pub async fn metrics_history(
+    State(app): State<Arc<AppState>>,
+    Query(q): Query<HistoryQuery>,
+) -> Result<impl IntoResponse, AppError> {
+    let res = match q.res.as_deref() {
+        None => &RESOLUTIONS[1],
+        Some(name) => Resolution::find(name).ok_or_else(|| {
+            AppError::BadRequest(format!("无效的分辨率: {name}（应为 1s、1m 或 1h）"))
+        })?,
+    };
+    let now = SystemTime::now();
+    let routes = app.metrics.snapshot();
+    let series = history::merge(
+        routes
+            .iter()
+            .filter(|(route, _)| q.route.as_ref().is_none_or(|r| r == route))
+            .map(|(_, m)| m.history().series(res, now)),
+    );
+    let points: Vec<_> = series
+        .iter()
+        .map(|b| {
+            let avg_ms = match b.requests {
+                0 => 0.0,
+                n => b.latency_sum_micros as f64 / n as f64 / 1000.0,
+            };
+            let error_rate = match b.requests {
+                0 => 0.0,
+                n => b.errors as f64 / n as f64,
+            };
+            serde_json::json!({
+                "time": rfc3339(UNIX_EPOCH + Duration::from_secs(b.start)),
+                "requests": b.requests,
+                "errors": b.errors,
+                "rate": b.requests as f64 / res.step_secs as f64,
+                "error_rate": error_rate,
+                "latency_avg_ms": avg_ms,
+                "latency_max_ms": b.latency_max_micros as f64 / 1000.0,
+            })
+        })
+        .collect();
+    Ok(Json(serde_json::json!({
+        "route": q.route,
+        "resolution": res.name,
+        "step_secs": res.step_secs,
+        "retention_secs": res.retention().as_secs(),
+        "points": points,
+    })))
+}
+
+// 最近请求筛选：status 为状态码（404）或状态类别（5xx），route 为路由模板（如 /sum，未匹配路由为 fallback），
+// limit 为最多返回条数（默认全部）
+#[derive(Deserialize)]
+pub struct RecentQuery {
+    pub status: Option<String>,
+    pub route: Option<String>,
+    pub limit: Option<usize>,
+}
+
+impl RecentQuery {
+    fn filter(&self) -> Result<Filter, AppError> {
+        let status = self.status.as_deref().map(str::parse).transpose();
+        Ok(Filter {
+            status: status.map_err(AppError::BadRequest)?,
+            route: self.route.clone(),
+        })
+    }
+}
+
+// 调试端点：GET /debug/requests 最近完成的请求，最新的在前
+pub async fn debug_requests(
+    State(app): State<Arc<AppState>>,
+    Query(q): Query<RecentQuery>,
+) -> Result<impl IntoResponse, AppError> {
+    let records = app
+        .recent
+        .snapshot(&q.filter()?, q.limit.unwrap_or(usize::MAX));
+    let records: Vec<&RequestRecord> = records.iter().map(|r| &**r).collect();
+    Ok(Json(serde_json::json!({
+        "capacity": app.recent.capacity(),
+        "requests": records,
+    })))
+}
+
+// 调试端点：GET /debug/requests/stream 以 SSE 实时推送此后完成的请求（筛选参数同 /debug/requests）
+// - event: request，data 为一条 JSON 记录
+// - event: lagged，data 为因客户端读取过慢而跳过的条数
+// 连接保持打开，直到客户端断开或优雅关闭开始（由 server::serve 服务时，此时结束响应，不占用 drain_timeout）
+pub async fn debug_requests_stream(
+    State(app): State<Arc<AppState>>,
+    shutdown: Option<Extension<ShutdownSignal>>,
+    Query(q): Query<RecentQuery>,
+) -> Result<Sse<impl Stream<Item = Result<Event, Infallible>>>, AppError> {
+    let filter = q.filter()?;
+    let rx = app.recent.subscribe();
+    let stream = futures_util::stream::unfold((rx, filter), |(mut rx, filter)| async move {
+        loop {
+            let event = match rx.recv().await {
+                Ok(r) if filter.matches(&r) => {
+                    match Event::default().event("request").json_data(&*r) {
+                        Ok(event) => event,
+                        Err(_) => continue,
+                    }
+                }
+                Ok(_) => continue,
+                Err(RecvError::Lagged(n)) => Event::default().event("lagged").data(n.to_string()),
+                Err(RecvError::Closed) => return None,
+            };
+            return Some((Ok(event), (rx, filter)));
+        }
+    });
+    // 优雅关闭开始时结束响应，连接随之关闭
+    let stream = stream.take_until(until_shutdown(shutdown));
+    Ok(Sse::new(stream).keep_alive(KeepAlive::default()))
+}
+
+// 由 server::serve 服务时在优雅关闭开始时完成；其他方式服务（无 ShutdownSignal）时不会完成
+async fn until_shutdown(signal: Option<Extension<ShutdownSignal>>) {
+    match signal {
+        Some(Extension(signal)) => signal.wait().await,
+        None => std::future::pending().await,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::parse_sum_input;
+
+    // 正常解析用例：空白/空项应被忽略
+    #[test]
+    fn parse_sum_ok() {
+        assert_eq!(parse_sum_input("1,2,3").unwrap(), 6);
+        assert_eq!(parse_sum_input(" 1 , 2 , 3 ").unwrap(), 6);
+        assert_eq!(parse_sum_input("1,,3").unwrap(), 4);
+    }
+
+    // 错误解析用例：包含非数字项，应返回携带索引信息的错误
+    #[test]
+    fn parse_sum_invalid() {
+        let err = parse_sum_input("1,x,3").unwrap_err();
+        let msg = format!("{}", err);
+        assert!(msg.contains("不是有效整数"));
+    }
+}
@@ -0,0 +1,299 @@
+// 零停机重启（仅 Unix）：
+// - systemd socket activation：读取 LISTEN_PID / LISTEN_FDS / LISTEN_FDNAMES，直接使用 systemd 传入的监听
+// - SIGUSR2 re-exec：把当前监听的文件描述符传给新启动的同一程序（RUSTDEMO_LISTEN_FDS），
+//   新进程开始接受连接后通过就绪管道通知，旧进程随后走正常的优雅关闭流程退出
+// 两种方式中监听 socket 始终处于打开状态，新旧进程交替期间连接在内核队列中排队，不会被拒绝
+use std::io;
+
+use crate::{config::ListenAddr, server::Listener};
+
+#[cfg(unix)]
+pub use imp::{notify_ready, reexec, take_inherited};
+
+#[cfg(unix)]
+type Fd = std::os::fd::OwnedFd;
+// 非 Unix 平台不存在继承的监听，InheritedListener 无法被构造
+#[cfg(not(unix))]
+type Fd = std::convert::Infallible;
+
+// 继承的监听：地址由 getsockname 得到，name 来自 LISTEN_FDNAMES（如 "api" / "admin"）
+pub struct InheritedListener {
+    pub addr: ListenAddr,
+    pub name: Option<String>,
+    fd: Fd,
+}
+
+impl InheritedListener {
+    #[cfg(unix)]
+    pub fn into_listener(self) -> io::Result<Listener> {
+        match self.addr {
+            ListenAddr::Tcp(_) => Listener::from_std_tcp(std::net::TcpListener::from(self.fd)),
+            ListenAddr::Unix(path) => {
+                Listener::from_std_unix(std::os::unix::net::UnixListener::from(self.fd), path)
+            }
+        }
+    }
+
+    #[cfg(not(unix))]
+    pub fn into_listener(self) -> io::Result<Listener> {
+        match self.fd {}
+    }
+}
+
+#[cfg(not(unix))]
+pub fn take_inherited() -> io::Result<Vec<InheritedListener>> {
+    Ok(Vec::new())
+}
+
+// 是否已把监听交给新进程（此后关闭监听时不再删除 Unix socket 文件）
+static HANDED_OFF: std::sync::atomic::AtomicBool = std::sync::atomic::AtomicBool::new(false);
+
+pub fn handed_off() -> bool {
+    HANDED_OFF.load(std::sync::atomic::Ordering::SeqCst)
+}
+
+// 通过 SIGUSR2 re-exec 启动时为旧进程的 PID（0 表示不是 re-exec 启动），由 take_inherited 记录
+static HANDOFF_PARENT: std::sync::atomic::AtomicU32 = std::sync::atomic::AtomicU32::new(0);
+
+// 交接监听给本进程的旧进程 PID；新进程据此跳过后台化并接管 PID 文件
+pub fn handoff_parent() -> Option<u32> {
+    match HANDOFF_PARENT.load(std::sync::atomic::Ordering::SeqCst) {
+        0 => None,
+        pid => Some(pid),
+    }
+}
+
+#[cfg(unix)]
+mod imp {
+    use std::{
+        fs::File,
+        io::{self, Read, Write},
+        os::{
+            fd::{AsRawFd, FromRawFd, OwnedFd, RawFd},
+            unix::process::CommandExt,
+        },
+        path::PathBuf,
+        process::Command,
+        sync::atomic::Ordering,
+        time::Duration,
+    };
+
+    use super::{InheritedListener, HANDED_OFF, HANDOFF_PARENT};
+    use crate::config::ListenAddr;
+
+    // sd_listen_fds 约定：继承的文件描述符从 3 开始连续编号
+    const LISTEN_FDS_START: RawFd = 3;
+
+    // re-exec 时使用的环境变量（不校验 PID，启动后立即清除）
+    const ENV_FDS: &str = "RUSTDEMO_LISTEN_FDS";
+    const ENV_FDNAMES: &str = "RUSTDEMO_LISTEN_FDNAMES";
+    const ENV_READY_FD: &str = "RUSTDEMO_READY_FD";
+
+    // 取出继承的监听（systemd 或 re-exec），并清除相关环境变量避免传给子进程
+    // 应在启动早期、其他线程读取环境变量之前调用
+    pub fn take_inherited() -> io::Result<Vec<InheritedListener>> {
+        let systemd = std::env::var("LISTEN_FDS")
+            .ok()
+            .filter(|_| std::env::var("LISTEN_PID").ok() == Some(std::process::id().to_string()));
+        let (count, names) = match (systemd, std::env::var(ENV_FDS).ok()) {
+            (Some(n), _) => (n, std::env::var("LISTEN_FDNAMES").ok()),
+            (None, Some(n)) => {
+                // SAFETY：getppid 总是成功
+                let parent = unsafe { libc::getppid() };
+                HANDOFF_PARENT.store(parent as u32, Ordering::SeqCst);
+                (n, std::env::var(ENV_FDNAMES).ok())
+            }
+            (None, None) => return Ok(Vec::new()),
+        };
+        for var in [
+            "LISTEN_PID",
+            "LISTEN_FDS",
+            "LISTEN_FDNAMES",
+            ENV_FDS,
+            ENV_FDNAMES,
+        ] {
+            std::env::remove_var(var);
+        }
+        let count: RawFd = count.parse().map_err(|_| {
+            io::Error::new(
+                io::ErrorKind::InvalidInput,
+                format!("无效的 LISTEN_FDS: {count}"),
+            )
+        })?;
+        let names: Vec<String> = names
+            .map(|n| n.split(':').map(String::from).collect())
+            .unwrap_or_default();
+        (0..count)
+            .map(|i| {
+                let raw = LISTEN_FDS_START + i;
+                // 继承的描述符不应再泄漏给之后启动的子进程
+                set_cloexec(raw)?;
+                // SAFETY：按 sd_listen_fds 约定，3..3+count 的描述符归本进程所有且仅在此处取得所有权
+                let fd = unsafe { OwnedFd::from_raw_fd(raw) };
+                let addr = socket_addr(&fd)?;
+                let name = names
+                    .get(i as usize)
+                    .filter(|n| !n.is_empty() && *n != "unknown")
+                    .cloned();
+                Ok(InheritedListener { addr, name, fd })
+            })
+            .collect()
+    }
+
+    // 新进程就绪（全部监听开始接受连接）后通知父进程；非 re-exec 启动时什么也不做
+    pub fn notify_ready() {
+        let Some(fd) = std::env::var(ENV_READY_FD)
+            .ok()
+            .and_then(|v| v.parse::<RawFd>().ok())
+        else {
+            return;
+        };
+        std::env::remove_var(ENV_READY_FD);
+        // SAFETY：就绪管道写端由父进程通过 re-exec 传入，仅在此处使用一次
+        let mut pipe = unsafe { File::from_raw_fd(fd) };
+        let _ = pipe.write_all(b"1");
+    }
+
+    // 以相同参数重新启动当前程序并传递监听；等待新进程就绪（或失败/超时）后返回其 PID
+    // listeners：(监听描述符, 名称)，名称用于新进程区分公共/管理监听
+    pub async fn reexec(listeners: &[(RawFd, &str)], ready_timeout: Duration) -> io::Result<u32> {
+        let n = listeners.len() as RawFd;
+        // 先复制到编号不小于 3+n+1 的描述符，避免子进程中 dup2 到 3.. 时互相覆盖
+        let dups = listeners
+            .iter()
+            .map(|(fd, _)| dup_above(*fd, LISTEN_FDS_START + n + 1))
+            .collect::<io::Result<Vec<OwnedFd>>>()?;
+        let (ready_rx, ready_tx) = pipe()?;
+        let ready_tx = {
+            let fd = dup_above(ready_tx.as_raw_fd(), LISTEN_FDS_START + n + 1)?;
+            drop(ready_tx);
+            fd
+        };
+        let sources: Vec<RawFd> = dups.iter().map(AsRawFd::as_raw_fd).collect();
+        let ready_src = ready_tx.as_raw_fd();
+        let ready_dst = LISTEN_FDS_START + n;
+        let names: Vec<&str> = listeners.iter().map(|(_, name)| *name).collect();
+
+        let mut cmd = Command::new(std::env::current_exe()?);
+        cmd.args(std::env::args_os().skip(1))
+            .env(ENV_FDS, n.to_string())
+            .env(ENV_FDNAMES, names.join(":"))
+            .env(ENV_READY_FD, ready_dst.to_string())
+            .env_remove("LISTEN_PID")
+            .env_remove("LISTEN_FDS")
+            .env_remove("LISTEN_FDNAMES");
+        // SAFETY：pre_exec 闭包在 fork 后的子进程中执行，只调用异步信号安全的 dup2
+        unsafe {
+            cmd.pre_exec(move || {
+                for (i, src) in sources.iter().enumerate() {
+                    if libc::dup2(*src, LISTEN_FDS_START + i as RawFd) < 0 {
+                        return Err(io::Error::last_os_error());
+                    }
+                }
+                if libc::dup2(ready_src, ready_dst) < 0 {
+                    return Err(io::Error::last_os_error());
+                }
+                Ok(())
+            });
+        }
+        let mut child = cmd.spawn()?;
+        drop(dups);
+        drop(ready_tx);
+
+        // 读取就绪字节：子进程退出时写端关闭，read 返回 0
+        let mut ready_rx = File::from(ready_rx);
+        let wait = tokio::task::spawn_blocking(move || {
+            let mut buf = [0u8; 1];
+            ready_rx.read(&mut buf).map(|n| n == 1)
+        });
+        let ready = match tokio::time::timeout(ready_timeout, wait).await {
+            Ok(Ok(Ok(true))) => true,
+            Ok(Ok(Ok(false))) => false,
+            Ok(Ok(Err(e))) => return Err(e),
+            Ok(Err(e)) => return Err(io::Error::other(e)),
+            Err(_) => {
+                let _ = child.kill();
+                let _ = child.wait();
+                return Err(io::Error::new(
+                    io::ErrorKind::TimedOut,
+                    "新进程未在期限内就绪",
+                ));
+            }
+        };
+        if !ready {
+            let status = child.wait()?;
+            return Err(io::Error::other(format!("新进程启动失败: {status}")));
+        }
+        HANDED_OFF.store(true, Ordering::SeqCst);
+        Ok(child.id())
+    }
+
+    fn set_cloexec(fd: RawFd) -> io::Result<()> {
+        // SAFETY：仅修改描述符标志位
+        let flags = unsafe { libc::fcntl(fd, libc::F_GETFD) };
+        if flags < 0 || unsafe { libc::fcntl(fd, libc::F_SETFD, flags | libc::FD_CLOEXEC) } < 0 {
+            return Err(io::Error::last_os_error());
+        }
+        Ok(())
+    }
+
+    // 复制描述符到不小于 min 的编号（带 CLOEXEC）
+    fn dup_above(fd: RawFd, min: RawFd) -> io::Result<OwnedFd> {
+        // SAFETY：F_DUPFD_CLOEXEC 返回新的、由本进程独占的描述符
+        let new = unsafe { libc::fcntl(fd, libc::F_DUPFD_CLOEXEC, min) };
+        if new < 0 {
+            return Err(io::Error::last_os_error());
+        }
+        Ok(unsafe { OwnedFd::from_raw_fd(new) })
+    }
+
+    fn pipe() -> io::Result<(OwnedFd, OwnedFd)> {
+        let mut fds = [0 as RawFd; 2];
+        // SAFETY：pipe 成功时写入两个新的描述符
+        if unsafe { libc::pipe(fds.as_mut_ptr()) } < 0 {
+            return Err(io::Error::last_os_error());
+        }
+        let (rx, tx) = unsafe { (OwnedFd::from_raw_fd(fds[0]), OwnedFd::from_raw_fd(fds[1])) };
+        set_cloexec(rx.as_raw_fd())?;
+        set_cloexec(tx.as_raw_fd())?;
+        Ok((rx, tx))
+    }
+
+    // 通过 getsockname 判断继承的 socket 类型与地址
+    fn socket_addr(fd: &OwnedFd) -> io::Result<ListenAddr> {
+        let mut storage: libc::sockaddr_storage = unsafe { std::mem::zeroed() };
+        let mut len = std::mem::size_of::<libc::sockaddr_storage>() as libc::socklen_t;
+        // SAFETY：storage 足够容纳任意地址族
+        let rc = unsafe {
+            libc::getsockname(
+                fd.as_raw_fd(),
+                &mut storage as *mut _ as *mut libc::sockaddr,
+                &mut len,
+            )
+        };
+        if rc < 0 {
+            return Err(io::Error::last_os_error());
+        }
+        let borrowed = fd.try_clone()?;
+        match storage.ss_family as libc::c_int {
+            libc::AF_INET | libc::AF_INET6 => std::net::TcpListener::from(borrowed)
+                .local_addr()
+                .map(ListenAddr::Tcp),
+            libc::AF_UNIX => {
+                let listener = std::os::unix::net::UnixListener::from(borrowed);
+                let addr = listener.local_addr()?;
+                let path = addr.as_pathname().map(PathBuf::from).ok_or_else(|| {
+                    io::Error::new(io::ErrorKind::InvalidInput, "不支持匿名 Unix socket")
+                })?;
+                Ok(ListenAddr::Unix(path))
+            }
+            family => Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                format!(
+                    "继承的描述符 {} 不是受支持的 socket（family {family}）",
+                    fd.as_raw_fd()
+                ),
+            )),
+        }
+    }
+}
@@ -0,0 +1,81 @@
+// healthcheck 子命令：向已配置的地址发送 GET /health，不依赖镜像中的 curl
+// 目标地址：配置了管理监听时取第一个管理监听，否则取第一个公共监听；
+// 0.0.0.0 / [::] 等通配地址改为本机回环地址
+use std::{
+    io::{self, Read, Write},
+    net::{IpAddr, Ipv4Addr, Ipv6Addr, SocketAddr},
+    time::Duration,
+};
+
+use crate::config::{Config, ListenAddr};
+
+pub fn target(cfg: &Config) -> ListenAddr {
+    let addr = cfg
+        .admin
+        .listen
+        .first()
+        .cloned()
+        .unwrap_or_else(|| cfg.listeners().remove(0));
+    match addr {
+        ListenAddr::Tcp(a) if a.ip().is_unspecified() => {
+            let ip = match a.ip() {
+                IpAddr::V4(_) => IpAddr::V4(Ipv4Addr::LOCALHOST),
+                IpAddr::V6(_) => IpAddr::V6(Ipv6Addr::LOCALHOST),
+            };
+            ListenAddr::Tcp(SocketAddr::new(ip, a.port()))
+        }
+        other => other,
+    }
+}
+
+// 请求 /health 并返回响应状态码；连接、读写均受 timeout 限制
+pub fn probe(addr: &ListenAddr, timeout: Duration) -> io::Result<u16> {
+    let req = b"GET /health HTTP/1.1\r\nHost: localhost\r\nConnection: close\r\n\r\n";
+    let mut res = Vec::new();
+    match addr {
+        ListenAddr::Tcp(a) => {
+            let mut stream = std::net::TcpStream::connect_timeout(a, timeout)?;
+            stream.set_read_timeout(Some(timeout))?;
+            stream.set_write_timeout(Some(timeout))?;
+            stream.write_all(req)?;
+            stream.read_to_end(&mut res)?;
+        }
+        #[cfg(unix)]
+        ListenAddr::Unix(path) => {
+            let mut stream = std::os::unix::net::UnixStream::connect(path)?;
+            stream.set_read_timeout(Some(timeout))?;
+            stream.set_write_timeout(Some(timeout))?;
+            stream.write_all(req)?;
+            stream.read_to_end(&mut res)?;
+        }
+        #[cfg(not(unix))]
+        ListenAddr::Unix(_) => {
+            return Err(io::Error::new(
+                io::ErrorKind::Unsupported,
+                "当前平台不支持 Unix socket",
+            ))
+        }
+    }
+    // 状态行形如 "HTTP/1.1 200 OK"
+    String::from_utf8_lossy(&res)
+        .split_whitespace()
+        .nth(1)
+        .and_then(|code| code.parse().ok())
+        .ok_or_else(|| io::Error::new(io::ErrorKind::InvalidData, "无效的 HTTP 响应"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // 优先管理监听；通配地址改为回环地址
+    #[test]
+    fn picks_target() {
+        let mut cfg = Config::default();
+        cfg.apply_args(["--listen=0.0.0.0:8080,unix:/tmp/a.sock"])
+            .unwrap();
+        assert_eq!(target(&cfg).to_string(), "127.0.0.1:8080");
+        cfg.apply_args(["--admin-listen=[::]:9000"]).unwrap();
+        assert_eq!(target(&cfg).to_string(), "[::1]:9000");
+    }
+}
@@ -0,0 +1,211 @@
+// 指标时间序列（/metrics/history）：
+// - History：每个路由模板一份，按固定分辨率降采样记录请求数、5xx 错误数与耗时（总和、最大值）：
+//   1s 保留 5 分钟、1m 保留 24 小时、1h 保留 30 天
+// - 每个分辨率是固定长度的环形数组，槽位按 时间 / 分辨率 取模定位，槽内记下所属时间段的起点，
+//   起点不符的槽位（已过期）在写入时重置、读取时视为空；
+//   内存占用为 路由数 × 槽位总数（2460），与请求量和运行时长无关
+use std::{
+    sync::Mutex,
+    time::{Duration, SystemTime, UNIX_EPOCH},
+};
+
+pub struct Resolution {
+    pub name: &'static str,
+    pub step_secs: u64,
+    pub slots: usize,
+}
+
+impl Resolution {
+    pub fn retention(&self) -> Duration {
+        Duration::from_secs(self.step_secs * self.slots as u64)
+    }
+
+    pub fn find(name: &str) -> Option<&'static Resolution> {
+        RESOLUTIONS.iter().find(|r| r.name == name)
+    }
+}
+
+pub const RESOLUTIONS: [Resolution; 3] = [
+    Resolution {
+        name: "1s",
+        step_secs: 1,
+        slots: 300,
+    },
+    Resolution {
+        name: "1m",
+        step_secs: 60,
+        slots: 1440,
+    },
+    Resolution {
+        name: "1h",
+        step_secs: 3600,
+        slots: 720,
+    },
+];
+
+// 一个时间段内的统计；start 为时间段起点（Unix 秒）
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct Bucket {
+    pub start: u64,
+    pub requests: u64,
+    pub errors: u64,
+    pub latency_sum_micros: u64,
+    pub latency_max_micros: u64,
+}
+
+impl Bucket {
+    fn empty(start: u64) -> Self {
+        Self {
+            start,
+            ..Self::default()
+        }
+    }
+
+    fn record(&mut self, error: bool, micros: u64) {
+        self.requests += 1;
+        self.errors += u64::from(error);
+        self.latency_sum_micros += micros;
+        self.latency_max_micros = self.latency_max_micros.max(micros);
+    }
+
+    pub fn merge(&mut self, other: &Bucket) {
+        self.requests += other.requests;
+        self.errors += other.errors;
+        self.latency_sum_micros += other.latency_sum_micros;
+        self.latency_max_micros = self.latency_max_micros.max(other.latency_max_micros);
+    }
+}
+
+pub struct History {
+    // 与 RESOLUTIONS 一一对应
+    tiers: Mutex<Vec<Vec<Bucket>>>,
+}
+
+impl Default for History {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl History {
+    pub fn new() -> Self {
+        let tiers = RESOLUTIONS
+            .iter()
+            .map(|r| vec![Bucket::default(); r.slots])
+            .collect();
+        Self {
+            tiers: Mutex::new(tiers),
+        }
+    }
+
+    // 记录一个已完成的请求；error 为是否 5xx
+    pub fn record(&self, error: bool, elapsed: Duration) {
+        self.record_at(SystemTime::now(), error, elapsed);
+    }
+
+    pub fn record_at(&self, now: SystemTime, error: bool, elapsed: Duration) {
+        let now = unix_secs(now);
+        let micros = elapsed.as_micros() as u64;
+        let mut tiers = self.tiers.lock().unwrap();
+        for (res, slots) in RESOLUTIONS.iter().zip(tiers.iter_mut()) {
+            let start = now - now % res.step_secs;
+            let slot = &mut slots[(now / res.step_secs) as usize % res.slots];
+            if slot.start != start {
+                *slot = Bucket::empty(start);
+            }
+            slot.record(error, micros);
+        }
+    }
+
+    // 保留期内的全部时间段，最早的在前，最后一项为当前（未结束的）时间段；无请求的时间段为空桶
+    pub fn series(&self, res: &Resolution, now: SystemTime) -> Vec<Bucket> {
+        let idx = RESOLUTIONS
+            .iter()
+            .position(|r| r.name == res.name)
+            .expect("unknown resolution");
+        let now = unix_secs(now);
+        let current = now / res.step_secs;
+        let tiers = self.tiers.lock().unwrap();
+        let slots = &tiers[idx];
+        (0..res.slots as u64)
+            .rev()
+            .filter_map(|back| current.checked_sub(back))
+            .map(|n| {
+                let start = n * res.step_secs;
+                let slot = slots[n as usize % res.slots];
+                if slot.start == start {
+                    slot
+                } else {
+                    Bucket::empty(start)
+                }
+            })
+            .collect()
+    }
+}
+
+// 合并多个路由的序列（各序列需为同一分辨率、同一时刻取得）
+pub fn merge(series: impl IntoIterator<Item = Vec<Bucket>>) -> Vec<Bucket> {
+    let mut series = series.into_iter();
+    let Some(mut merged) = series.next() else {
+        return Vec::new();
+    };
+    for other in series {
+        for (a, b) in merged.iter_mut().zip(&other) {
+            a.merge(b);
+        }
+    }
+    merged
+}
+
+fn unix_secs(t: SystemTime) -> u64 {
+    t.duration_since(UNIX_EPOCH).unwrap_or_default().as_secs()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn at(secs: u64) -> SystemTime {
+        UNIX_EPOCH + Duration::from_secs(secs)
+    }
+
+    // 各分辨率按时间段聚合，过期的槽位被覆盖且不出现在序列中
+    #[test]
+    fn downsamples_and_expires() {
+        let h = History::new();
+        let base = 1_700_000_000 - 1_700_000_000 % 3600;
+        h.record_at(at(base), false, Duration::from_millis(10));
+        h.record_at(at(base + 1), true, Duration::from_millis(30));
+        h.record_at(at(base + 61), false, Duration::from_millis(20));
+
+        let secs = h.series(&RESOLUTIONS[0], at(base + 61));
+        assert_eq!(secs.len(), 300);
+        assert_eq!(secs.last().unwrap().start, base + 61);
+        assert_eq!(secs.last().unwrap().requests, 1);
+        assert_eq!(secs[300 - 61].errors, 1);
+
+        let mins = h.series(&RESOLUTIONS[1], at(base + 61));
+        let first = mins[1438];
+        assert_eq!(first.start, base);
+        assert_eq!((first.requests, first.errors), (2, 1));
+        assert_eq!(first.latency_sum_micros, 40_000);
+        assert_eq!(first.latency_max_micros, 30_000);
+
+        let hours = h.series(&RESOLUTIONS[2], at(base + 61));
+        assert_eq!(hours.last().unwrap().requests, 3);
+
+        // 5 分钟后 1s 序列不再包含这些请求；同一槽位的新时间段从零开始
+        let later = base + 361;
+        assert!(h
+            .series(&RESOLUTIONS[0], at(later))
+            .iter()
+            .all(|b| b.requests == 0));
+        h.record_at(at(later), false, Duration::from_millis(1));
+        let secs = h.series(&RESOLUTIONS[0], at(later));
+        assert_eq!(secs.iter().map(|b| b.requests).sum::<u64>(), 1);
+
+        let merged = merge([mins.clone(), mins]);
+        assert_eq!(merged[1438].requests, 4);
+        assert_eq!(merged[1438].latency_max_micros, 30_000);
+    }
+}
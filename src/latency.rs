@@ -0,0 +1,220 @@
+// 耗时分位数：
+// - LogHistogram：HDR 风格的对数-线性直方图（以微秒计），每个 2 的幂区间再均分为 32 个子桶，
+//   任意取值的相对误差约 3%，内存占用与取值范围的对数成正比
+// - LatencyWindow：滑动时间窗口，由若干个按时间轮转的 LogHistogram 组成；
+//   分位数只反映最近约 window 时长内的请求，而非启动以来的全部请求
+use std::{
+    collections::VecDeque,
+    sync::Mutex,
+    time::{Duration, Instant},
+};
+
+// 每个 2 的幂区间的子桶数为 2^SUB_BITS
+const SUB_BITS: u32 = 5;
+const SUB_COUNT: u64 = 1 << SUB_BITS;
+
+// 窗口被划分的时间片数量：过期按时间片整体丢弃
+const SLOTS: u32 = 6;
+
+#[derive(Debug, Clone, Default)]
+pub struct LogHistogram {
+    counts: Vec<u64>,
+    count: u64,
+    max: u64,
+}
+
+impl LogHistogram {
+    pub fn record(&mut self, micros: u64) {
+        let idx = index(micros);
+        if idx >= self.counts.len() {
+            self.counts.resize(idx + 1, 0);
+        }
+        self.counts[idx] += 1;
+        self.count += 1;
+        self.max = self.max.max(micros);
+    }
+
+    pub fn merge(&mut self, other: &LogHistogram) {
+        if other.counts.len() > self.counts.len() {
+            self.counts.resize(other.counts.len(), 0);
+        }
+        for (a, b) in self.counts.iter_mut().zip(&other.counts) {
+            *a += b;
+        }
+        self.count += other.count;
+        self.max = self.max.max(other.max);
+    }
+
+    pub fn count(&self) -> u64 {
+        self.count
+    }
+
+    pub fn max(&self) -> u64 {
+        self.max
+    }
+
+    // 分位数（q 取 0..=1），返回所在子桶的上界（不超过最大值）；无数据时返回 0
+    pub fn quantile(&self, q: f64) -> u64 {
+        if self.count == 0 {
+            return 0;
+        }
+        let rank = ((q.clamp(0.0, 1.0) * self.count as f64).ceil() as u64).max(1);
+        let mut seen = 0;
+        for (idx, n) in self.counts.iter().enumerate() {
+            seen += n;
+            if seen >= rank {
+                return upper_bound(idx).min(self.max);
+            }
+        }
+        self.max
+    }
+}
+
+// 取值所在子桶：小于 SUB_COUNT 的取值各占一个桶，其余按最高位所在区间与其后 SUB_BITS 位定位
+fn index(v: u64) -> usize {
+    if v < SUB_COUNT {
+        return v as usize;
+    }
+    let mag = 63 - v.leading_zeros();
+    let sub = (v >> (mag - SUB_BITS)) & (SUB_COUNT - 1);
+    (((mag - SUB_BITS + 1) as u64) << SUB_BITS | sub) as usize
+}
+
+fn upper_bound(idx: usize) -> u64 {
+    let idx = idx as u64;
+    if idx < SUB_COUNT {
+        return idx;
+    }
+    let mag = (idx >> SUB_BITS) as u32 - 1 + SUB_BITS;
+    let width = 1u64 << (mag - SUB_BITS);
+    let lower = (1u64 << mag) | ((idx & (SUB_COUNT - 1)) << (mag - SUB_BITS));
+    lower + width - 1
+}
+
+// 分位数摘要（毫秒）
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct Percentiles {
+    pub count: u64,
+    pub p50_ms: f64,
+    pub p90_ms: f64,
+    pub p99_ms: f64,
+    pub max_ms: f64,
+}
+
+pub struct LatencyWindow {
+    base: Instant,
+    slot: Duration,
+    // (时间片序号, 该时间片内的直方图)，按时间先后排列
+    slots: Mutex<VecDeque<(u64, LogHistogram)>>,
+}
+
+impl LatencyWindow {
+    pub fn new(window: Duration) -> Self {
+        Self {
+            base: Instant::now(),
+            slot: (window / SLOTS).max(Duration::from_millis(1)),
+            slots: Mutex::new(VecDeque::new()),
+        }
+    }
+
+    pub fn window(&self) -> Duration {
+        self.slot * SLOTS
+    }
+
+    pub fn record(&self, d: Duration) {
+        self.record_at(d, Instant::now());
+    }
+
+    pub fn percentiles(&self) -> Percentiles {
+        self.percentiles_at(Instant::now())
+    }
+
+    fn epoch(&self, now: Instant) -> u64 {
+        (now.saturating_duration_since(self.base).as_nanos() / self.slot.as_nanos()) as u64
+    }
+
+    fn record_at(&self, d: Duration, now: Instant) {
+        let epoch = self.epoch(now);
+        let mut slots = self.slots.lock().unwrap();
+        if slots.back().is_none_or(|(e, _)| *e != epoch) {
+            slots.push_back((epoch, LogHistogram::default()));
+        }
+        while slots
+            .front()
+            .is_some_and(|(e, _)| *e + u64::from(SLOTS) <= epoch)
+        {
+            slots.pop_front();
+        }
+        let micros = u64::try_from(d.as_micros()).unwrap_or(u64::MAX);
+        slots.back_mut().unwrap().1.record(micros);
+    }
+
+    fn percentiles_at(&self, now: Instant) -> Percentiles {
+        let epoch = self.epoch(now);
+        let mut merged = LogHistogram::default();
+        for (e, h) in self.slots.lock().unwrap().iter() {
+            if *e + u64::from(SLOTS) > epoch {
+                merged.merge(h);
+            }
+        }
+        let ms = |micros: u64| micros as f64 / 1000.0;
+        Percentiles {
+            count: merged.count(),
+            p50_ms: ms(merged.quantile(0.5)),
+            p90_ms: ms(merged.quantile(0.9)),
+            p99_ms: ms(merged.quantile(0.99)),
+            max_ms: ms(merged.max()),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // 子桶上界不小于取值，且相对误差在 1/SUB_COUNT 以内
+    #[test]
+    fn bucket_precision() {
+        for v in [0, 1, 31, 32, 33, 63, 64, 1000, 123_456, 10_000_000_007] {
+            let ub = upper_bound(index(v));
+            assert!(ub >= v, "{v} -> {ub}");
+            assert!(
+                (ub - v) as f64 <= v as f64 / SUB_COUNT as f64,
+                "{v} -> {ub}"
+            );
+        }
+        assert_eq!(index(32), 32);
+        assert_eq!(index(64), 64);
+    }
+
+    #[test]
+    fn quantiles() {
+        let mut h = LogHistogram::default();
+        for v in 1..=1000 {
+            h.record(v);
+        }
+        let p50 = h.quantile(0.5);
+        assert!((500..=516).contains(&p50), "{p50}");
+        let p99 = h.quantile(0.99);
+        assert!((990..=1000).contains(&p99), "{p99}");
+        assert_eq!(h.quantile(1.0), 1000);
+        assert_eq!(LogHistogram::default().quantile(0.5), 0);
+    }
+
+    // 超出窗口的时间片不再参与分位数计算
+    #[test]
+    fn window_expires_old_slots() {
+        let w = LatencyWindow::new(Duration::from_secs(60));
+        let t0 = w.base;
+        w.record_at(Duration::from_millis(500), t0);
+        w.record_at(Duration::from_millis(5), t0 + Duration::from_secs(30));
+        let p = w.percentiles_at(t0 + Duration::from_secs(30));
+        assert_eq!(p.count, 2);
+        assert_eq!(p.max_ms, 500.0);
+
+        let p = w.percentiles_at(t0 + Duration::from_secs(65));
+        assert_eq!(p.count, 1);
+        assert_eq!(p.max_ms, 5.0);
+        assert_eq!(w.percentiles_at(t0 + Duration::from_secs(200)).count, 0);
+    }
+}
@@ -0,0 +1,40 @@
+// 演示：基于 Axum 的高并发 HTTP 服务 + 强类型 JSON + 并发任务
+// 项目概览：
+// - 框架：Tokio 异步运行时 + Axum Web 框架（无阻塞 I/O，路由清晰）
+// - 中间件：压缩、CORS、请求追踪、超时（提升可观测性与健壮性）
+// - 端点：/、/health、/sum、/echo、/parallel、/metrics、/metrics/history、/metrics/stream、/admin/config、/admin/log-level、/debug/requests
+// - 工程特性：统一错误模型、优雅关闭、纯函数单元测试
+//
+// 库入口：对外提供 build_app / AppBuilder，便于嵌入其他 axum 服务或在集成测试中驱动；
+// 二进制 main.rs 仅负责加载配置、初始化日志、（可选）后台运行与启动监听。
+pub mod access_log;
+pub mod app;
+pub mod config;
+pub mod daemon;
+pub mod error;
+pub mod extract;
+pub mod handlers;
+pub mod handoff;
+pub mod healthcheck;
+pub mod history;
+pub mod latency;
+pub mod logging;
+pub mod metrics;
+pub mod metrics_stream;
+#[cfg(feature = "otel")]
+pub mod otel;
+pub mod persist;
+pub mod recent;
+pub mod reload;
+pub mod request_id;
+pub mod runtime_metrics;
+pub mod server;
+pub mod slow;
+pub mod state;
+pub mod statsd;
+pub mod toml;
+
+pub use app::{build_app, AppBuilder};
+pub use config::Config;
+pub use error::AppError;
+pub use state::AppState;
@@ -0,0 +1,189 @@
+// JSON 日志（--log-format json）：每个事件输出一行 JSON 对象，便于日志管道直接解析
+// - 固定字段：timestamp（RFC 3339，UTC）、level、target、span（当前 span 名称）
+// - 当前事件所在全部 span 的字段（如请求 span 的 method、path、request_id）与事件字段
+//   （如 message、status、latency_ms）平铺在同一层；同名时内层 span 覆盖外层，事件字段覆盖 span 字段
+// tracing-subscriber 的 json 特性依赖 tracing-serde，这里以自定义 Layer 实现
+use std::{fmt, io::Write};
+
+use serde_json::{Map, Value};
+use tracing::{
+    field::{Field, Visit},
+    span::{Attributes, Id, Record},
+    Event, Subscriber,
+};
+use tracing_subscriber::{
+    fmt::{
+        format::Writer,
+        time::{FormatTime, SystemTime},
+        MakeWriter,
+    },
+    layer::Context,
+    registry::LookupSpan,
+    Layer,
+};
+
+pub struct JsonLayer<W> {
+    make_writer: W,
+}
+
+impl<W> JsonLayer<W>
+where
+    W: for<'a> MakeWriter<'a> + 'static,
+{
+    pub fn new(make_writer: W) -> Self {
+        Self { make_writer }
+    }
+}
+
+// 保存在 span 扩展中的字段
+struct SpanFields(Map<String, Value>);
+
+struct JsonVisitor<'a>(&'a mut Map<String, Value>);
+
+impl JsonVisitor<'_> {
+    fn insert(&mut self, field: &Field, value: impl Into<Value>) {
+        self.0.insert(field.name().to_string(), value.into());
+    }
+}
+
+impl Visit for JsonVisitor<'_> {
+    fn record_f64(&mut self, field: &Field, value: f64) {
+        self.insert(field, value);
+    }
+
+    fn record_i64(&mut self, field: &Field, value: i64) {
+        self.insert(field, value);
+    }
+
+    fn record_u64(&mut self, field: &Field, value: u64) {
+        self.insert(field, value);
+    }
+
+    fn record_bool(&mut self, field: &Field, value: bool) {
+        self.insert(field, value);
+    }
+
+    fn record_str(&mut self, field: &Field, value: &str) {
+        self.insert(field, value);
+    }
+
+    fn record_error(&mut self, field: &Field, value: &(dyn std::error::Error + 'static)) {
+        self.insert(field, value.to_string());
+    }
+
+    // 以 % 记录的字段（Display）同样经由此处，取其格式化结果
+    fn record_debug(&mut self, field: &Field, value: &dyn fmt::Debug) {
+        self.insert(field, format!("{value:?}"));
+    }
+}
+
+impl<S, W> Layer<S> for JsonLayer<W>
+where
+    S: Subscriber + for<'a> LookupSpan<'a>,
+    W: for<'a> MakeWriter<'a> + 'static,
+{
+    fn on_new_span(&self, attrs: &Attributes<'_>, id: &Id, ctx: Context<'_, S>) {
+        let Some(span) = ctx.span(id) else { return };
+        let mut fields = Map::new();
+        attrs.record(&mut JsonVisitor(&mut fields));
+        span.extensions_mut().insert(SpanFields(fields));
+    }
+
+    fn on_record(&self, id: &Id, values: &Record<'_>, ctx: Context<'_, S>) {
+        let Some(span) = ctx.span(id) else { return };
+        let mut extensions = span.extensions_mut();
+        if let Some(fields) = extensions.get_mut::<SpanFields>() {
+            values.record(&mut JsonVisitor(&mut fields.0));
+        }
+    }
+
+    fn on_event(&self, event: &Event<'_>, ctx: Context<'_, S>) {
+        let meta = event.metadata();
+        let mut obj = Map::new();
+        if let Some(scope) = ctx.event_scope(event) {
+            for span in scope.from_root() {
+                if let Some(fields) = span.extensions().get::<SpanFields>() {
+                    obj.extend(fields.0.clone());
+                }
+                obj.insert("span".into(), span.name().into());
+            }
+        }
+        event.record(&mut JsonVisitor(&mut obj));
+
+        let mut timestamp = String::new();
+        let _ = SystemTime.format_time(&mut Writer::new(&mut timestamp));
+        obj.insert("timestamp".into(), timestamp.into());
+        obj.insert("level".into(), meta.level().as_str().into());
+        obj.insert("target".into(), meta.target().into());
+
+        let Ok(mut line) = serde_json::to_vec(&obj) else {
+            return;
+        };
+        line.push(b'\n');
+        let _ = self.make_writer.make_writer_for(meta).write_all(&line);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::{Arc, Mutex};
+
+    use tracing_subscriber::layer::SubscriberExt;
+
+    use super::*;
+
+    #[derive(Clone, Default)]
+    struct Buf(Arc<Mutex<Vec<u8>>>);
+
+    impl Write for Buf {
+        fn write(&mut self, data: &[u8]) -> std::io::Result<usize> {
+            self.0.lock().unwrap().write(data)
+        }
+
+        fn flush(&mut self) -> std::io::Result<()> {
+            Ok(())
+        }
+    }
+
+    // 每个事件一行 JSON：span 字段与事件字段平铺，固定字段不被覆盖
+    #[test]
+    fn events_carry_span_fields() {
+        let buf = Buf::default();
+        let out = buf.clone();
+        let subscriber = tracing_subscriber::registry().with(JsonLayer::new(move || out.clone()));
+        tracing::subscriber::with_default(subscriber, || {
+            tracing::info!(port = 3000u16, "starting");
+            let span = tracing::info_span!(
+                "request",
+                method = "GET",
+                path = %"/sum",
+                request_id = tracing::field::Empty,
+            );
+            span.record("request_id", "req-1");
+            let _guard = span.enter();
+            tracing::warn!(status = 400u16, latency_ms = 1.5, level = "x", "done");
+        });
+
+        let text = String::from_utf8(buf.0.lock().unwrap().clone()).unwrap();
+        let lines: Vec<Value> = text
+            .lines()
+            .map(|l| serde_json::from_str(l).unwrap())
+            .collect();
+        assert_eq!(lines.len(), 2);
+        assert_eq!(lines[0]["message"], "starting");
+        assert_eq!(lines[0]["port"], 3000);
+        assert_eq!(lines[0]["level"], "INFO");
+        assert!(lines[0].get("span").is_none());
+        assert!(lines[0]["timestamp"].as_str().unwrap().ends_with('Z'));
+
+        let e = &lines[1];
+        assert_eq!(e["span"], "request");
+        assert_eq!(e["method"], "GET");
+        assert_eq!(e["path"], "/sum");
+        assert_eq!(e["request_id"], "req-1");
+        assert_eq!(e["status"], 400);
+        assert_eq!(e["latency_ms"], 1.5);
+        assert_eq!(e["level"], "WARN");
+        assert_eq!(e["target"], module_path!());
+    }
+}
@@ -1,260 +1,475 @@
-// 演示：基于 Axum 的高并发 HTTP 服务 + 强类型 JSON + 并发任务
-// 项目概览：
-// - 框架：Tokio 异步运行时 + Axum Web 框架（无阻塞 I/O，路由清晰）
-// - 中间件：压缩、CORS、请求追踪、超时（提升可观测性与健壮性）
-// - 端点：/、/health、/sum、/echo、/parallel、/metrics
-// - 工程特性：统一错误模型、优雅关闭、纯函数单元测试
-use std::{net::SocketAddr, sync::Arc, time::Duration};
+// rustdemo 启动器：加载配置、初始化日志、（可选）后台运行、绑定监听并启动服务；另提供 healthcheck 子命令
+// 路由、状态与处理器均位于库 crate（见 lib.rs）
+use std::{path::Path, sync::Arc, time::Duration};
 
-// Axum（路由/提取器/响应类型）：定义 HTTP 端点与参数解析
-use axum::{
-    extract::{Query, State},
-    http::StatusCode,
-    response::IntoResponse,
-    routing::{get, post},
-    Json, Router,
-};
-// Serde（序列化/反序列化）：类型安全地映射请求/响应 JSON
-use serde::{Deserialize, Serialize};
-// Tokio（异步运行时）：管理并发任务与计时
-use tokio::{task::JoinSet, time::sleep};
-// tracing（结构化日志）：输出服务启动与请求追踪信息
-use tracing::{info, Level};
-use tracing_subscriber::FmtSubscriber;
-// 错误与中间件
-use thiserror::Error;
-use tower_http::{cors::CorsLayer, trace::TraceLayer};
-// 并发计数器与时间
-use std::sync::atomic::{AtomicU64, Ordering};
-use std::time::Instant;
-use tower_http::services::ServeDir;
-// 应用状态（用于指标与观测）
-// - start：服务启动时间（计算运行时长）
-// - hits_*：各端点访问计数（AtomicU64 并发安全、开销低）
-struct AppState {
-    start: Instant,
-    hits_root: AtomicU64,
-    hits_health: AtomicU64,
-    hits_sum: AtomicU64,
-    hits_echo: AtomicU64,
-    hits_parallel: AtomicU64,
-}
-
-// 健康检查返回体（简单 JSON）
-#[derive(Serialize)]
-struct Health {
-    status: &'static str,
-}
+use tokio::{sync::watch, task::JoinSet};
 
-// 求和查询参数：以逗号分隔的整数字符串（如 "1,2,3"）
-#[derive(Deserialize)]
-struct SumQuery {
-    nums: String,
-}
-
-// 回显请求体（POST JSON）：{"message": "..."}，用于演示 JSON 解析
-#[derive(Deserialize, Serialize)]
-struct EchoBody {
-    message: String,
-}
+// tracing（结构化日志）：输出服务启动与请求追踪信息
+use tracing::{error, info};
+use tracing_subscriber::{layer::SubscriberExt, util::SubscriberInitExt, EnvFilter, Layer as _};
 
-// 并发示例返回项：索引、计算值（平方）、模拟耗时毫秒
-#[derive(Serialize)]
-struct ParallelResult {
-    index: usize,
-    value: usize,
-    ms: u64,
-}
+use rustdemo::{
+    access_log::AccessLog,
+    config::{Config, ConfigError, ConfigLoader, ListenAddr, LogFormat, USAGE},
+    daemon::{self, Pidfile},
+    handoff, healthcheck,
+    logging::JsonLayer,
+    metrics::RequestMetrics,
+    persist,
+    reload::{self, RuntimeConfig},
+    server::{self, DrainStats, Listener, ServeOptions},
+    slow::SpanTimingLayer,
+    statsd::Statsd,
+    AppBuilder,
+};
 
 // 程序入口：
-// 1) 初始化日志（INFO 级别）
-// 2) 构建 Router：注册端点、注入状态、挂载中间件
-// 3) 绑定监听地址，启用优雅关闭（Ctrl+C）
-#[tokio::main]
-async fn main() {
-    let subscriber = FmtSubscriber::builder()
-        .with_max_level(Level::INFO)
-        .with_env_filter("info")
-        .finish();
-    let _ = tracing::subscriber::set_global_default(subscriber);
+// 1) 加载配置（默认值 < rustdemo.toml < RUSTDEMO_* 环境变量 < 命令行参数）；healthcheck 子命令探测后直接退出
+// 2) 按需后台运行并写入 PID 文件（须在创建 Tokio 运行时之前），初始化日志（级别由 log_level 决定，可热加载；格式由 log_format 决定）
+// 3) 构建 Router：注册端点、注入状态、挂载中间件
+// 4) 绑定全部监听（公共 / 管理，TCP / Unix socket；优先复用 systemd 或旧进程传入的监听）；后台监听配置文件变化
+// 5) 收到 SIGINT / SIGTERM / SIGQUIT 后优雅关闭，超过 drain_timeout 仍未完成的请求被强制断开
+// 6) 收到 SIGUSR2 时启动新进程接管监听，新进程就绪后按第 5 步退出
+fn main() {
+    // 继承的监听需在其他任务读取环境变量之前取出（会清除 LISTEN_* 环境变量）
+    let inherited = handoff::take_inherited();
+    let mut args: Vec<String> = std::env::args().skip(1).collect();
+    let healthcheck = args.first().is_some_and(|a| a == "healthcheck");
+    if healthcheck {
+        args.remove(0);
+    }
+    let loaded = ConfigLoader::new(args, |k| std::env::var(k).ok())
+        .and_then(|loader| Ok((loader.load()?, loader)));
+    let (cfg, loader) = match loaded {
+        Ok(v) => v,
+        Err(ConfigError::Help) => {
+            print!("{USAGE}");
+            return;
+        }
+        Err(e) => {
+            eprintln!("error: {e}\n\n{USAGE}");
+            std::process::exit(2);
+        }
+    };
+    if healthcheck {
+        std::process::exit(run_healthcheck(&cfg));
+    }
 
-    let state = Arc::new(AppState {
-        start: Instant::now(),
-        hits_root: AtomicU64::new(0),
-        hits_health: AtomicU64::new(0),
-        hits_sum: AtomicU64::new(0),
-        hits_echo: AtomicU64::new(0),
-        hits_parallel: AtomicU64::new(0),
+    // re-exec 启动的新进程已处于后台，且需要接管旧进程的 PID 文件
+    if cfg.process.daemon && handoff::handoff_parent().is_none() {
+        if let Err(e) = daemon::detach() {
+            eprintln!("error: 无法后台运行: {e}");
+            std::process::exit(1);
+        }
+    }
+    let pidfile = cfg.process.pidfile.as_deref().map(|path| {
+        Pidfile::create(path, handoff::handoff_parent()).unwrap_or_else(|e| {
+            eprintln!("error: {e}");
+            std::process::exit(1);
+        })
     });
 
-    // 路由与中间件说明：
-    // - CorsLayer::permissive：开发环境放开跨域；生产需按域名/方法细化
-    // - TraceLayer：为每个请求生成 span，输出请求/响应耗时
-    let cwd = std::env::current_dir().unwrap_or_default();
-    info!("serving static files from: {:?}", cwd);
-
-    let app = Router::new()
-        .route("/", get(root))
-        .route("/health", get(health))
-        .route("/sum", get(sum))
-        .route("/echo", post(echo))
-        .route("/parallel", get(parallel))
-        .route("/metrics", get(metrics))
-        .fallback_service(ServeDir::new(cwd))
-        .with_state(state)
-        .layer(CorsLayer::permissive())
-        .layer(TraceLayer::new_for_http());
-
-    let addr = SocketAddr::from(([127, 0, 0, 1], 3000));
-    info!("listening on http://{}", addr);
-    let listener = tokio::net::TcpListener::bind(addr).await.unwrap();
-    axum::serve(listener, app)
-        .with_graceful_shutdown(shutdown_signal())
-        .await
-        .unwrap();
+    let runtime = tokio::runtime::Builder::new_multi_thread()
+        .enable_all()
+        .build()
+        .expect("failed to build tokio runtime");
+    runtime.block_on(run(cfg, loader, inherited));
+    drop(pidfile);
 }
 
-// 根路径：健康返回（文本 "ok"），并记录命中次数
-async fn root(State(app): State<Arc<AppState>>) -> impl IntoResponse {
-    app.hits_root.fetch_add(1, Ordering::Relaxed);
-    (StatusCode::OK, "ok")
-}
-
-// 健康检查：返回 {"status":"healthy"}，并记录命中次数
-async fn health(State(app): State<Arc<AppState>>) -> impl IntoResponse {
-    app.hits_health.fetch_add(1, Ordering::Relaxed);
-    Json(Health { status: "healthy" })
+// healthcheck 子命令：/health 返回 200 时退出码为 0，否则为 1
+fn run_healthcheck(cfg: &Config) -> i32 {
+    let addr = healthcheck::target(cfg);
+    match healthcheck::probe(&addr, HEALTHCHECK_TIMEOUT) {
+        Ok(200) => {
+            println!("{addr}: healthy");
+            0
+        }
+        Ok(status) => {
+            println!("{addr}: unhealthy (HTTP {status})");
+            1
+        }
+        Err(e) => {
+            println!("{addr}: unhealthy ({e})");
+            1
+        }
+    }
 }
 
-// 求和接口：GET /sum?nums=1,2,3
-// - 流程：拆分 -> 去空白 -> 逐项解析 i64 -> 求和 -> 返回 {"total":X}
-// - 错误：任何项非数字则返回 400 与说明（含索引与原值）
-#[derive(Error, Debug)]
-enum AppError {
-    #[error("{0}")]
-    BadRequest(String),
-    #[error("{0}")]
-    Internal(String),
-}
+async fn run(
+    cfg: Config,
+    loader: ConfigLoader,
+    inherited: std::io::Result<Vec<handoff::InheritedListener>>,
+) {
+    let filter = match EnvFilter::try_new(&cfg.log_level) {
+        Ok(f) => f,
+        Err(e) => {
+            eprintln!("error: log_level 的取值无效: {e}");
+            std::process::exit(2);
+        }
+    };
+    let (filter, log_handle) = tracing_subscriber::reload::Layer::new(filter);
+    let output = match cfg.log_format {
+        LogFormat::Text => tracing_subscriber::fmt::layer().boxed(),
+        LogFormat::Json => JsonLayer::new(std::io::stdout).boxed(),
+    };
+    // 日志过滤规则只作用于输出层；SpanTimingLayer 单独接收本 crate 的 span（不受日志级别影响），
+    // 日志级别调高时慢请求日志也能带上完整的 span 树
+    tracing_subscriber::registry()
+        .with(output.with_filter(filter))
+        .with(SpanTimingLayer::layer())
+        .init();
 
-// 将错误统一转换为 JSON 响应：
-// - BadRequest -> 400 {"error":"..."}
-// - Internal   -> 500 {"error":"..."}
-impl IntoResponse for AppError {
-    fn into_response(self) -> axum::response::Response {
-        let (code, msg) = match self {
-            AppError::BadRequest(m) => (StatusCode::BAD_REQUEST, m),
-            AppError::Internal(m) => (StatusCode::INTERNAL_SERVER_ERROR, m),
-        };
-        (code, Json(serde_json::json!({ "error": msg }))).into_response()
+    if let Some(path) = loader.path() {
+        info!("loaded config from {:?}", path);
     }
-}
+    let runtime = Arc::new(
+        RuntimeConfig::new(cfg.clone(), loader.path().map(Into::into)).with_log_handle(log_handle),
+    );
+    reload::spawn_watcher(loader, runtime.clone());
 
-// 输入解析（纯函数，便于单元测试与复用）：
-// - 返回总和或携带详细错误的 BadRequest
-fn parse_sum_input(s: &str) -> Result<i64, AppError> {
-    let mut total: i64 = 0;
-    for (idx, token) in s.split(',').filter(|t| !t.is_empty()).enumerate() {
-        let t = token.trim();
-        match t.parse::<i64>() {
-            Ok(v) => total += v,
-            Err(_) => {
-                return Err(AppError::BadRequest(format!(
-                    "nums 第 {idx} 项不是有效整数: {t}"
-                )))
+    info!("serving static files from: {:?}", cfg.static_dir);
+    let builder = AppBuilder::with_runtime_config(runtime);
+    // 恢复累计计数；文件无法读取或内容损坏时退出，避免覆盖掉已有的累计值
+    let metrics = builder.state().metrics();
+    if let Some(path) = &cfg.metrics.state_file {
+        match persist::load(path) {
+            Ok(Some(totals)) => {
+                info!(
+                    "restored metrics totals from {:?} (accumulating since unix time {})",
+                    path, totals.since_unix
+                );
+                metrics.restore(totals);
+            }
+            Ok(None) => info!(
+                "metrics state file {:?} not found, starting new totals",
+                path
+            ),
+            Err(e) => {
+                error!("failed to load metrics state from {:?}: {}", path, e);
+                std::process::exit(1);
             }
         }
     }
-    Ok(total)
-}
-
-async fn sum(State(app): State<Arc<AppState>>, Query(q): Query<SumQuery>) -> Result<impl IntoResponse, AppError> {
-    app.hits_sum.fetch_add(1, Ordering::Relaxed);
-    let total = parse_sum_input(&q.nums)?;
-    Ok(Json(serde_json::json!({ "total": total })))
-}
+    let spawn_persister = || match (&cfg.metrics.state_file, cfg.metrics.state_interval_secs) {
+        (Some(path), secs) if secs > 0 => Some(persist::spawn(
+            metrics.clone(),
+            path.clone(),
+            Duration::from_secs(secs),
+        )),
+        _ => None,
+    };
+    #[cfg_attr(not(unix), allow(unused_mut))]
+    let mut persister = spawn_persister();
+    let access_log = match AccessLog::open(&cfg.access_log) {
+        Ok(log) => log,
+        Err(e) => {
+            error!("failed to open access log: {}", e);
+            std::process::exit(1);
+        }
+    };
+    let builder = match (&access_log, &cfg.access_log.path) {
+        (Some(log), Some(path)) => {
+            info!("writing {} access log to {:?}", log.format(), path);
+            builder.access_log(log.clone())
+        }
+        _ => builder,
+    };
+    let statsd = match Statsd::connect(&cfg.statsd).await {
+        Ok(statsd) => statsd,
+        Err(e) => {
+            error!("failed to set up statsd exporter: {}", e);
+            std::process::exit(1);
+        }
+    };
+    let (builder, statsd) = match statsd {
+        Some(statsd) => {
+            info!(
+                "pushing metrics to statsd at {} every {}ms",
+                statsd.address(),
+                cfg.statsd.flush_interval_ms
+            );
+            let task = statsd.spawn(metrics.clone());
+            (builder.statsd(statsd.clone()), Some((statsd, task)))
+        }
+        None => (builder, None),
+    };
+    #[cfg(feature = "otel")]
+    let (builder, telemetry) = match init_otel() {
+        Some(telemetry) => {
+            let task = telemetry.spawn(metrics.clone());
+            (builder.otel(telemetry.clone()), Some((telemetry, task)))
+        }
+        None => (builder, None),
+    };
+    // 配置了管理监听时，/health、/metrics、/admin/* 仅在管理监听上提供
+    let (public, admin) = if cfg.admin.listen.is_empty() {
+        (builder.build(), None)
+    } else {
+        let (public, admin) = builder.build_split();
+        (public, Some(admin))
+    };
+    let app_for = |kind: &str| match (kind, &admin) {
+        ("admin", Some(admin)) => admin.clone(),
+        _ => public.clone(),
+    };
+    let mut targets: Vec<(ListenAddr, &'static str)> =
+        cfg.listeners().into_iter().map(|a| (a, "api")).collect();
+    targets.extend(cfg.admin.listen.iter().map(|a| (a.clone(), "admin")));
 
-// 回显接口：POST /echo
-// - 请求体：{"message":"..."}；若为空字符串则返回 400 错误
-// - 作用：演示 JSON 反序列化与简单校验
-async fn echo(State(app): State<Arc<AppState>>, Json(body): Json<EchoBody>) -> Result<impl IntoResponse, AppError> {
-    app.hits_echo.fetch_add(1, Ordering::Relaxed);
-    if body.message.trim().is_empty() {
-        return Err(AppError::BadRequest("message 不能为空".into()));
+    // 先绑定全部监听，任一失败则直接退出（避免只启动了部分监听）
+    // 与继承的监听地址相同时直接复用继承的 socket，不再重新绑定
+    let mut inherited = match inherited {
+        Ok(list) => list,
+        Err(e) => {
+            error!("failed to take inherited listeners: {}", e);
+            std::process::exit(1);
+        }
+    };
+    if !inherited.is_empty() {
+        info!("inherited {} listener(s)", inherited.len());
+    }
+    let mut listeners = Vec::new();
+    for (addr, kind) in targets {
+        let bound = match inherited.iter().position(|i| i.addr == addr) {
+            Some(idx) => inherited.swap_remove(idx).into_listener(),
+            None => Listener::bind(&addr, cfg.server.unix_socket_mode).await,
+        };
+        match bound {
+            Ok(l) => listeners.push((l, kind)),
+            Err(e) => {
+                error!("failed to bind {}: {}", addr, e);
+                std::process::exit(1);
+            }
+        }
+    }
+    // 未出现在配置中的继承监听（如 systemd socket 单元定义的地址）：名为 admin 的作为管理监听，其余作为公共监听
+    for i in inherited {
+        let kind = match i.name.as_deref() {
+            Some("admin") if admin.is_some() => "admin",
+            _ => "api",
+        };
+        let addr = i.addr.clone();
+        match i.into_listener() {
+            Ok(l) => listeners.push((l, kind)),
+            Err(e) => {
+                error!("failed to use inherited listener {}: {}", addr, e);
+                std::process::exit(1);
+            }
+        }
     }
-    Ok(Json(body))
-}
 
-// 并发查询参数：
-// - n：并发任务数量（默认 5，上限 32；避免过度并发）
-#[derive(Deserialize)]
-struct ParallelQuery {
-    n: Option<usize>,
-}
+    // 关闭信号通过 watch 通道广播给所有监听
+    let (shutdown_tx, shutdown_rx) = watch::channel(());
+    let opts = ServeOptions::from_config(&cfg);
+    let http = &cfg.http;
+    info!(
+        "http/1.1: keep_alive={}, header_read_timeout={}s, max_headers={}, max_header_bytes={}, max_connections={}",
+        http.keep_alive,
+        http.header_read_timeout_secs,
+        http.max_headers,
+        http.max_header_bytes,
+        http.max_connections
+    );
+    // 在通知就绪（父进程 / 启动命令据此认为服务已可用）之前安装信号处理，
+    // 否则这段时间内收到的 SIGTERM / SIGUSR2 按默认动作直接终止进程
+    let mut signals = Signals::new();
+    let mut servers = JoinSet::new();
+    #[cfg(unix)]
+    let mut handoff_fds = Vec::new();
+    for (listener, kind) in listeners {
+        match listener.local_addr() {
+            Ok(ListenAddr::Tcp(addr)) => info!("{} listening on http://{}", kind, addr),
+            Ok(addr) => info!("{} listening on {}", kind, addr),
+            Err(_) => {}
+        }
+        #[cfg(unix)]
+        handoff_fds.push((std::os::fd::AsRawFd::as_raw_fd(&listener), kind));
+        let mut rx = shutdown_rx.clone();
+        servers.spawn(server::serve(
+            listener,
+            app_for(kind),
+            opts.clone(),
+            async move {
+                let _ = rx.changed().await;
+            },
+        ));
+    }
+    #[cfg(unix)]
+    handoff::notify_ready();
+    if let Some(path) = &cfg.process.pidfile {
+        info!("pid {} written to {:?}", std::process::id(), path);
+    }
+    if let Err(e) = daemon::notify_ready(cfg.process.log_file.as_deref()) {
+        error!("failed to finish daemonizing: {}", e);
+        std::process::exit(1);
+    }
 
-// 并发示例：GET /parallel?n=8
-// - 任务：为每个 i 启动一个异步任务，sleep 不同毫秒模拟耗时
-// - 结果：返回每个任务的 index/value/ms，按完成顺序收集（非阻塞）
-// - 展示：Tokio 并发与 JoinSet 收集的用法
-async fn parallel(State(app): State<Arc<AppState>>, Query(q): Query<ParallelQuery>) -> impl IntoResponse {
-    app.hits_parallel.fetch_add(1, Ordering::Relaxed);
-    let n = q.n.unwrap_or(5).min(32);
-    let mut tasks = JoinSet::new();
-    for i in 0..n {
-        tasks.spawn(async move {
-            let ms = 50 + (i as u64) * 30;
-            sleep(Duration::from_millis(ms)).await;
-            ParallelResult {
-                index: i,
-                value: i * i,
-                ms,
+    let signal = loop {
+        match signals.recv().await {
+            Signal::Shutdown(name) => break name,
+            // SIGUSR2：启动新进程接管监听，新进程就绪后本进程优雅退出；失败则继续服务
+            #[cfg(unix)]
+            Signal::Restart => {
+                info!(
+                    "received SIGUSR2, re-executing with {} listener(s)",
+                    handoff_fds.len()
+                );
+                // 新进程启动时读取累计计数；本进程交接后仍在处理的请求不再写入（避免覆盖新进程的计数），
+                // 因此先停止定期写入再保存最后一次；交接失败时恢复定期写入
+                if let Some(p) = persister.take() {
+                    p.stop().await;
+                }
+                if let Some(path) = &cfg.metrics.state_file {
+                    save_metrics(path, &metrics);
+                }
+                match handoff::reexec(&handoff_fds, REEXEC_READY_TIMEOUT).await {
+                    Ok(pid) => {
+                        info!("new process {} is ready, handing over", pid);
+                        break "SIGUSR2";
+                    }
+                    Err(e) => {
+                        error!("re-exec failed, continuing to serve: {}", e);
+                        persister = spawn_persister();
+                    }
+                }
             }
-        });
+        }
+    };
+    match opts.drain_timeout {
+        Some(t) => info!("received {}, draining for up to {:?}", signal, t),
+        None => info!("received {}, draining without deadline", signal),
     }
-    let mut results = Vec::with_capacity(n);
-    while let Some(res) = tasks.join_next().await {
-        results.push(res.unwrap());
+    let _ = shutdown_tx.send(());
+    let mut total = DrainStats::default();
+    while let Some(res) = servers.join_next().await {
+        match res {
+            Ok(Ok(stats)) => {
+                total.drained += stats.drained;
+                total.cut_off += stats.cut_off;
+            }
+            Ok(Err(e)) => error!("server error: {}", e),
+            Err(e) => error!("server task failed: {}", e),
+        }
+    }
+    info!(
+        "shutdown complete: {} connection(s) drained, {} cut off",
+        total.drained, total.cut_off
+    );
+    if let Some(log) = &access_log {
+        if !log.flush() {
+            error!("timed out flushing access log");
+        }
+        if log.dropped() > 0 {
+            error!("{} access log line(s) dropped (queue full)", log.dropped());
+        }
+    }
+    if let Some(p) = persister {
+        p.stop().await;
+    }
+    if let (Some(path), false) = (&cfg.metrics.state_file, signal == "SIGUSR2") {
+        save_metrics(path, &metrics);
+    }
+    if let Some((statsd, task)) = statsd {
+        task.abort();
+        statsd.shutdown(&metrics).await;
+    }
+    #[cfg(feature = "otel")]
+    if let Some((telemetry, task)) = telemetry {
+        task.abort();
+        telemetry.shutdown(&metrics).await;
     }
-    Json(results)
 }
 
-// 指标端点：返回运行时长与各端点命中次数（便于监控与压测）
-async fn metrics(State(app): State<Arc<AppState>>) -> impl IntoResponse {
-    let uptime = app.start.elapsed().as_secs();
-    Json(serde_json::json!({
-        "uptime_seconds": uptime,
-        "hits": {
-            "root": app.hits_root.load(Ordering::Relaxed),
-            "health": app.hits_health.load(Ordering::Relaxed),
-            "sum": app.hits_sum.load(Ordering::Relaxed),
-            "echo": app.hits_echo.load(Ordering::Relaxed),
-            "parallel": app.hits_parallel.load(Ordering::Relaxed)
+// OpenTelemetry 导出：按 OTEL_* 环境变量配置，配置无效时退出
+#[cfg(feature = "otel")]
+fn init_otel() -> Option<Arc<rustdemo::otel::Telemetry>> {
+    use rustdemo::otel::{OtelConfig, Telemetry};
+
+    let cfg = match OtelConfig::from_env(|k| std::env::var(k).ok()) {
+        Ok(cfg) => cfg?,
+        Err(e) => {
+            error!("invalid OpenTelemetry configuration: {}", e);
+            std::process::exit(2);
         }
-    }))
+    };
+    let show =
+        |e: &Option<rustdemo::otel::Endpoint>| e.as_ref().map_or("none".into(), |e| e.to_string());
+    info!(
+        "exporting OTLP traces to {}, metrics to {}",
+        show(&cfg.traces),
+        show(&cfg.metrics)
+    );
+    Some(Telemetry::new(cfg))
+}
+
+fn save_metrics(path: &Path, metrics: &RequestMetrics) {
+    match persist::save(path, &metrics.totals()) {
+        Ok(()) => info!("saved metrics totals to {:?}", path),
+        Err(e) => error!("failed to save metrics state to {:?}: {}", path, e),
+    }
 }
 
-// 优雅关闭：监听 Ctrl+C，触发服务的 graceful shutdown
-async fn shutdown_signal() {
-    let _ = tokio::signal::ctrl_c().await;
+// healthcheck 子命令的连接与读写期限
+const HEALTHCHECK_TIMEOUT: Duration = Duration::from_secs(5);
+
+// 等待新进程就绪的最长时间
+#[cfg(unix)]
+const REEXEC_READY_TIMEOUT: Duration = Duration::from_secs(30);
+
+enum Signal {
+    Shutdown(&'static str),
+    #[cfg(unix)]
+    Restart,
 }
 
-#[cfg(test)]
-mod tests {
-    use super::parse_sum_input;
+// 进程信号：
+// - Ctrl+C（SIGINT）、SIGTERM（systemd / Kubernetes）与 SIGQUIT：优雅关闭
+// - SIGUSR2：零停机重启（re-exec 并交接监听）
+// 创建时即安装全部处理器
+struct Signals {
+    #[cfg(unix)]
+    int: tokio::signal::unix::Signal,
+    #[cfg(unix)]
+    term: tokio::signal::unix::Signal,
+    #[cfg(unix)]
+    quit: tokio::signal::unix::Signal,
+    #[cfg(unix)]
+    usr2: tokio::signal::unix::Signal,
+}
 
-    // 正常解析用例：空白/空项应被忽略
-    #[test]
-    fn parse_sum_ok() {
-        assert_eq!(parse_sum_input("1,2,3").unwrap(), 6);
-        assert_eq!(parse_sum_input(" 1 , 2 , 3 ").unwrap(), 6);
-        assert_eq!(parse_sum_input("1,,3").unwrap(), 4);
+impl Signals {
+    fn new() -> Self {
+        #[cfg(unix)]
+        {
+            use tokio::signal::unix::{signal, SignalKind};
+            let install =
+                |kind: SignalKind| signal(kind).expect("failed to install signal handler");
+            Self {
+                int: install(SignalKind::interrupt()),
+                term: install(SignalKind::terminate()),
+                quit: install(SignalKind::quit()),
+                usr2: install(SignalKind::user_defined2()),
+            }
+        }
+        #[cfg(not(unix))]
+        Self {}
     }
 
-    // 错误解析用例：包含非数字项，应返回携带索引信息的错误
-    #[test]
-    fn parse_sum_invalid() {
-        let err = parse_sum_input("1,x,3").unwrap_err();
-        let msg = format!("{}", err);
-        assert!(msg.contains("不是有效整数"));
+    async fn recv(&mut self) -> Signal {
+        #[cfg(unix)]
+        {
+            tokio::select! {
+                _ = self.int.recv() => Signal::Shutdown("SIGINT"),
+                _ = self.term.recv() => Signal::Shutdown("SIGTERM"),
+                _ = self.quit.recv() => Signal::Shutdown("SIGQUIT"),
+                _ = self.usr2.recv() => Signal::Restart,
+            }
+        }
+        #[cfg(not(unix))]
+        {
+            let _ = tokio::signal::ctrl_c().await;
+            Signal::Shutdown("Ctrl+C")
+        }
     }
 }
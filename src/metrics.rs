@@ -0,0 +1,520 @@
+// 指标：
+// - MetricsLayer：tower 中间件，按路由模板自动统计每个请求（含静态文件兜底与错误响应）：
+//   请求数（按方法与状态码）、状态类别计数、在途请求数、耗时直方图（启动以来）与滑动窗口内的耗时分位数
+// - Format / negotiate：按 Accept 选择 JSON（默认，index.html 使用）、Prometheus 文本格式或 OpenMetrics
+// - Encoder：生成带 HELP / TYPE 行的文本 exposition（两种文本格式仅在计数器命名与结尾标记上不同）
+use std::{
+    collections::BTreeMap,
+    fmt::{Display, Write},
+    future::Future,
+    pin::Pin,
+    sync::{
+        atomic::{AtomicI64, AtomicU64, Ordering},
+        Arc, Mutex, RwLock,
+    },
+    task::{Context, Poll},
+    time::{Duration, Instant, SystemTime},
+};
+
+use axum::{
+    extract::{MatchedPath, Request},
+    http::{Method, StatusCode},
+    response::Response,
+};
+use tower::{Layer, Service};
+
+use crate::{
+    config::MetricsConfig,
+    history::History,
+    latency::LatencyWindow,
+    persist::{RouteTotals, Totals},
+};
+
+// 未匹配任何路由的请求（静态文件兜底、404）使用的路由标签
+pub const FALLBACK_ROUTE: &str = "fallback";
+
+// 全部路由的指标；路由在第一次收到请求时登记
+// baseline 为启动时从 metrics.state_file 恢复的累计计数（未恢复时为空，开始时间为本次启动）
+pub struct RequestMetrics {
+    buckets: Arc<[f64]>,
+    window: Duration,
+    routes: RwLock<BTreeMap<String, Arc<RouteMetrics>>>,
+    baseline: Mutex<Totals>,
+}
+
+impl RequestMetrics {
+    pub fn new(cfg: &MetricsConfig) -> Self {
+        Self {
+            buckets: cfg.latency_buckets.clone().into(),
+            window: Duration::from_secs(cfg.window_secs),
+            routes: RwLock::default(),
+            baseline: Mutex::new(Totals::new(SystemTime::now())),
+        }
+    }
+
+    pub fn route(&self, route: &str) -> Arc<RouteMetrics> {
+        if let Some(m) = self.routes.read().unwrap().get(route) {
+            return m.clone();
+        }
+        self.routes
+            .write()
+            .unwrap()
+            .entry(route.to_string())
+            .or_insert_with(|| Arc::new(RouteMetrics::new(self.buckets.clone(), self.window)))
+            .clone()
+    }
+
+    // 以持久化的累计计数作为基线（启动时、开始处理请求前调用）
+    pub fn restore(&self, totals: Totals) {
+        *self.baseline.lock().unwrap() = totals;
+    }
+
+    // 累计计数：基线加上本次启动以来的计数
+    pub fn totals(&self) -> Totals {
+        let mut totals = self.baseline.lock().unwrap().clone();
+        for (route, m) in self.snapshot() {
+            totals.add_route(&route, &m.totals());
+        }
+        totals
+    }
+
+    // 按路由模板排序的快照（输出顺序稳定）
+    pub fn snapshot(&self) -> Vec<(String, Arc<RouteMetrics>)> {
+        let routes = self.routes.read().unwrap();
+        routes.iter().map(|(k, v)| (k.clone(), v.clone())).collect()
+    }
+}
+
+// 单个路由模板的指标：
+// - by_status：按 (方法, 状态码) 的请求数
+// - classes：1xx..5xx 各状态类别的请求数
+// - in_flight：正在处理的请求数
+// - latency：已完成请求的耗时分布（启动以来，固定桶）
+// - recent：滑动窗口内的耗时分布（用于分位数）
+// - slow：超过慢请求阈值的请求数（由 SlowRequestLayer 记录）
+// - history：按固定分辨率降采样的请求数、错误数与耗时时间序列（/metrics/history）
+pub struct RouteMetrics {
+    by_status: Mutex<BTreeMap<(String, u16), u64>>,
+    classes: [AtomicU64; 5],
+    in_flight: AtomicI64,
+    slow: AtomicU64,
+    latency: Histogram,
+    recent: LatencyWindow,
+    history: History,
+}
+
+// 方法标签：标准方法之外的（扩展方法名由客户端任意指定）统一记为 OTHER，避免标签组合无限增长
+fn method_label(method: &Method) -> &'static str {
+    match *method {
+        Method::GET => "GET",
+        Method::HEAD => "HEAD",
+        Method::POST => "POST",
+        Method::PUT => "PUT",
+        Method::DELETE => "DELETE",
+        Method::CONNECT => "CONNECT",
+        Method::OPTIONS => "OPTIONS",
+        Method::TRACE => "TRACE",
+        Method::PATCH => "PATCH",
+        _ => "OTHER",
+    }
+}
+
+impl RouteMetrics {
+    fn new(buckets: Arc<[f64]>, window: Duration) -> Self {
+        Self {
+            by_status: Mutex::default(),
+            classes: Default::default(),
+            in_flight: AtomicI64::new(0),
+            slow: AtomicU64::new(0),
+            latency: Histogram::new(buckets),
+            recent: LatencyWindow::new(window),
+            history: History::new(),
+        }
+    }
+
+    fn observe(&self, method: Method, status: StatusCode, elapsed: Duration) {
+        *self
+            .by_status
+            .lock()
+            .unwrap()
+            .entry((method_label(&method).to_string(), status.as_u16()))
+            .or_default() += 1;
+        let class = (status.as_u16() / 100).clamp(1, 5) as usize - 1;
+        self.classes[class].fetch_add(1, Ordering::Relaxed);
+        self.latency.observe(elapsed);
+        self.recent.record(elapsed);
+        self.history.record(status.is_server_error(), elapsed);
+    }
+
+    pub fn requests(&self) -> u64 {
+        self.classes.iter().map(|c| c.load(Ordering::Relaxed)).sum()
+    }
+
+    pub fn by_status(&self) -> Vec<((String, u16), u64)> {
+        let by_status = self.by_status.lock().unwrap();
+        by_status.iter().map(|(k, v)| (k.clone(), *v)).collect()
+    }
+
+    // 各状态类别的请求数，键为 "2xx" 等（省略为 0 的类别）
+    pub fn classes(&self) -> BTreeMap<String, u64> {
+        self.classes
+            .iter()
+            .enumerate()
+            .map(|(i, c)| (format!("{}xx", i + 1), c.load(Ordering::Relaxed)))
+            .filter(|(_, n)| *n > 0)
+            .collect()
+    }
+
+    pub fn in_flight(&self) -> i64 {
+        self.in_flight.load(Ordering::Relaxed)
+    }
+
+    pub(crate) fn observe_slow(&self) {
+        self.slow.fetch_add(1, Ordering::Relaxed);
+    }
+
+    pub fn slow(&self) -> u64 {
+        self.slow.load(Ordering::Relaxed)
+    }
+
+    // 本次启动以来的计数（用于累计与持久化）
+    pub fn totals(&self) -> RouteTotals {
+        let mut requests: BTreeMap<String, BTreeMap<u16, u64>> = BTreeMap::new();
+        for ((method, status), n) in self.by_status() {
+            requests.entry(method).or_default().insert(status, n);
+        }
+        RouteTotals {
+            requests,
+            slow_requests: self.slow(),
+            latency_count: self.latency.count(),
+            latency_sum_seconds: self.latency.sum().as_secs_f64(),
+        }
+    }
+
+    pub fn latency(&self) -> &Histogram {
+        &self.latency
+    }
+
+    pub fn recent(&self) -> &LatencyWindow {
+        &self.recent
+    }
+
+    pub fn history(&self) -> &History {
+        &self.history
+    }
+}
+
+// 固定桶直方图（桶上界来自 metrics.latency_buckets）；counts 为各桶（非累计）计数，最后一项为 +Inf
+pub struct Histogram {
+    bounds: Arc<[f64]>,
+    counts: Vec<AtomicU64>,
+    sum_nanos: AtomicU64,
+}
+
+impl Histogram {
+    pub fn new(bounds: Arc<[f64]>) -> Self {
+        Self {
+            counts: (0..=bounds.len()).map(|_| AtomicU64::new(0)).collect(),
+            bounds,
+            sum_nanos: AtomicU64::new(0),
+        }
+    }
+
+    pub fn observe(&self, d: Duration) {
+        let secs = d.as_secs_f64();
+        let idx = self
+            .bounds
+            .iter()
+            .position(|b| secs <= *b)
+            .unwrap_or(self.bounds.len());
+        self.counts[idx].fetch_add(1, Ordering::Relaxed);
+        self.sum_nanos
+            .fetch_add(d.as_nanos() as u64, Ordering::Relaxed);
+    }
+
+    // (桶上界, 累计计数)，最后一项上界为 +Inf
+    pub fn cumulative(&self) -> Vec<(f64, u64)> {
+        let mut total = 0;
+        self.counts
+            .iter()
+            .zip(self.bounds.iter().copied().chain([f64::INFINITY]))
+            .map(|(c, le)| {
+                total += c.load(Ordering::Relaxed);
+                (le, total)
+            })
+            .collect()
+    }
+
+    pub fn count(&self) -> u64 {
+        self.counts.iter().map(|c| c.load(Ordering::Relaxed)).sum()
+    }
+
+    pub fn sum(&self) -> Duration {
+        Duration::from_nanos(self.sum_nanos.load(Ordering::Relaxed))
+    }
+}
+
+// 统计中间件；需通过 Router::layer 挂载（逐路由包裹，才能读取到 MatchedPath）
+#[derive(Clone)]
+pub struct MetricsLayer {
+    metrics: Arc<RequestMetrics>,
+}
+
+impl MetricsLayer {
+    pub fn new(metrics: Arc<RequestMetrics>) -> Self {
+        Self { metrics }
+    }
+}
+
+impl<S> Layer<S> for MetricsLayer {
+    type Service = MetricsService<S>;
+
+    fn layer(&self, inner: S) -> Self::Service {
+        MetricsService {
+            inner,
+            metrics: self.metrics.clone(),
+        }
+    }
+}
+
+#[derive(Clone)]
+pub struct MetricsService<S> {
+    inner: S,
+    metrics: Arc<RequestMetrics>,
+}
+
+impl<S, B> Service<Request> for MetricsService<S>
+where
+    S: Service<Request, Response = Response<B>> + Send + 'static,
+    S::Future: Send + 'static,
+{
+    type Response = S::Response;
+    type Error = S::Error;
+    type Future = Pin<Box<dyn Future<Output = Result<Self::Response, Self::Error>> + Send>>;
+
+    fn poll_ready(&mut self, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        self.inner.poll_ready(cx)
+    }
+
+    fn call(&mut self, req: Request) -> Self::Future {
+        let route = req
+            .extensions()
+            .get::<MatchedPath>()
+            .map_or(FALLBACK_ROUTE, MatchedPath::as_str);
+        let guard = InFlight::new(self.metrics.route(route));
+        let method = req.method().clone();
+        let fut = self.inner.call(req);
+        Box::pin(async move {
+            let res = fut.await;
+            if let Ok(res) = &res {
+                guard.finish(method, res.status());
+            }
+            res
+        })
+    }
+}
+
+// 在途计数：创建时加一，完成或被取消（连接断开、超过关闭期限）时减一；被取消的请求不计入请求数与耗时
+struct InFlight {
+    route: Arc<RouteMetrics>,
+    start: Instant,
+}
+
+impl InFlight {
+    fn new(route: Arc<RouteMetrics>) -> Self {
+        route.in_flight.fetch_add(1, Ordering::Relaxed);
+        Self {
+            route,
+            start: Instant::now(),
+        }
+    }
+
+    fn finish(self, method: Method, status: StatusCode) {
+        self.route.observe(method, status, self.start.elapsed());
+    }
+}
+
+impl Drop for InFlight {
+    fn drop(&mut self) {
+        self.route.in_flight.fetch_sub(1, Ordering::Relaxed);
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Format {
+    Json,
+    Prometheus,
+    OpenMetrics,
+}
+
+impl Format {
+    pub fn content_type(self) -> &'static str {
+        match self {
+            Format::Json => "application/json",
+            Format::Prometheus => "text/plain; version=0.0.4; charset=utf-8",
+            Format::OpenMetrics => "application/openmetrics-text; version=1.0.0; charset=utf-8",
+        }
+    }
+}
+
+// 内容协商：取 q 值最高的可支持类型（相同 q 值按出现顺序），无匹配或未提供 Accept 时返回 JSON
+pub fn negotiate(accept: Option<&str>) -> Format {
+    let mut best: Option<(f32, Format)> = None;
+    for item in accept.unwrap_or_default().split(',') {
+        let mut parts = item.split(';').map(str::trim);
+        let format = match parts
+            .next()
+            .unwrap_or_default()
+            .to_ascii_lowercase()
+            .as_str()
+        {
+            "application/openmetrics-text" => Format::OpenMetrics,
+            "text/plain" => Format::Prometheus,
+            "application/json" | "application/*" | "*/*" => Format::Json,
+            _ => continue,
+        };
+        let q = parts
+            .filter_map(|p| p.strip_prefix("q="))
+            .find_map(|q| q.parse::<f32>().ok())
+            .unwrap_or(1.0);
+        if q > 0.0 && best.is_none_or(|(b, _)| q > b) {
+            best = Some((q, format));
+        }
+    }
+    best.map_or(Format::Json, |(_, f)| f)
+}
+
+// 文本 exposition 编码器：先调用 family 写入 HELP / TYPE，再逐条写入 sample
+pub struct Encoder {
+    out: String,
+    openmetrics: bool,
+}
+
+impl Encoder {
+    pub fn new(format: Format) -> Self {
+        Self {
+            out: String::new(),
+            openmetrics: format == Format::OpenMetrics,
+        }
+    }
+
+    // name 为指标族名；计数器的样本名为 name + "_total"，
+    // Prometheus 文本格式的 HELP / TYPE 使用样本名，OpenMetrics 使用族名
+    pub fn family(&mut self, name: &str, kind: &str, help: &str) {
+        let suffix = if kind == "counter" && !self.openmetrics {
+            "_total"
+        } else {
+            ""
+        };
+        let _ = writeln!(self.out, "# HELP {name}{suffix} {help}");
+        let _ = writeln!(self.out, "# TYPE {name}{suffix} {kind}");
+    }
+
+    pub fn sample(&mut self, name: &str, labels: &[(&str, &str)], value: impl Display) {
+        self.out.push_str(name);
+        if !labels.is_empty() {
+            self.out.push('{');
+            for (i, (k, v)) in labels.iter().enumerate() {
+                if i > 0 {
+                    self.out.push(',');
+                }
+                let _ = write!(self.out, "{k}=\"{}\"", escape(v));
+            }
+            self.out.push('}');
+        }
+        let _ = writeln!(self.out, " {value}");
+    }
+
+    pub fn finish(mut self) -> String {
+        if self.openmetrics {
+            self.out.push_str("# EOF\n");
+        }
+        self.out
+    }
+}
+
+// 标签值转义：反斜杠、双引号与换行
+fn escape(v: &str) -> String {
+    v.replace('\\', "\\\\")
+        .replace('"', "\\\"")
+        .replace('\n', "\\n")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::Config;
+
+    // 直方图：按上界落桶，累计计数的最后一项（+Inf）等于总数
+    #[test]
+    fn histogram_buckets() {
+        let h = Histogram::new(Config::default().metrics.latency_buckets.into());
+        h.observe(Duration::from_millis(3));
+        h.observe(Duration::from_millis(10));
+        h.observe(Duration::from_secs(30));
+        let cum = h.cumulative();
+        assert_eq!(cum[0], (0.005, 1));
+        assert_eq!(cum[1], (0.01, 2));
+        assert_eq!(cum.last().unwrap(), &(f64::INFINITY, 3));
+        assert_eq!(h.count(), 3);
+        assert_eq!(h.sum(), Duration::from_millis(30_013));
+    }
+
+    // 非标准方法合并为 OTHER
+    #[test]
+    fn nonstandard_methods_share_a_label() {
+        let m = RouteMetrics::new(
+            Config::default().metrics.latency_buckets.into(),
+            Duration::from_secs(60),
+        );
+        for method in ["GET", "PURGE", "X-RANDOM-1", "X-RANDOM-2"] {
+            let method = Method::from_bytes(method.as_bytes()).unwrap();
+            m.observe(method, StatusCode::OK, Duration::from_millis(1));
+        }
+        assert_eq!(
+            m.by_status(),
+            [
+                (("GET".to_string(), 200), 1),
+                (("OTHER".to_string(), 200), 3)
+            ]
+        );
+    }
+
+    #[test]
+    fn negotiates_by_quality() {
+        assert_eq!(negotiate(None), Format::Json);
+        assert_eq!(negotiate(Some("*/*")), Format::Json);
+        assert_eq!(negotiate(Some("text/html, text/plain")), Format::Prometheus);
+        // Prometheus 抓取时发送的 Accept
+        let scrape = "application/openmetrics-text;version=1.0.0,application/openmetrics-text;\
+                      version=0.0.1;q=0.75,text/plain;version=0.0.4;q=0.5,*/*;q=0.1";
+        assert_eq!(negotiate(Some(scrape)), Format::OpenMetrics);
+        assert_eq!(
+            negotiate(Some("application/json;q=0.5, text/plain;q=0.9")),
+            Format::Prometheus
+        );
+        assert_eq!(negotiate(Some("text/plain;q=0")), Format::Json);
+    }
+
+    #[test]
+    fn encodes_counters_per_format() {
+        let render = |format| {
+            let mut enc = Encoder::new(format);
+            enc.family("demo_requests", "counter", "Requests.");
+            enc.sample(
+                "demo_requests_total",
+                &[("route", "/a\"b"), ("status", "200")],
+                3,
+            );
+            enc.finish()
+        };
+        assert_eq!(
+            render(Format::Prometheus),
+            "# HELP demo_requests_total Requests.\n# TYPE demo_requests_total counter\n\
+             demo_requests_total{route=\"/a\\\"b\",status=\"200\"} 3\n"
+        );
+        let om = render(Format::OpenMetrics);
+        assert!(om.starts_with("# HELP demo_requests Requests.\n# TYPE demo_requests counter\n"));
+        assert!(om.ends_with("} 3\n# EOF\n"));
+    }
+}
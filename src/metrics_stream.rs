@@ -0,0 +1,293 @@
+// 指标实时推送（/metrics/stream）：
+// - MetricsStream：第一个订阅者连接时启动采样任务，每 metrics.stream_interval_ms 取一次各路由本次启动以来的计数，
+//   与上次采样相减得到一帧增量（event: delta），广播给订阅者，并保留最近 BACKLOG 帧供断线续传；
+//   没有订阅者时跳过采样（下一帧的增量覆盖更长的间隔，interval_ms 如实给出）
+// - 事件 ID 为 "<实例>-<序号>"，实例为创建时间（毫秒），进程重启后旧的 Last-Event-ID 不会被误认
+// - 新连接（或 Last-Event-ID 已超出保留范围、来自其他实例）先收到一帧 snapshot（截至该序号的累计计数），
+//   之后是序号更大的 delta；客户端以 snapshot 为基准累加 delta 即得当前值。订阅者落后过多时同样重发 snapshot
+// - 同时连接数受 metrics.stream_max_subscribers 限制（连接时检查，可热加载）
+use std::{
+    collections::{BTreeMap, VecDeque},
+    sync::{
+        atomic::{AtomicUsize, Ordering},
+        Arc, Mutex, Weak,
+    },
+    time::{Duration, Instant, SystemTime, UNIX_EPOCH},
+};
+
+use tokio::sync::broadcast::{self, error::RecvError};
+
+use crate::{
+    access_log::rfc3339, metrics::RequestMetrics, persist::RouteTotals, reload::RuntimeConfig,
+};
+
+// 保留用于续传的帧数；同时也是广播通道容量
+const BACKLOG: usize = 64;
+
+#[derive(Debug)]
+pub struct Frame {
+    pub seq: u64,
+    pub id: String,
+    pub event: &'static str,
+    pub data: String,
+}
+
+// 各路由的 (本次启动以来的计数, 在途请求数)
+type Sample = BTreeMap<String, (RouteTotals, i64)>;
+
+struct Sampler {
+    seq: u64,
+    at: Instant,
+    routes: Sample,
+    backlog: VecDeque<Arc<Frame>>,
+}
+
+pub struct MetricsStream {
+    instance: u64,
+    metrics: Arc<RequestMetrics>,
+    config: Arc<RuntimeConfig>,
+    subscribers: AtomicUsize,
+    // 第一个订阅者连接前为 None
+    sampler: Mutex<Option<Sampler>>,
+    tx: broadcast::Sender<Arc<Frame>>,
+}
+
+impl MetricsStream {
+    pub fn new(metrics: Arc<RequestMetrics>, config: Arc<RuntimeConfig>) -> Self {
+        let instance = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .unwrap_or_default()
+            .as_millis() as u64;
+        Self {
+            instance,
+            metrics,
+            config,
+            subscribers: AtomicUsize::new(0),
+            sampler: Mutex::new(None),
+            tx: broadcast::channel(BACKLOG).0,
+        }
+    }
+
+    pub fn subscribers(&self) -> usize {
+        self.subscribers.load(Ordering::Relaxed)
+    }
+
+    // 新订阅；last_event_id 为客户端最后收到的事件 ID（请求头 Last-Event-ID）。订阅者已满时返回 None
+    pub fn subscribe(self: &Arc<Self>, last_event_id: Option<&str>) -> Option<Subscription> {
+        let max = self.config.read(|c| c.metrics.stream_max_subscribers);
+        self.subscribers
+            .fetch_update(Ordering::AcqRel, Ordering::Acquire, |n| {
+                (n < max).then_some(n + 1)
+            })
+            .ok()?;
+        let guard = SubscriberGuard(self.clone());
+        let resume = last_event_id.and_then(|id| self.parse_id(id));
+        let (pending, rx) = self.attach(resume);
+        Some(Subscription { pending, rx, guard })
+    }
+
+    // 在采样锁内取得待发送的帧与接收端，保证与之后广播的帧之间没有遗漏或重复
+    fn attach(
+        self: &Arc<Self>,
+        resume: Option<u64>,
+    ) -> (VecDeque<Arc<Frame>>, broadcast::Receiver<Arc<Frame>>) {
+        let mut sampler = self.sampler.lock().unwrap();
+        let s = sampler.get_or_insert_with(|| {
+            self.spawn();
+            Sampler {
+                seq: 0,
+                at: Instant::now(),
+                routes: self.sample(),
+                backlog: VecDeque::with_capacity(BACKLOG),
+            }
+        });
+        let rx = self.tx.subscribe();
+        let resumable = resume.filter(|n| {
+            *n == s.seq || (*n < s.seq && s.backlog.front().is_some_and(|f| f.seq <= n + 1))
+        });
+        let pending = match resumable {
+            Some(n) => s.backlog.iter().filter(|f| f.seq > n).cloned().collect(),
+            None => VecDeque::from([Arc::new(self.snapshot(s))]),
+        };
+        (pending, rx)
+    }
+
+    // 采样任务只持有弱引用，应用状态释放后自行退出
+    fn spawn(self: &Arc<Self>) {
+        let weak = Arc::downgrade(self);
+        tokio::spawn(async move {
+            loop {
+                let Some(interval) =
+                    Weak::upgrade(&weak).map(|s| s.config.read(|c| c.metrics.stream_interval_ms))
+                else {
+                    return;
+                };
+                tokio::time::sleep(Duration::from_millis(interval)).await;
+                match weak.upgrade() {
+                    Some(stream) if stream.subscribers() > 0 => stream.tick(),
+                    Some(_) => {}
+                    None => return,
+                }
+            }
+        });
+    }
+
+    fn tick(&self) {
+        let mut sampler = self.sampler.lock().unwrap();
+        let Some(s) = sampler.as_mut() else {
+            return;
+        };
+        let now = Instant::now();
+        let current = self.sample();
+        let empty = (RouteTotals::default(), 0);
+        let mut routes = serde_json::Map::new();
+        for (route, (totals, in_flight)) in &current {
+            let (before, before_in_flight) = s.routes.get(route).unwrap_or(&empty);
+            let delta = totals.since(before);
+            let changed =
+                delta.latency_count > 0 || delta.slow_requests > 0 || in_flight != before_in_flight;
+            if changed {
+                routes.insert(route.clone(), route_json(&delta, *in_flight));
+            }
+        }
+        s.seq += 1;
+        let data = serde_json::json!({
+            "seq": s.seq,
+            "time": rfc3339(SystemTime::now()),
+            "interval_ms": now.duration_since(s.at).as_millis() as u64,
+            "routes": routes,
+        });
+        let frame = Arc::new(self.frame(s.seq, "delta", data));
+        s.at = now;
+        s.routes = current;
+        if s.backlog.len() == BACKLOG {
+            s.backlog.pop_front();
+        }
+        s.backlog.push_back(frame.clone());
+        let _ = self.tx.send(frame);
+    }
+
+    fn sample(&self) -> Sample {
+        self.metrics
+            .snapshot()
+            .into_iter()
+            .map(|(route, m)| (route, (m.totals(), m.in_flight())))
+            .collect()
+    }
+
+    fn snapshot(&self, s: &Sampler) -> Frame {
+        let routes: serde_json::Map<_, _> = s
+            .routes
+            .iter()
+            .map(|(route, (totals, in_flight))| (route.clone(), route_json(totals, *in_flight)))
+            .collect();
+        let data = serde_json::json!({
+            "seq": s.seq,
+            "time": rfc3339(SystemTime::now()),
+            "routes": routes,
+        });
+        self.frame(s.seq, "snapshot", data)
+    }
+
+    fn frame(&self, seq: u64, event: &'static str, data: serde_json::Value) -> Frame {
+        Frame {
+            seq,
+            id: format!("{}-{}", self.instance, seq),
+            event,
+            data: data.to_string(),
+        }
+    }
+
+    fn parse_id(&self, id: &str) -> Option<u64> {
+        let (instance, seq) = id.trim().split_once('-')?;
+        if instance.parse::<u64>().ok()? != self.instance {
+            return None;
+        }
+        seq.parse().ok()
+    }
+}
+
+fn route_json(totals: &RouteTotals, in_flight: i64) -> serde_json::Value {
+    serde_json::json!({
+        "requests": totals.total_requests(),
+        "by_status": totals.requests,
+        "slow_requests": totals.slow_requests,
+        "latency_count": totals.latency_count,
+        "latency_sum_seconds": totals.latency_sum_seconds,
+        "in_flight": in_flight,
+    })
+}
+
+// 订阅者计数：随 Subscription 一起释放（连接断开时）
+struct SubscriberGuard(Arc<MetricsStream>);
+
+impl Drop for SubscriberGuard {
+    fn drop(&mut self) {
+        self.0.subscribers.fetch_sub(1, Ordering::AcqRel);
+    }
+}
+
+pub struct Subscription {
+    pending: VecDeque<Arc<Frame>>,
+    rx: broadcast::Receiver<Arc<Frame>>,
+    guard: SubscriberGuard,
+}
+
+impl Subscription {
+    // 下一帧：先发送连接时待发的帧（snapshot 或补发的 delta），再依次发送新的 delta
+    pub async fn next(&mut self) -> Option<Arc<Frame>> {
+        loop {
+            if let Some(frame) = self.pending.pop_front() {
+                return Some(frame);
+            }
+            match self.rx.recv().await {
+                Ok(frame) => return Some(frame),
+                Err(RecvError::Lagged(_)) => {
+                    (self.pending, self.rx) = self.guard.0.attach(None);
+                }
+                Err(RecvError::Closed) => return None,
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::Config;
+
+    fn stream(max: usize) -> Arc<MetricsStream> {
+        let mut cfg = Config::default();
+        cfg.metrics.stream_max_subscribers = max;
+        let metrics = Arc::new(RequestMetrics::new(&cfg.metrics));
+        Arc::new(MetricsStream::new(
+            metrics,
+            Arc::new(RuntimeConfig::new(cfg, None)),
+        ))
+    }
+
+    // 新连接先收到 snapshot；Last-Event-ID 在保留范围内时补发之后的 delta，否则重新发送 snapshot
+    #[tokio::test]
+    async fn snapshot_then_resume() {
+        let stream = stream(2);
+        let mut sub = stream.subscribe(None).unwrap();
+        let first = sub.next().await.unwrap();
+        assert_eq!((first.event, first.seq), ("snapshot", 0));
+        stream.tick();
+        stream.tick();
+        let delta = sub.next().await.unwrap();
+        assert_eq!((delta.event, delta.seq), ("delta", 1));
+
+        let mut resumed = stream.subscribe(Some(&delta.id)).unwrap();
+        let next = resumed.next().await.unwrap();
+        assert_eq!((next.event, next.seq), ("delta", 2));
+        assert!(stream.subscribe(None).is_none());
+
+        drop(resumed);
+        let mut other = stream.subscribe(Some("1-1")).unwrap();
+        let next = other.next().await.unwrap();
+        assert_eq!((next.event, next.seq), ("snapshot", 2));
+        drop((sub, other));
+        assert_eq!(stream.subscribers(), 0);
+    }
+}
@@ -0,0 +1,927 @@
+// OpenTelemetry 导出（otel 特性）：按 OTLP/HTTP（JSON 编码）将请求 span 与请求指标发送给 collector
+// - OtelConfig::from_env：读取标准 OTEL_* 环境变量（端点、协议、请求头、超时、service.name 与资源属性、导出间隔）
+// - OtelLayer：为每个请求生成服务端 span；请求头 traceparent / tracestate（W3C Trace Context）合法时作为其子 span，
+//   否则新建根 trace；span 上下文写入请求扩展（TraceLayer 的 span 据此记录 trace_id），并在处理请求期间设为当前上下文
+// - inject：发起下游请求时写入当前 span 的 traceparent / tracestate
+// - Telemetry：span 先进入有界队列，按 OTEL_BSP_SCHEDULE_DELAY 批量导出；指标（累计值）按 OTEL_METRIC_EXPORT_INTERVAL 导出
+// 仅支持 http/json 协议与 http:// 端点（gRPC、protobuf 与 TLS 需要的依赖未引入），其他取值在启动时报错
+use std::{
+    fmt,
+    future::Future,
+    io,
+    pin::Pin,
+    str::FromStr,
+    sync::{
+        atomic::{AtomicU64, Ordering},
+        Arc, Mutex,
+    },
+    task::{Context, Poll},
+    time::{Duration, Instant, SystemTime, UNIX_EPOCH},
+};
+
+use axum::{
+    extract::{MatchedPath, Request},
+    http::{HeaderMap, HeaderName, HeaderValue},
+    response::Response,
+};
+use serde_json::{json, Value};
+use thiserror::Error;
+use tokio::{
+    io::{AsyncReadExt, AsyncWriteExt},
+    net::TcpStream,
+    task::JoinHandle,
+};
+use tower::{Layer, Service};
+use tracing::warn;
+
+use crate::{
+    metrics::RequestMetrics,
+    request_id::{random_u64, RequestId},
+};
+
+pub const TRACEPARENT: HeaderName = HeaderName::from_static("traceparent");
+pub const TRACESTATE: HeaderName = HeaderName::from_static("tracestate");
+
+// 未设置 OTEL_EXPORTER_OTLP_ENDPOINT 时使用的 collector 地址（OTLP/HTTP 默认端口）
+const DEFAULT_ENDPOINT: &str = "http://localhost:4318";
+
+// 读取 collector 响应的最大字节数
+const MAX_RESPONSE_BYTES: u64 = 64 * 1024;
+
+// 服务端 span（OTLP SpanKind::SERVER）与错误状态（StatusCode::ERROR）
+const SPAN_KIND_SERVER: u8 = 2;
+const STATUS_ERROR: u8 = 2;
+
+// 累计型指标（AggregationTemporality::CUMULATIVE）
+const CUMULATIVE: u8 = 2;
+
+tokio::task_local! {
+    static CURRENT: SpanContext;
+}
+
+#[derive(Error, Debug)]
+pub enum OtelError {
+    #[error("{key} 的取值无效: {value}")]
+    InvalidValue { key: String, value: String },
+    #[error("{key}={value}: 仅支持 http/json 协议")]
+    UnsupportedProtocol { key: String, value: String },
+    #[error("{key}={value}: 仅支持 otlp 或 none")]
+    UnsupportedExporter { key: String, value: String },
+}
+
+// collector 端点：仅支持 http://host[:port][/path]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Endpoint {
+    authority: String,
+    host: String,
+    port: u16,
+    path: String,
+}
+
+impl FromStr for Endpoint {
+    type Err = ();
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let rest = s.strip_prefix("http://").ok_or(())?;
+        let (authority, path) = match rest.find('/') {
+            Some(i) => (&rest[..i], &rest[i..]),
+            None => (rest, "/"),
+        };
+        // IPv6 地址写作 [::1]:4318
+        let (host, port) = match authority.rsplit_once(':') {
+            Some((h, p)) if !p.contains(']') => (h, p.parse().map_err(|_| ())?),
+            _ => (authority, 80),
+        };
+        let host = host.trim_start_matches('[').trim_end_matches(']');
+        if host.is_empty() {
+            return Err(());
+        }
+        Ok(Self {
+            authority: authority.to_string(),
+            host: host.to_string(),
+            port,
+            path: path.to_string(),
+        })
+    }
+}
+
+impl fmt::Display for Endpoint {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "http://{}{}", self.authority, self.path)
+    }
+}
+
+impl Endpoint {
+    // 通用端点追加信号路径（/v1/traces、/v1/metrics）
+    fn join(&self, signal: &str) -> Self {
+        Self {
+            path: format!("{}{signal}", self.path.trim_end_matches('/')),
+            ..self.clone()
+        }
+    }
+}
+
+// 导出配置；traces / metrics 为 None 表示该信号不导出（OTEL_*_EXPORTER=none）
+#[derive(Debug, Clone, PartialEq)]
+pub struct OtelConfig {
+    pub traces: Option<Endpoint>,
+    pub metrics: Option<Endpoint>,
+    pub headers: Vec<(String, String)>,
+    pub timeout: Duration,
+    pub resource: Vec<(String, String)>,
+    pub schedule_delay: Duration,
+    pub max_queue_size: usize,
+    pub max_export_batch_size: usize,
+    pub metric_interval: Duration,
+}
+
+impl OtelConfig {
+    // 读取 OTEL_* 环境变量（通过闭包读取，便于测试时注入）；OTEL_SDK_DISABLED=true 或两类信号均为 none 时返回 None
+    pub fn from_env(var: impl Fn(&str) -> Option<String>) -> Result<Option<Self>, OtelError> {
+        let var = |key: &str| var(key).filter(|v| !v.trim().is_empty());
+        let invalid = |key: &str, value: &str| OtelError::InvalidValue {
+            key: key.to_string(),
+            value: value.to_string(),
+        };
+        if var("OTEL_SDK_DISABLED").is_some_and(|v| v.eq_ignore_ascii_case("true")) {
+            return Ok(None);
+        }
+        let millis = |key: &str, default: u64| match var(key) {
+            Some(v) => v
+                .trim()
+                .parse()
+                .map(Duration::from_millis)
+                .map_err(|_| invalid(key, &v)),
+            None => Ok(Duration::from_millis(default)),
+        };
+        let count = |key: &str, default: usize| match var(key) {
+            Some(v) => match v.trim().parse() {
+                Ok(n) if n > 0 => Ok(n),
+                _ => Err(invalid(key, &v)),
+            },
+            None => Ok(default),
+        };
+
+        let base = match var("OTEL_EXPORTER_OTLP_ENDPOINT") {
+            Some(v) => v
+                .trim()
+                .parse::<Endpoint>()
+                .map_err(|_| invalid("OTEL_EXPORTER_OTLP_ENDPOINT", &v))?,
+            None => DEFAULT_ENDPOINT.parse().unwrap(),
+        };
+        let general_protocol = var("OTEL_EXPORTER_OTLP_PROTOCOL");
+        let signal = |name: &str, path: &str| -> Result<Option<Endpoint>, OtelError> {
+            let upper = name.to_ascii_uppercase();
+            let exporter_key = format!("OTEL_{upper}_EXPORTER");
+            match var(&exporter_key).as_deref().map(str::trim) {
+                None | Some("otlp") => {}
+                Some("none") => return Ok(None),
+                Some(v) => {
+                    return Err(OtelError::UnsupportedExporter {
+                        key: exporter_key,
+                        value: v.to_string(),
+                    })
+                }
+            }
+            let protocol_key = format!("OTEL_EXPORTER_OTLP_{upper}_PROTOCOL");
+            let (key, protocol) = match var(&protocol_key) {
+                Some(v) => (protocol_key, Some(v)),
+                None => (
+                    "OTEL_EXPORTER_OTLP_PROTOCOL".into(),
+                    general_protocol.clone(),
+                ),
+            };
+            if let Some(p) = protocol.filter(|p| p.trim() != "http/json") {
+                return Err(OtelError::UnsupportedProtocol { key, value: p });
+            }
+            let endpoint_key = format!("OTEL_EXPORTER_OTLP_{upper}_ENDPOINT");
+            match var(&endpoint_key) {
+                Some(v) => v
+                    .trim()
+                    .parse()
+                    .map(Some)
+                    .map_err(|_| invalid(&endpoint_key, &v)),
+                None => Ok(Some(base.join(path))),
+            }
+        };
+        let traces = signal("traces", "/v1/traces")?;
+        let metrics = signal("metrics", "/v1/metrics")?;
+        if traces.is_none() && metrics.is_none() {
+            return Ok(None);
+        }
+
+        let headers = match var("OTEL_EXPORTER_OTLP_HEADERS") {
+            Some(v) => parse_pairs(&v)
+                .filter(|pairs| {
+                    pairs.iter().all(|(k, v)| {
+                        HeaderName::from_str(k).is_ok() && HeaderValue::from_str(v).is_ok()
+                    })
+                })
+                .ok_or_else(|| invalid("OTEL_EXPORTER_OTLP_HEADERS", &v))?,
+            None => Vec::new(),
+        };
+        let mut resource = match var("OTEL_RESOURCE_ATTRIBUTES") {
+            Some(v) => parse_pairs(&v).ok_or_else(|| invalid("OTEL_RESOURCE_ATTRIBUTES", &v))?,
+            None => Vec::new(),
+        };
+        // service.name：OTEL_SERVICE_NAME 优先于资源属性中的同名项，均未设置时为 rustdemo
+        let service = var("OTEL_SERVICE_NAME").map(|v| v.trim().to_string());
+        match (service, resource.iter().any(|(k, _)| k == "service.name")) {
+            (Some(name), _) => {
+                resource.retain(|(k, _)| k != "service.name");
+                resource.insert(0, ("service.name".into(), name));
+            }
+            (None, false) => resource.insert(0, ("service.name".into(), "rustdemo".into())),
+            (None, true) => {}
+        }
+        resource.push(("service.version".into(), env!("CARGO_PKG_VERSION").into()));
+
+        Ok(Some(Self {
+            traces,
+            metrics,
+            headers,
+            timeout: millis("OTEL_EXPORTER_OTLP_TIMEOUT", 10_000)?,
+            resource,
+            schedule_delay: millis("OTEL_BSP_SCHEDULE_DELAY", 5_000)?,
+            max_queue_size: count("OTEL_BSP_MAX_QUEUE_SIZE", 2048)?,
+            max_export_batch_size: count("OTEL_BSP_MAX_EXPORT_BATCH_SIZE", 512)?,
+            metric_interval: millis("OTEL_METRIC_EXPORT_INTERVAL", 60_000)?,
+        }))
+    }
+}
+
+// "k1=v1,k2=v2"（取值可含 %XX 转义）；格式错误时返回 None
+fn parse_pairs(s: &str) -> Option<Vec<(String, String)>> {
+    s.split(',')
+        .map(str::trim)
+        .filter(|p| !p.is_empty())
+        .map(|pair| {
+            let (k, v) = pair.split_once('=')?;
+            let k = k.trim();
+            (!k.is_empty()).then(|| (k.to_string(), percent_decode(v.trim())))
+        })
+        .collect()
+}
+
+fn percent_decode(s: &str) -> String {
+    let bytes = s.as_bytes();
+    let mut out = Vec::with_capacity(bytes.len());
+    let mut i = 0;
+    while i < bytes.len() {
+        let hex = bytes
+            .get(i + 1..i + 3)
+            .and_then(|h| std::str::from_utf8(h).ok())
+            .and_then(|h| u8::from_str_radix(h, 16).ok());
+        match (bytes[i], hex) {
+            (b'%', Some(b)) => {
+                out.push(b);
+                i += 3;
+            }
+            (b, _) => {
+                out.push(b);
+                i += 1;
+            }
+        }
+    }
+    String::from_utf8_lossy(&out).into_owned()
+}
+
+// W3C Trace Context：当前服务端 span 的上下文
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SpanContext {
+    pub trace_id: [u8; 16],
+    pub span_id: [u8; 8],
+    pub parent_span_id: Option<[u8; 8]>,
+    pub sampled: bool,
+    pub trace_state: Option<String>,
+}
+
+impl SpanContext {
+    // traceparent 合法时作为其子 span（沿用 trace_id、采样标志与 tracestate），否则新建根 span（采样）
+    pub fn from_headers(headers: &HeaderMap) -> Self {
+        let span_id = random_id();
+        let parent = headers
+            .get(&TRACEPARENT)
+            .and_then(|v| v.to_str().ok())
+            .and_then(parse_traceparent);
+        match parent {
+            Some((trace_id, parent_id, sampled)) => Self {
+                trace_id,
+                span_id,
+                parent_span_id: Some(parent_id),
+                sampled,
+                trace_state: headers
+                    .get(&TRACESTATE)
+                    .and_then(|v| v.to_str().ok())
+                    .map(str::to_string),
+            },
+            None => {
+                let mut trace_id = [0; 16];
+                trace_id[..8].copy_from_slice(&random_id());
+                trace_id[8..].copy_from_slice(&random_id());
+                Self {
+                    trace_id,
+                    span_id,
+                    parent_span_id: None,
+                    sampled: true,
+                    trace_state: None,
+                }
+            }
+        }
+    }
+
+    pub fn trace_id_hex(&self) -> String {
+        hex(&self.trace_id)
+    }
+
+    pub fn traceparent(&self) -> String {
+        format!(
+            "00-{}-{}-{:02x}",
+            hex(&self.trace_id),
+            hex(&self.span_id),
+            u8::from(self.sampled)
+        )
+    }
+}
+
+// 非零的 64 位随机 ID
+fn random_id() -> [u8; 8] {
+    random_u64().max(1).to_be_bytes()
+}
+
+// traceparent："{version}-{trace_id}-{parent_id}-{flags}"，均为小写十六进制；
+// 全零 ID 与版本 ff 无效；未知的更高版本按 00 的前四段解析
+fn parse_traceparent(s: &str) -> Option<([u8; 16], [u8; 8], bool)> {
+    let parts: Vec<&str> = s.trim().split('-').collect();
+    let [version, trace, parent, flags, ..] = parts[..] else {
+        return None;
+    };
+    let version = unhex::<1>(version)?[0];
+    if version == 0xff || (version == 0 && parts.len() != 4) {
+        return None;
+    }
+    let trace_id = unhex::<16>(trace).filter(|id| id.iter().any(|b| *b != 0))?;
+    let parent_id = unhex::<8>(parent).filter(|id| id.iter().any(|b| *b != 0))?;
+    let flags = unhex::<1>(flags)?[0];
+    Some((trace_id, parent_id, flags & 1 == 1))
+}
+
+fn hex(bytes: &[u8]) -> String {
+    bytes.iter().map(|b| format!("{b:02x}")).collect()
+}
+
+fn unhex<const N: usize>(s: &str) -> Option<[u8; N]> {
+    if s.len() != N * 2 || !s.bytes().all(|b| matches!(b, b'0'..=b'9' | b'a'..=b'f')) {
+        return None;
+    }
+    let mut out = [0; N];
+    for (i, b) in out.iter_mut().enumerate() {
+        *b = u8::from_str_radix(&s[i * 2..i * 2 + 2], 16).ok()?;
+    }
+    Some(out)
+}
+
+// 当前请求的 span 上下文（不在请求处理期间或在 spawn 出的子任务中时为 None）
+pub fn current() -> Option<SpanContext> {
+    CURRENT.try_with(SpanContext::clone).ok()
+}
+
+// 下游请求传播当前 trace：写入 traceparent（父 span 为当前服务端 span）与 tracestate
+pub fn inject(headers: &mut HeaderMap) {
+    let Some(cx) = current() else { return };
+    if let Ok(v) = HeaderValue::from_str(&cx.traceparent()) {
+        headers.insert(TRACEPARENT, v);
+    }
+    if let Some(v) = cx
+        .trace_state
+        .as_deref()
+        .and_then(|s| HeaderValue::from_str(s).ok())
+    {
+        headers.insert(TRACESTATE, v);
+    }
+}
+
+fn unix_nanos(t: SystemTime) -> String {
+    t.duration_since(UNIX_EPOCH)
+        .unwrap_or_default()
+        .as_nanos()
+        .to_string()
+}
+
+fn attr(key: &str, value: Value) -> Value {
+    json!({ "key": key, "value": value })
+}
+
+fn str_attr(key: &str, value: &str) -> Value {
+    attr(key, json!({ "stringValue": value }))
+}
+
+fn int_attr(key: &str, value: impl fmt::Display) -> Value {
+    attr(key, json!({ "intValue": value.to_string() }))
+}
+
+pub struct Telemetry {
+    config: OtelConfig,
+    start: SystemTime,
+    spans: Mutex<Vec<Value>>,
+    dropped: AtomicU64,
+}
+
+impl Telemetry {
+    pub fn new(config: OtelConfig) -> Arc<Self> {
+        Arc::new(Self {
+            config,
+            start: SystemTime::now(),
+            spans: Mutex::new(Vec::new()),
+            dropped: AtomicU64::new(0),
+        })
+    }
+
+    pub fn config(&self) -> &OtelConfig {
+        &self.config
+    }
+
+    // 后台导出任务：按 schedule_delay 批量导出 span，按 metric_interval 导出指标；导出失败仅记录日志
+    pub fn spawn(self: &Arc<Self>, metrics: Arc<RequestMetrics>) -> JoinHandle<()> {
+        let this = self.clone();
+        tokio::spawn(async move {
+            let mut traces = tokio::time::interval(this.config.schedule_delay);
+            let mut export = tokio::time::interval(this.config.metric_interval);
+            traces.tick().await;
+            export.tick().await;
+            loop {
+                tokio::select! {
+                    _ = traces.tick() => this.flush_traces_logged().await,
+                    _ = export.tick() => this.export_metrics_logged(&metrics).await,
+                }
+            }
+        })
+    }
+
+    // 退出前导出剩余 span 与最终指标
+    pub async fn shutdown(&self, metrics: &RequestMetrics) {
+        self.flush_traces_logged().await;
+        self.export_metrics_logged(metrics).await;
+    }
+
+    async fn flush_traces_logged(&self) {
+        if let Err(e) = self.flush_traces().await {
+            warn!("failed to export spans: {}", e);
+        }
+    }
+
+    async fn export_metrics_logged(&self, metrics: &RequestMetrics) {
+        if let Err(e) = self.export_metrics(metrics).await {
+            warn!("failed to export metrics: {}", e);
+        }
+    }
+
+    // 记录已结束的 span；队列已满时丢弃（下次导出时记录丢弃数量）
+    fn record(&self, span: Value) {
+        if self.config.traces.is_none() {
+            return;
+        }
+        let mut spans = self.spans.lock().unwrap();
+        if spans.len() < self.config.max_queue_size {
+            spans.push(span);
+        } else {
+            self.dropped.fetch_add(1, Ordering::Relaxed);
+        }
+    }
+
+    // 导出队列中的全部 span（每批不超过 max_export_batch_size）；导出失败的批次被丢弃
+    pub async fn flush_traces(&self) -> io::Result<()> {
+        let Some(endpoint) = &self.config.traces else {
+            return Ok(());
+        };
+        let dropped = self.dropped.swap(0, Ordering::Relaxed);
+        if dropped > 0 {
+            warn!("span queue full, dropped {} span(s)", dropped);
+        }
+        loop {
+            let batch: Vec<Value> = {
+                let mut spans = self.spans.lock().unwrap();
+                let n = spans.len().min(self.config.max_export_batch_size);
+                spans.drain(..n).collect()
+            };
+            if batch.is_empty() {
+                return Ok(());
+            }
+            let body = json!({
+                "resourceSpans": [{
+                    "resource": self.resource(),
+                    "scopeSpans": [{ "scope": scope(), "spans": batch }],
+                }],
+            });
+            self.post(endpoint, &body).await?;
+        }
+    }
+
+    // 导出请求指标（自启动以来的累计值）：
+    // - http.server.request.duration：按路由的耗时直方图（秒，桶上界同 metrics.latency_buckets）
+    // - http.server.active_requests：按路由的在途请求数
+    // - rustdemo.http.requests：按路由、方法与状态码的请求数
+    pub async fn export_metrics(&self, metrics: &RequestMetrics) -> io::Result<()> {
+        let Some(endpoint) = &self.config.metrics else {
+            return Ok(());
+        };
+        let start = unix_nanos(self.start);
+        let now = unix_nanos(SystemTime::now());
+        let (mut durations, mut active, mut requests) = (Vec::new(), Vec::new(), Vec::new());
+        for (route, m) in metrics.snapshot() {
+            let route_attr = str_attr("http.route", &route);
+            let buckets = m.latency().cumulative();
+            let mut prev = 0;
+            let counts: Vec<String> = buckets
+                .iter()
+                .map(|(_, c)| {
+                    let n = c - prev;
+                    prev = *c;
+                    n.to_string()
+                })
+                .collect();
+            let bounds: Vec<f64> = buckets[..buckets.len() - 1]
+                .iter()
+                .map(|(le, _)| *le)
+                .collect();
+            durations.push(json!({
+                "attributes": [route_attr],
+                "startTimeUnixNano": start,
+                "timeUnixNano": now,
+                "count": prev.to_string(),
+                "sum": m.latency().sum().as_secs_f64(),
+                "bucketCounts": counts,
+                "explicitBounds": bounds,
+            }));
+            active.push(json!({
+                "attributes": [route_attr],
+                "startTimeUnixNano": start,
+                "timeUnixNano": now,
+                "asInt": m.in_flight().to_string(),
+            }));
+            for ((method, status), n) in m.by_status() {
+                requests.push(json!({
+                    "attributes": [
+                        route_attr,
+                        str_attr("http.request.method", &method),
+                        int_attr("http.response.status_code", status),
+                    ],
+                    "startTimeUnixNano": start,
+                    "timeUnixNano": now,
+                    "asInt": n.to_string(),
+                }));
+            }
+        }
+        let body = json!({
+            "resourceMetrics": [{
+                "resource": self.resource(),
+                "scopeMetrics": [{
+                    "scope": scope(),
+                    "metrics": [
+                        {
+                            "name": "http.server.request.duration",
+                            "unit": "s",
+                            "description": "Duration of HTTP server requests.",
+                            "histogram": { "aggregationTemporality": CUMULATIVE, "dataPoints": durations },
+                        },
+                        {
+                            "name": "http.server.active_requests",
+                            "unit": "{request}",
+                            "description": "Number of active HTTP server requests.",
+                            "sum": { "aggregationTemporality": CUMULATIVE, "isMonotonic": false, "dataPoints": active },
+                        },
+                        {
+                            "name": "rustdemo.http.requests",
+                            "unit": "{request}",
+                            "description": "Completed HTTP requests by route, method and status.",
+                            "sum": { "aggregationTemporality": CUMULATIVE, "isMonotonic": true, "dataPoints": requests },
+                        },
+                    ],
+                }],
+            }],
+        });
+        self.post(endpoint, &body).await
+    }
+
+    fn resource(&self) -> Value {
+        let attributes: Vec<Value> = self
+            .config
+            .resource
+            .iter()
+            .map(|(k, v)| str_attr(k, v))
+            .collect();
+        json!({ "attributes": attributes })
+    }
+
+    // OTLP/HTTP 导出：POST JSON（Connection: close），collector 返回 2xx 视为成功
+    async fn post(&self, endpoint: &Endpoint, body: &Value) -> io::Result<()> {
+        let body = serde_json::to_vec(body).map_err(io::Error::other)?;
+        let mut req = format!(
+            "POST {} HTTP/1.1\r\nHost: {}\r\nContent-Type: application/json\r\nContent-Length: {}\r\nConnection: close\r\n",
+            endpoint.path,
+            endpoint.authority,
+            body.len()
+        );
+        for (k, v) in &self.config.headers {
+            req.push_str(&format!("{k}: {v}\r\n"));
+        }
+        req.push_str("\r\n");
+        let exchange = async {
+            let mut stream = TcpStream::connect((endpoint.host.as_str(), endpoint.port)).await?;
+            stream.write_all(req.as_bytes()).await?;
+            stream.write_all(&body).await?;
+            let mut res = Vec::new();
+            stream
+                .take(MAX_RESPONSE_BYTES)
+                .read_to_end(&mut res)
+                .await?;
+            Ok::<_, io::Error>(res)
+        };
+        let res = tokio::time::timeout(self.config.timeout, exchange)
+            .await
+            .map_err(|_| io::Error::new(io::ErrorKind::TimedOut, "collector 响应超时"))??;
+        let status = std::str::from_utf8(&res)
+            .ok()
+            .and_then(|s| s.split(' ').nth(1))
+            .and_then(|s| s.parse::<u16>().ok());
+        match status {
+            Some(200..=299) => Ok(()),
+            Some(code) => Err(io::Error::other(format!("{endpoint} 返回 HTTP {code}"))),
+            None => Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                format!("{endpoint} 的响应无法解析"),
+            )),
+        }
+    }
+}
+
+fn scope() -> Value {
+    json!({ "name": "rustdemo", "version": env!("CARGO_PKG_VERSION") })
+}
+
+// 追踪中间件；需通过 Router::layer 挂载（逐路由包裹，才能读取到 MatchedPath 作为 span 名称）
+#[derive(Clone)]
+pub struct OtelLayer {
+    telemetry: Arc<Telemetry>,
+}
+
+impl OtelLayer {
+    pub fn new(telemetry: Arc<Telemetry>) -> Self {
+        Self { telemetry }
+    }
+}
+
+impl<S> Layer<S> for OtelLayer {
+    type Service = OtelService<S>;
+
+    fn layer(&self, inner: S) -> Self::Service {
+        OtelService {
+            inner,
+            telemetry: self.telemetry.clone(),
+        }
+    }
+}
+
+#[derive(Clone)]
+pub struct OtelService<S> {
+    inner: S,
+    telemetry: Arc<Telemetry>,
+}
+
+impl<S, B> Service<Request> for OtelService<S>
+where
+    S: Service<Request, Response = Response<B>> + Send + 'static,
+    S::Future: Send + 'static,
+{
+    type Response = S::Response;
+    type Error = S::Error;
+    type Future = Pin<Box<dyn Future<Output = Result<Self::Response, Self::Error>> + Send>>;
+
+    fn poll_ready(&mut self, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        self.inner.poll_ready(cx)
+    }
+
+    fn call(&mut self, mut req: Request) -> Self::Future {
+        let cx = SpanContext::from_headers(req.headers());
+        let method = req.method().to_string();
+        let route = req
+            .extensions()
+            .get::<MatchedPath>()
+            .map(|p| p.as_str().to_string());
+        let mut attributes = vec![
+            str_attr("http.request.method", &method),
+            str_attr("url.path", req.uri().path()),
+        ];
+        if let Some(route) = &route {
+            attributes.push(str_attr("http.route", route));
+        }
+        if let Some(id) = req.extensions().get::<RequestId>() {
+            let value = json!({ "arrayValue": { "values": [{ "stringValue": id.as_str() }] } });
+            attributes.push(attr("http.request.header.x-request-id", value));
+        }
+        let name = match route {
+            Some(route) => format!("{method} {route}"),
+            None => method,
+        };
+        req.extensions_mut().insert(cx.clone());
+
+        let start = SystemTime::now();
+        let timer = Instant::now();
+        let telemetry = self.telemetry.clone();
+        let fut = CURRENT.sync_scope(cx.clone(), || self.inner.call(req));
+        Box::pin(CURRENT.scope(cx.clone(), async move {
+            let res = fut.await?;
+            if cx.sampled {
+                let status = res.status();
+                attributes.push(int_attr("http.response.status_code", status.as_u16()));
+                let mut span = json!({
+                    "traceId": hex(&cx.trace_id),
+                    "spanId": hex(&cx.span_id),
+                    "name": name,
+                    "kind": SPAN_KIND_SERVER,
+                    "startTimeUnixNano": unix_nanos(start),
+                    "endTimeUnixNano": unix_nanos(start + timer.elapsed()),
+                    "attributes": attributes,
+                });
+                if let Some(parent) = &cx.parent_span_id {
+                    span["parentSpanId"] = hex(parent).into();
+                }
+                if let Some(state) = &cx.trace_state {
+                    span["traceState"] = state.as_str().into();
+                }
+                if status.is_server_error() {
+                    span["status"] = json!({ "code": STATUS_ERROR });
+                }
+                telemetry.record(span);
+            }
+            Ok(res)
+        }))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_traceparent() {
+        let (trace, parent, sampled) =
+            parse_traceparent("00-4bf92f3577b34da6a3ce929d0e0e4736-00f067aa0ba902b7-01").unwrap();
+        assert_eq!(hex(&trace), "4bf92f3577b34da6a3ce929d0e0e4736");
+        assert_eq!(hex(&parent), "00f067aa0ba902b7");
+        assert!(sampled);
+        // 更高版本允许附加字段
+        assert!(
+            parse_traceparent("01-4bf92f3577b34da6a3ce929d0e0e4736-00f067aa0ba902b7-00-x")
+                .is_some_and(|(_, _, sampled)| !sampled)
+        );
+        for bad in [
+            "",
+            "ff-4bf92f3577b34da6a3ce929d0e0e4736-00f067aa0ba902b7-01",
+            "00-00000000000000000000000000000000-00f067aa0ba902b7-01",
+            "00-4bf92f3577b34da6a3ce929d0e0e4736-0000000000000000-01",
+            "00-4BF92F3577B34DA6A3CE929D0E0E4736-00f067aa0ba902b7-01",
+            "00-4bf92f3577b34da6a3ce929d0e0e4736-00f067aa0ba902b7-01-x",
+            "00-4bf92f3577b34da6a3ce929d0e0e47-00f067aa0ba902b7-01",
+        ] {
+            assert!(parse_traceparent(bad).is_none(), "{bad}");
+        }
+    }
+
+    // 子 span 沿用 trace_id 与 tracestate，inject 写出以当前 span 为父的 traceparent
+    #[tokio::test]
+    async fn propagates_context() {
+        let mut headers = HeaderMap::new();
+        headers.insert(
+            TRACEPARENT,
+            HeaderValue::from_static("00-4bf92f3577b34da6a3ce929d0e0e4736-00f067aa0ba902b7-01"),
+        );
+        headers.insert(TRACESTATE, HeaderValue::from_static("vendor=abc"));
+        let cx = SpanContext::from_headers(&headers);
+        assert_eq!(cx.trace_id_hex(), "4bf92f3577b34da6a3ce929d0e0e4736");
+        assert_eq!(
+            cx.parent_span_id.map(|p| hex(&p)).unwrap(),
+            "00f067aa0ba902b7"
+        );
+
+        let root = SpanContext::from_headers(&HeaderMap::new());
+        assert!(root.sampled && root.parent_span_id.is_none());
+        assert_ne!(root.trace_id, cx.trace_id);
+
+        let mut out = HeaderMap::new();
+        inject(&mut out);
+        assert!(out.is_empty());
+        CURRENT
+            .scope(cx.clone(), async {
+                inject(&mut out);
+            })
+            .await;
+        assert_eq!(out[TRACEPARENT], cx.traceparent());
+        assert!(out[TRACEPARENT]
+            .to_str()
+            .unwrap()
+            .starts_with("00-4bf92f3577b34da6a3ce929d0e0e4736-"));
+        assert_eq!(out[TRACESTATE], "vendor=abc");
+    }
+
+    #[test]
+    fn config_from_env() {
+        let env = |pairs: &'static [(&'static str, &'static str)]| {
+            move |k: &str| {
+                pairs
+                    .iter()
+                    .find(|(key, _)| *key == k)
+                    .map(|(_, v)| v.to_string())
+            }
+        };
+        let cfg = OtelConfig::from_env(env(&[])).unwrap().unwrap();
+        assert_eq!(
+            cfg.traces.unwrap().to_string(),
+            "http://localhost:4318/v1/traces"
+        );
+        assert_eq!(cfg.resource[0], ("service.name".into(), "rustdemo".into()));
+        assert_eq!(cfg.metric_interval, Duration::from_secs(60));
+
+        let cfg = OtelConfig::from_env(env(&[
+            ("OTEL_EXPORTER_OTLP_ENDPOINT", "http://[::1]:9000/otlp/"),
+            ("OTEL_EXPORTER_OTLP_METRICS_ENDPOINT", "http://collector/m"),
+            ("OTEL_EXPORTER_OTLP_HEADERS", "api-key=a%3Db, x-team = core"),
+            (
+                "OTEL_RESOURCE_ATTRIBUTES",
+                "service.name=a,deployment.environment=prod",
+            ),
+            ("OTEL_SERVICE_NAME", "b"),
+            ("OTEL_BSP_SCHEDULE_DELAY", "100"),
+        ]))
+        .unwrap()
+        .unwrap();
+        let traces = cfg.traces.unwrap();
+        assert_eq!((traces.host.as_str(), traces.port), ("::1", 9000));
+        assert_eq!(traces.path, "/otlp/v1/traces");
+        let metrics = cfg.metrics.unwrap();
+        assert_eq!((metrics.port, metrics.path.as_str()), (80, "/m"));
+        assert_eq!(
+            cfg.headers,
+            vec![
+                ("api-key".into(), "a=b".into()),
+                ("x-team".into(), "core".into())
+            ]
+        );
+        assert_eq!(cfg.resource[0], ("service.name".into(), "b".into()));
+        assert_eq!(cfg.resource.len(), 3);
+        assert_eq!(cfg.schedule_delay, Duration::from_millis(100));
+
+        assert!(OtelConfig::from_env(env(&[("OTEL_SDK_DISABLED", "true")]))
+            .unwrap()
+            .is_none());
+        let cfg = OtelConfig::from_env(env(&[("OTEL_TRACES_EXPORTER", "none")]))
+            .unwrap()
+            .unwrap();
+        assert!(cfg.traces.is_none() && cfg.metrics.is_some());
+        assert!(matches!(
+            OtelConfig::from_env(env(&[("OTEL_EXPORTER_OTLP_PROTOCOL", "grpc")])),
+            Err(OtelError::UnsupportedProtocol { .. })
+        ));
+        assert!(matches!(
+            OtelConfig::from_env(env(&[(
+                "OTEL_EXPORTER_OTLP_ENDPOINT",
+                "https://collector:4318"
+            )])),
+            Err(OtelError::InvalidValue { .. })
+        ));
+    }
+
+    // gRPC 与 protobuf 编码不受支持：通用与按信号的协议变量都在启动时报错，错误信息指明变量与取值
+    #[test]
+    fn rejects_unsupported_protocols() {
+        let from_env = |vars: &[(&str, &str)]| {
+            OtelConfig::from_env(|k| {
+                vars.iter()
+                    .find(|(key, _)| *key == k)
+                    .map(|(_, v)| v.to_string())
+            })
+        };
+        let err = |vars: &[(&str, &str)]| match from_env(vars) {
+            Err(e @ OtelError::UnsupportedProtocol { .. }) => e.to_string(),
+            other => panic!("expected UnsupportedProtocol, got {other:?}"),
+        };
+        assert_eq!(
+            err(&[("OTEL_EXPORTER_OTLP_PROTOCOL", "grpc")]),
+            "OTEL_EXPORTER_OTLP_PROTOCOL=grpc: 仅支持 http/json 协议"
+        );
+        assert_eq!(
+            err(&[("OTEL_EXPORTER_OTLP_PROTOCOL", "http/protobuf")]),
+            "OTEL_EXPORTER_OTLP_PROTOCOL=http/protobuf: 仅支持 http/json 协议"
+        );
+        assert_eq!(
+            err(&[
+                ("OTEL_EXPORTER_OTLP_PROTOCOL", "http/json"),
+                ("OTEL_EXPORTER_OTLP_METRICS_PROTOCOL", "grpc"),
+            ]),
+            "OTEL_EXPORTER_OTLP_METRICS_PROTOCOL=grpc: 仅支持 http/json 协议"
+        );
+        assert!(from_env(&[("OTEL_EXPORTER_OTLP_PROTOCOL", "http/json")])
+            .unwrap()
+            .is_some());
+    }
+}
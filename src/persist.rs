@@ -0,0 +1,269 @@
+// 指标累计计数的持久化（metrics.state_file）：
+// - Totals：各路由按 (方法, 状态码) 的请求数、慢请求数与耗时计数 / 总和，以及开始累计的时间
+// - load / save：JSON 文件；先写临时文件（每次写入各用一个文件名）再改名，进程中途退出也不会留下半个文件
+// - spawn：按 metrics.state_interval_secs 定期写入；优雅关闭时由 main 先 stop（等待正在进行的写入完成）再写一次
+// 启动时读到的 Totals 作为 RequestMetrics 的基线，/metrics 的累计值为基线加上本次启动以来的计数
+use std::{
+    collections::BTreeMap,
+    fs, io,
+    path::{Path, PathBuf},
+    sync::{
+        atomic::{AtomicU64, Ordering},
+        Arc,
+    },
+    time::{Duration, SystemTime, UNIX_EPOCH},
+};
+
+use serde::{Deserialize, Serialize};
+use tokio::{sync::oneshot, task::JoinHandle};
+use tracing::warn;
+
+use crate::metrics::RequestMetrics;
+
+// 文件格式版本；不同版本的文件拒绝读取
+const VERSION: u32 = 1;
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct Totals {
+    // 开始累计的时间（Unix 秒）
+    pub since_unix: u64,
+    pub routes: BTreeMap<String, RouteTotals>,
+}
+
+impl Totals {
+    pub fn new(since: SystemTime) -> Self {
+        Self {
+            since_unix: unix_secs(since),
+            routes: BTreeMap::new(),
+        }
+    }
+
+    pub fn since(&self) -> SystemTime {
+        UNIX_EPOCH + Duration::from_secs(self.since_unix)
+    }
+
+    pub fn add_route(&mut self, route: &str, other: &RouteTotals) {
+        self.routes.entry(route.to_string()).or_default().add(other);
+    }
+}
+
+// 单个路由模板的计数；requests 为 方法 -> 状态码 -> 请求数
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct RouteTotals {
+    pub requests: BTreeMap<String, BTreeMap<u16, u64>>,
+    pub slow_requests: u64,
+    pub latency_count: u64,
+    pub latency_sum_seconds: f64,
+}
+
+impl RouteTotals {
+    pub fn add(&mut self, other: &RouteTotals) {
+        for (method, statuses) in &other.requests {
+            let mine = self.requests.entry(method.clone()).or_default();
+            for (status, n) in statuses {
+                *mine.entry(*status).or_default() += n;
+            }
+        }
+        self.slow_requests += other.slow_requests;
+        self.latency_count += other.latency_count;
+        self.latency_sum_seconds += other.latency_sum_seconds;
+    }
+
+    // 自 earlier（同一路由较早的计数）以来的增量；省略增量为 0 的 (方法, 状态码)
+    pub fn since(&self, earlier: &RouteTotals) -> RouteTotals {
+        let mut requests: BTreeMap<String, BTreeMap<u16, u64>> = BTreeMap::new();
+        for (method, statuses) in &self.requests {
+            for (status, n) in statuses {
+                let before = earlier
+                    .requests
+                    .get(method)
+                    .and_then(|s| s.get(status))
+                    .copied()
+                    .unwrap_or(0);
+                if *n > before {
+                    requests
+                        .entry(method.clone())
+                        .or_default()
+                        .insert(*status, n - before);
+                }
+            }
+        }
+        RouteTotals {
+            requests,
+            slow_requests: self.slow_requests.saturating_sub(earlier.slow_requests),
+            latency_count: self.latency_count.saturating_sub(earlier.latency_count),
+            latency_sum_seconds: (self.latency_sum_seconds - earlier.latency_sum_seconds).max(0.0),
+        }
+    }
+
+    pub fn total_requests(&self) -> u64 {
+        self.requests.values().flat_map(|s| s.values()).sum()
+    }
+}
+
+#[derive(Serialize, Deserialize)]
+struct StateFile {
+    version: u32,
+    saved_unix: u64,
+    since_unix: u64,
+    routes: BTreeMap<String, RouteTotals>,
+}
+
+// 文件不存在时返回 None；内容无法解析或版本不符时返回 InvalidData 错误
+pub fn load(path: &Path) -> io::Result<Option<Totals>> {
+    let data = match fs::read(path) {
+        Ok(data) => data,
+        Err(e) if e.kind() == io::ErrorKind::NotFound => return Ok(None),
+        Err(e) => return Err(e),
+    };
+    let file: StateFile =
+        serde_json::from_slice(&data).map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))?;
+    if file.version != VERSION {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidData,
+            format!("不支持的文件版本 {}（应为 {VERSION}）", file.version),
+        ));
+    }
+    Ok(Some(Totals {
+        since_unix: file.since_unix,
+        routes: file.routes,
+    }))
+}
+
+pub fn save(path: &Path, totals: &Totals) -> io::Result<()> {
+    let file = StateFile {
+        version: VERSION,
+        saved_unix: unix_secs(SystemTime::now()),
+        since_unix: totals.since_unix,
+        routes: totals.routes.clone(),
+    };
+    let data = serde_json::to_vec_pretty(&file)?;
+    // 临时文件名含进程 ID 与序号：同时进行的写入（含交接前后的两个进程）互不干扰
+    static SEQ: AtomicU64 = AtomicU64::new(0);
+    let mut tmp = path.as_os_str().to_owned();
+    tmp.push(format!(
+        ".tmp.{}.{}",
+        std::process::id(),
+        SEQ.fetch_add(1, Ordering::Relaxed)
+    ));
+    let tmp = PathBuf::from(tmp);
+    let written = fs::write(&tmp, &data)
+        .and_then(|()| fs::File::open(&tmp)?.sync_all())
+        .and_then(|()| fs::rename(&tmp, path));
+    if written.is_err() {
+        let _ = fs::remove_file(&tmp);
+    }
+    written
+}
+
+// 定期写入任务
+pub struct Persister {
+    stop: oneshot::Sender<()>,
+    task: JoinHandle<()>,
+}
+
+impl Persister {
+    // 停止定期写入；正在进行的写入（在阻塞线程上，无法中途取消）完成后才返回，
+    // 之后的写入不会与其交错或被其覆盖
+    pub async fn stop(self) {
+        let _ = self.stop.send(());
+        let _ = self.task.await;
+    }
+}
+
+// 定期写入累计计数；写入失败只记录日志，下个周期重试
+pub fn spawn(metrics: Arc<RequestMetrics>, path: PathBuf, interval: Duration) -> Persister {
+    let (stop, mut stopped) = oneshot::channel();
+    let task = tokio::spawn(async move {
+        let mut ticker = tokio::time::interval(interval);
+        ticker.tick().await;
+        loop {
+            tokio::select! {
+                _ = ticker.tick() => {}
+                _ = &mut stopped => return,
+            }
+            let totals = metrics.totals();
+            let target = path.clone();
+            match tokio::task::spawn_blocking(move || save(&target, &totals)).await {
+                Ok(Ok(())) => {}
+                Ok(Err(e)) => warn!("failed to save metrics state to {:?}: {}", path, e),
+                Err(e) => warn!("metrics state task failed: {}", e),
+            }
+        }
+    });
+    Persister { stop, task }
+}
+
+fn unix_secs(t: SystemTime) -> u64 {
+    t.duration_since(UNIX_EPOCH).unwrap_or_default().as_secs()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn route(method: &str, status: u16, n: u64) -> RouteTotals {
+        RouteTotals {
+            requests: BTreeMap::from([(method.to_string(), BTreeMap::from([(status, n)]))]),
+            slow_requests: 1,
+            latency_count: n,
+            latency_sum_seconds: 0.5,
+        }
+    }
+
+    #[test]
+    fn saves_and_loads() {
+        let dir = std::env::temp_dir().join(format!("rustdemo-persist-{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("metrics.json");
+        assert_eq!(load(&path).unwrap(), None);
+
+        let mut totals = Totals::new(UNIX_EPOCH + Duration::from_secs(1_700_000_000));
+        totals.add_route("/sum", &route("GET", 200, 3));
+        totals.add_route("/sum", &route("GET", 400, 1));
+        totals.add_route("/sum", &route("GET", 200, 2));
+        let sum = &totals.routes["/sum"];
+        assert_eq!(sum.requests["GET"][&200], 5);
+        assert_eq!(sum.total_requests(), 6);
+        assert_eq!(sum.slow_requests, 3);
+        let delta = sum.since(&route("GET", 200, 4));
+        assert_eq!(
+            delta.requests,
+            BTreeMap::from([("GET".to_string(), BTreeMap::from([(200, 1), (400, 1)]))])
+        );
+        assert_eq!((delta.slow_requests, delta.latency_count), (2, 2));
+
+        save(&path, &totals).unwrap();
+        assert_eq!(load(&path).unwrap(), Some(totals));
+        assert_eq!(fs::read_dir(&dir).unwrap().count(), 1);
+
+        fs::write(
+            &path,
+            r#"{"version":9,"saved_unix":0,"since_unix":0,"routes":{}}"#,
+        )
+        .unwrap();
+        assert_eq!(load(&path).unwrap_err().kind(), io::ErrorKind::InvalidData);
+        fs::write(&path, "{").unwrap();
+        assert!(load(&path).is_err());
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    // stop 等待定期写入任务结束；之后目录中只有目标文件（没有残留的临时文件）
+    #[tokio::test]
+    async fn stops_periodic_saves() {
+        let dir = std::env::temp_dir().join(format!("rustdemo-persister-{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("metrics.json");
+        let metrics = Arc::new(RequestMetrics::new(
+            &crate::config::Config::default().metrics,
+        ));
+        let persister = spawn(metrics, path.clone(), Duration::from_millis(5));
+        tokio::time::sleep(Duration::from_millis(50)).await;
+        persister.stop().await;
+        assert!(load(&path).unwrap().is_some());
+        fs::remove_file(&path).unwrap();
+        tokio::time::sleep(Duration::from_millis(20)).await;
+        assert_eq!(fs::read_dir(&dir).unwrap().count(), 0);
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -0,0 +1,282 @@
+// 最近请求记录（/debug/requests）：
+// - RecentRequests：固定容量的环形缓冲，保存最近完成的请求（方法、路由模板、路径、查询串、状态码、耗时、请求 ID、
+//   AppError 的错误信息），写满后淘汰最旧的记录；新记录同时广播给订阅者（SSE 实时推送）
+// - Filter：按状态码（"404"）或状态类别（"5xx"）与路由模板筛选
+// - RecentRequestsLayer：请求完成时写入一条记录（metrics.recent_requests 为 0 时不挂载）
+use std::{
+    collections::VecDeque,
+    future::Future,
+    pin::Pin,
+    str::FromStr,
+    sync::{
+        atomic::{AtomicU64, Ordering},
+        Arc, Mutex,
+    },
+    task::{Context, Poll},
+    time::{Instant, SystemTime},
+};
+
+use axum::{
+    extract::{MatchedPath, Request},
+    response::Response,
+};
+use serde::Serialize;
+use tokio::sync::broadcast;
+use tower::{Layer, Service};
+
+use crate::{
+    access_log::rfc3339, error::ErrorMessage, metrics::FALLBACK_ROUTE, request_id::RequestId,
+};
+
+// 广播通道容量：订阅者落后超过该条数时跳过较旧的记录
+const BROADCAST_CAPACITY: usize = 256;
+
+#[derive(Debug, Clone, Serialize)]
+pub struct RequestRecord {
+    pub seq: u64,
+    pub time: String,
+    pub method: String,
+    pub route: String,
+    pub path: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub query: Option<String>,
+    pub status: u16,
+    pub latency_ms: f64,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub request_id: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+}
+
+pub struct RecentRequests {
+    capacity: usize,
+    records: Mutex<VecDeque<Arc<RequestRecord>>>,
+    seq: AtomicU64,
+    tx: broadcast::Sender<Arc<RequestRecord>>,
+}
+
+impl RecentRequests {
+    pub fn new(capacity: usize) -> Self {
+        Self {
+            capacity,
+            records: Mutex::new(VecDeque::with_capacity(capacity)),
+            seq: AtomicU64::new(0),
+            tx: broadcast::channel(BROADCAST_CAPACITY).0,
+        }
+    }
+
+    pub fn capacity(&self) -> usize {
+        self.capacity
+    }
+
+    // 写入一条记录（seq 由此处分配，从 1 开始递增）
+    pub fn push(&self, mut record: RequestRecord) {
+        if self.capacity == 0 {
+            return;
+        }
+        record.seq = self.seq.fetch_add(1, Ordering::Relaxed) + 1;
+        let record = Arc::new(record);
+        {
+            let mut records = self.records.lock().unwrap();
+            if records.len() == self.capacity {
+                records.pop_front();
+            }
+            records.push_back(record.clone());
+        }
+        let _ = self.tx.send(record);
+    }
+
+    // 符合条件的记录，最新的在前，最多 limit 条
+    pub fn snapshot(&self, filter: &Filter, limit: usize) -> Vec<Arc<RequestRecord>> {
+        let records = self.records.lock().unwrap();
+        records
+            .iter()
+            .rev()
+            .filter(|r| filter.matches(r))
+            .take(limit)
+            .cloned()
+            .collect()
+    }
+
+    // 订阅之后写入的记录
+    pub fn subscribe(&self) -> broadcast::Receiver<Arc<RequestRecord>> {
+        self.tx.subscribe()
+    }
+}
+
+// 状态码筛选：具体状态码或状态类别（1xx..5xx）
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum StatusFilter {
+    Code(u16),
+    Class(u16),
+}
+
+impl FromStr for StatusFilter {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let invalid = || format!("无效的状态码筛选: {s}（应为如 404 或 5xx）");
+        match s.as_bytes() {
+            [c @ b'1'..=b'5', b'x' | b'X', b'x' | b'X'] => {
+                Ok(StatusFilter::Class(u16::from(c - b'0')))
+            }
+            _ => match s.parse() {
+                Ok(code @ 100..=599) => Ok(StatusFilter::Code(code)),
+                _ => Err(invalid()),
+            },
+        }
+    }
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct Filter {
+    pub status: Option<StatusFilter>,
+    pub route: Option<String>,
+}
+
+impl Filter {
+    pub fn matches(&self, r: &RequestRecord) -> bool {
+        let status = match self.status {
+            None => true,
+            Some(StatusFilter::Code(code)) => r.status == code,
+            Some(StatusFilter::Class(class)) => r.status / 100 == class,
+        };
+        status && self.route.as_ref().is_none_or(|route| *route == r.route)
+    }
+}
+
+#[derive(Clone)]
+pub struct RecentRequestsLayer {
+    recent: Arc<RecentRequests>,
+}
+
+impl RecentRequestsLayer {
+    pub fn new(recent: Arc<RecentRequests>) -> Self {
+        Self { recent }
+    }
+}
+
+impl<S> Layer<S> for RecentRequestsLayer {
+    type Service = RecentRequestsService<S>;
+
+    fn layer(&self, inner: S) -> Self::Service {
+        RecentRequestsService {
+            inner,
+            recent: self.recent.clone(),
+        }
+    }
+}
+
+#[derive(Clone)]
+pub struct RecentRequestsService<S> {
+    inner: S,
+    recent: Arc<RecentRequests>,
+}
+
+impl<S, B> Service<Request> for RecentRequestsService<S>
+where
+    S: Service<Request, Response = Response<B>> + Send + 'static,
+    S::Future: Send + 'static,
+{
+    type Response = S::Response;
+    type Error = S::Error;
+    type Future = Pin<Box<dyn Future<Output = Result<Self::Response, Self::Error>> + Send>>;
+
+    fn poll_ready(&mut self, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        self.inner.poll_ready(cx)
+    }
+
+    fn call(&mut self, req: Request) -> Self::Future {
+        let time = SystemTime::now();
+        let start = Instant::now();
+        let mut record = RequestRecord {
+            seq: 0,
+            time: rfc3339(time),
+            method: req.method().to_string(),
+            route: req
+                .extensions()
+                .get::<MatchedPath>()
+                .map_or(FALLBACK_ROUTE, MatchedPath::as_str)
+                .to_string(),
+            path: req.uri().path().to_string(),
+            query: req.uri().query().map(str::to_string),
+            status: 0,
+            latency_ms: 0.0,
+            request_id: req
+                .extensions()
+                .get::<RequestId>()
+                .map(|id| id.as_str().to_string()),
+            error: None,
+        };
+        let recent = self.recent.clone();
+        let fut = self.inner.call(req);
+        Box::pin(async move {
+            let res = fut.await?;
+            record.status = res.status().as_u16();
+            record.latency_ms = start.elapsed().as_secs_f64() * 1000.0;
+            record.error = res
+                .extensions()
+                .get::<ErrorMessage>()
+                .map(|ErrorMessage(m)| m.clone());
+            recent.push(record);
+            Ok(res)
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn record(route: &str, status: u16) -> RequestRecord {
+        RequestRecord {
+            seq: 0,
+            time: String::new(),
+            method: "GET".into(),
+            route: route.into(),
+            path: route.into(),
+            query: None,
+            status,
+            latency_ms: 1.0,
+            request_id: None,
+            error: None,
+        }
+    }
+
+    // 写满后淘汰最旧的记录；快照最新的在前并按条件筛选
+    #[test]
+    fn keeps_latest_records() {
+        let recent = RecentRequests::new(3);
+        let mut rx = recent.subscribe();
+        for (route, status) in [("/a", 200), ("/sum", 400), ("/sum", 200), ("/b", 503)] {
+            recent.push(record(route, status));
+        }
+        let all = recent.snapshot(&Filter::default(), 10);
+        assert_eq!(all.iter().map(|r| r.seq).collect::<Vec<_>>(), [4, 3, 2]);
+        assert_eq!(recent.snapshot(&Filter::default(), 1)[0].seq, 4);
+        let sum = Filter {
+            route: Some("/sum".into()),
+            ..Filter::default()
+        };
+        assert_eq!(recent.snapshot(&sum, 10).len(), 2);
+        let client_errors = Filter {
+            status: Some("4xx".parse().unwrap()),
+            route: Some("/sum".into()),
+        };
+        assert_eq!(recent.snapshot(&client_errors, 10)[0].seq, 2);
+        assert_eq!(rx.try_recv().unwrap().seq, 1);
+
+        let disabled = RecentRequests::new(0);
+        disabled.push(record("/a", 200));
+        assert!(disabled.snapshot(&Filter::default(), 10).is_empty());
+    }
+
+    #[test]
+    fn parses_status_filter() {
+        assert_eq!("404".parse(), Ok(StatusFilter::Code(404)));
+        assert_eq!("5xx".parse(), Ok(StatusFilter::Class(5)));
+        for bad in ["6xx", "99", "abc", "", "4x"] {
+            assert!(bad.parse::<StatusFilter>().is_err(), "{bad}");
+        }
+    }
+}
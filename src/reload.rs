@@ -0,0 +1,282 @@
+// 配置热加载：
+// - RuntimeConfig：保存启动时配置与当前生效配置，处理器按需读取
+// - spawn_watcher：轮询配置文件修改时间，变化后重新合成配置并应用可热加载项
+// - 需要重启的变更不会生效，仅记录在 pending_restart 中（见 /admin/config）
+// - 日志过滤器：默认取配置中的 log_level；可通过 /admin/log-level 临时覆盖（可设 TTL，到期或撤销后恢复为 log_level）
+use std::{
+    path::PathBuf,
+    sync::{
+        atomic::{AtomicU64, Ordering},
+        Arc, Mutex, RwLock,
+    },
+    time::{Duration, Instant, SystemTime},
+};
+
+use serde::Serialize;
+use thiserror::Error;
+use tracing::{info, warn};
+use tracing_subscriber::{reload, EnvFilter, Registry};
+
+use crate::config::{Config, ConfigLoader};
+
+// 日志过滤器的热替换句柄
+pub type LogHandle = reload::Handle<EnvFilter, Registry>;
+
+pub struct RuntimeConfig {
+    boot: Config,
+    current: RwLock<Config>,
+    pending_restart: RwLock<Vec<&'static str>>,
+    file: Option<PathBuf>,
+    log: Option<LogHandle>,
+    log_override: Mutex<Option<LogOverride>>,
+    override_seq: AtomicU64,
+}
+
+// 临时覆盖的日志过滤规则；id 用于判断 TTL 到期时该覆盖是否已被替换
+struct LogOverride {
+    level: String,
+    expires: Option<Instant>,
+    id: u64,
+}
+
+// /admin/log-level 返回体：生效的过滤规则、配置中的 log_level 与当前覆盖（含剩余秒数）
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct LogLevelStatus {
+    pub level: String,
+    pub configured: String,
+    #[serde(rename = "override")]
+    pub override_: Option<LogOverrideStatus>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct LogOverrideStatus {
+    pub level: String,
+    pub expires_in_secs: Option<u64>,
+}
+
+#[derive(Error, Debug)]
+pub enum LogLevelError {
+    #[error("日志过滤规则无效: {0}")]
+    Invalid(String),
+    #[error("未启用日志过滤器热替换")]
+    Unavailable,
+}
+
+impl RuntimeConfig {
+    pub fn new(cfg: Config, file: Option<PathBuf>) -> Self {
+        Self {
+            boot: cfg.clone(),
+            current: RwLock::new(cfg),
+            pending_restart: RwLock::new(Vec::new()),
+            file,
+            log: None,
+            log_override: Mutex::new(None),
+            override_seq: AtomicU64::new(0),
+        }
+    }
+
+    // 关联日志过滤器的热替换句柄（配置热加载与 /admin/log-level 共用）
+    pub fn with_log_handle(mut self, handle: LogHandle) -> Self {
+        self.log = Some(handle);
+        self
+    }
+
+    // 读取当前配置的某一部分（持锁时间尽量短）
+    pub fn read<T>(&self, f: impl FnOnce(&Config) -> T) -> T {
+        f(&self.current.read().unwrap())
+    }
+
+    pub fn snapshot(&self) -> Config {
+        self.read(Config::clone)
+    }
+
+    pub fn pending_restart(&self) -> Vec<&'static str> {
+        self.pending_restart.read().unwrap().clone()
+    }
+
+    pub fn file(&self) -> Option<&PathBuf> {
+        self.file.as_ref()
+    }
+
+    // 应用新配置：可热加载项立即生效；需要重启的项与启动时配置比较后记录
+    // log_level 变化时替换日志过滤器（存在临时覆盖时等覆盖结束后再生效）
+    pub fn update(&self, new: &Config) {
+        let old_level = self.read(|c| c.log_level.clone());
+        self.current.write().unwrap().apply_reloadable(new);
+        *self.pending_restart.write().unwrap() = self.boot.restart_required_changes(new);
+        let overridden = self.log_override.lock().unwrap().is_some();
+        if new.log_level != old_level && !overridden {
+            if let Err(e) = self.reload_filter(&new.log_level) {
+                warn!("failed to apply log level {:?}: {}", new.log_level, e);
+            }
+        }
+    }
+
+    pub fn log_level(&self) -> LogLevelStatus {
+        let configured = self.read(|c| c.log_level.clone());
+        let current = self.log_override.lock().unwrap();
+        let override_ = current.as_ref().map(|o| LogOverrideStatus {
+            level: o.level.clone(),
+            expires_in_secs: o
+                .expires
+                .map(|t| t.saturating_duration_since(Instant::now()).as_secs()),
+        });
+        LogLevelStatus {
+            level: override_
+                .as_ref()
+                .map_or_else(|| configured.clone(), |o| o.level.clone()),
+            configured,
+            override_,
+        }
+    }
+
+    // 临时覆盖日志过滤规则（可按模块，如 "info,rustdemo::server=trace"）；
+    // 设置 ttl 时到期后自动恢复为配置中的 log_level（需在 Tokio 运行时中调用）
+    pub fn set_log_override(
+        self: &Arc<Self>,
+        level: &str,
+        ttl: Option<Duration>,
+    ) -> Result<LogLevelStatus, LogLevelError> {
+        self.reload_filter(level)?;
+        let id = self.override_seq.fetch_add(1, Ordering::Relaxed);
+        *self.log_override.lock().unwrap() = Some(LogOverride {
+            level: level.to_string(),
+            expires: ttl.map(|ttl| Instant::now() + ttl),
+            id,
+        });
+        info!("log level overridden with {:?} (ttl: {:?})", level, ttl);
+        if let Some(ttl) = ttl {
+            let runtime = Arc::downgrade(self);
+            tokio::spawn(async move {
+                tokio::time::sleep(ttl).await;
+                if let Some(runtime) = runtime.upgrade() {
+                    runtime.expire_log_override(id);
+                }
+            });
+        }
+        Ok(self.log_level())
+    }
+
+    // 撤销临时覆盖，恢复为配置中的 log_level
+    pub fn clear_log_override(&self) -> Result<LogLevelStatus, LogLevelError> {
+        let configured = self.read(|c| c.log_level.clone());
+        self.reload_filter(&configured)?;
+        if self.log_override.lock().unwrap().take().is_some() {
+            info!("log level override cleared, restored {:?}", configured);
+        }
+        Ok(self.log_level())
+    }
+
+    fn expire_log_override(&self, id: u64) {
+        let mut current = self.log_override.lock().unwrap();
+        if current.as_ref().is_none_or(|o| o.id != id) {
+            return;
+        }
+        let configured = self.read(|c| c.log_level.clone());
+        match self.reload_filter(&configured) {
+            Ok(()) => {
+                *current = None;
+                info!("log level override expired, restored {:?}", configured);
+            }
+            Err(e) => warn!("failed to restore log level {:?}: {}", configured, e),
+        }
+    }
+
+    fn reload_filter(&self, level: &str) -> Result<(), LogLevelError> {
+        let filter =
+            EnvFilter::try_new(level).map_err(|e| LogLevelError::Invalid(e.to_string()))?;
+        let handle = self.log.as_ref().ok_or(LogLevelError::Unavailable)?;
+        handle
+            .reload(filter)
+            .map_err(|_| LogLevelError::Unavailable)
+    }
+}
+
+fn modified(path: &PathBuf) -> Option<SystemTime> {
+    std::fs::metadata(path).and_then(|m| m.modified()).ok()
+}
+
+// 启动后台任务监听配置文件；未使用配置文件时不启动
+pub fn spawn_watcher(loader: ConfigLoader, runtime: Arc<RuntimeConfig>) {
+    let Some(path) = loader.path().map(PathBuf::from) else {
+        return;
+    };
+    tokio::spawn(async move {
+        let mut last = modified(&path);
+        loop {
+            let interval = runtime.read(|c| c.reload.interval_secs);
+            tokio::time::sleep(Duration::from_secs(interval)).await;
+            let now = modified(&path);
+            if now.is_none() || now == last {
+                continue;
+            }
+            last = now;
+            let new = match loader.load() {
+                Ok(cfg) => cfg,
+                Err(e) => {
+                    warn!("config reload failed, keeping previous settings: {}", e);
+                    continue;
+                }
+            };
+            if let Err(e) = EnvFilter::try_new(&new.log_level) {
+                warn!("invalid log level {:?}: {}", new.log_level, e);
+                continue;
+            }
+            runtime.update(&new);
+            info!("config reloaded from {:?}", path);
+            let pending = runtime.pending_restart();
+            if !pending.is_empty() {
+                warn!("settings changed that require a restart: {:?}", pending);
+            }
+        }
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use tracing_subscriber::layer::SubscriberExt;
+
+    use super::*;
+
+    fn current_filter(handle: &LogHandle) -> String {
+        handle.with_current(|f| f.to_string()).unwrap()
+    }
+
+    // 覆盖到期后恢复为配置中的 log_level；覆盖期间配置热加载只更新 log_level，不替换过滤器
+    #[tokio::test]
+    async fn log_override_expires() {
+        let (layer, handle) = reload::Layer::new(EnvFilter::new("info"));
+        let _subscriber = tracing_subscriber::registry().with(layer);
+        let runtime =
+            Arc::new(RuntimeConfig::new(Config::default(), None).with_log_handle(handle.clone()));
+
+        let status = runtime
+            .set_log_override("warn,rustdemo=trace", Some(Duration::from_millis(50)))
+            .unwrap();
+        assert_eq!(status.level, "warn,rustdemo=trace");
+        assert_eq!(status.configured, "info");
+        assert!(current_filter(&handle).contains("rustdemo=trace"));
+
+        runtime.update(&Config {
+            log_level: "debug".into(),
+            ..Config::default()
+        });
+        assert!(current_filter(&handle).contains("rustdemo=trace"));
+
+        tokio::time::sleep(Duration::from_millis(200)).await;
+        let status = runtime.log_level();
+        assert_eq!(status.override_, None);
+        assert_eq!(status.level, "debug");
+        assert_eq!(current_filter(&handle), "debug");
+
+        assert!(matches!(
+            runtime.set_log_override("rustdemo=loud", None),
+            Err(LogLevelError::Invalid(_))
+        ));
+        let unmanaged = Arc::new(RuntimeConfig::new(Config::default(), None));
+        assert!(matches!(
+            unmanaged.set_log_override("debug", None),
+            Err(LogLevelError::Unavailable)
+        ));
+    }
+}
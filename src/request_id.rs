@@ -0,0 +1,201 @@
+// 请求 ID：
+// - RequestIdLayer：沿用请求头 x-request-id（格式合法时），否则生成 UUIDv7；
+//   写入请求扩展（处理器可用 Extension<RequestId> 读取）与响应头，并在处理请求期间设为当前任务的请求 ID
+// - current / inject：读取当前请求 ID（AppError 错误体、日志 span 使用），发起下游请求时写入请求头以便串联
+use std::{
+    collections::hash_map::RandomState,
+    fmt,
+    future::Future,
+    hash::{BuildHasher, Hasher},
+    pin::Pin,
+    sync::{
+        atomic::{AtomicU64, Ordering},
+        OnceLock,
+    },
+    task::{Context, Poll},
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+use axum::{
+    extract::Request,
+    http::{HeaderMap, HeaderName, HeaderValue},
+    response::Response,
+};
+use tower::{Layer, Service};
+
+pub const REQUEST_ID_HEADER: HeaderName = HeaderName::from_static("x-request-id");
+
+// 接受的外部请求 ID 最大长度
+const MAX_LEN: usize = 128;
+
+tokio::task_local! {
+    static CURRENT: RequestId;
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RequestId(String);
+
+impl RequestId {
+    // 外部传入的请求 ID：仅接受不超过 128 个字符的字母、数字与 - _ . : 字符，其余视为无效
+    pub fn parse(s: &str) -> Option<Self> {
+        let valid = !s.is_empty()
+            && s.len() <= MAX_LEN
+            && s.bytes()
+                .all(|b| b.is_ascii_alphanumeric() || b"-_.:".contains(&b));
+        valid.then(|| Self(s.to_string()))
+    }
+
+    pub fn generate() -> Self {
+        Self(uuid_v7())
+    }
+
+    pub fn as_str(&self) -> &str {
+        &self.0
+    }
+}
+
+impl fmt::Display for RequestId {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.0)
+    }
+}
+
+// 当前正在处理的请求 ID（不在请求处理期间或在 spawn 出的子任务中时为 None）
+pub fn current() -> Option<RequestId> {
+    CURRENT.try_with(RequestId::clone).ok()
+}
+
+// 下游请求转发当前请求 ID
+pub fn inject(headers: &mut HeaderMap) {
+    if let Some(id) = current() {
+        if let Ok(v) = HeaderValue::from_str(id.as_str()) {
+            headers.insert(REQUEST_ID_HEADER, v);
+        }
+    }
+}
+
+// UUIDv7（RFC 9562）：48 位 Unix 毫秒时间戳 + 版本号 7 + 74 位随机数 + 变体位，按生成时间排序
+pub fn uuid_v7() -> String {
+    let ms = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap_or_default()
+        .as_millis() as u64;
+    let mut b = [0u8; 16];
+    b[..6].copy_from_slice(&ms.to_be_bytes()[2..]);
+    b[6..14].copy_from_slice(&random_u64().to_be_bytes());
+    b[14..].copy_from_slice(&random_u64().to_be_bytes()[..2]);
+    b[6] = (b[6] & 0x0f) | 0x70;
+    b[8] = (b[8] & 0x3f) | 0x80;
+    let hex: String = b.iter().map(|x| format!("{x:02x}")).collect();
+    format!(
+        "{}-{}-{}-{}-{}",
+        &hex[..8],
+        &hex[8..12],
+        &hex[12..16],
+        &hex[16..20],
+        &hex[20..]
+    )
+}
+
+// 随机数：以进程级随机密钥（RandomState）对递增计数器做 SipHash，
+// 结果不可预测，足以用于请求 ID（不用于安全用途）
+pub(crate) fn random_u64() -> u64 {
+    static KEYS: OnceLock<RandomState> = OnceLock::new();
+    static COUNTER: AtomicU64 = AtomicU64::new(0);
+    let mut h = KEYS.get_or_init(RandomState::new).build_hasher();
+    h.write_u64(COUNTER.fetch_add(1, Ordering::Relaxed));
+    h.finish()
+}
+
+#[derive(Clone, Default)]
+pub struct RequestIdLayer;
+
+impl<S> Layer<S> for RequestIdLayer {
+    type Service = RequestIdService<S>;
+
+    fn layer(&self, inner: S) -> Self::Service {
+        RequestIdService { inner }
+    }
+}
+
+#[derive(Clone)]
+pub struct RequestIdService<S> {
+    inner: S,
+}
+
+impl<S, B> Service<Request> for RequestIdService<S>
+where
+    S: Service<Request, Response = Response<B>> + Send + 'static,
+    S::Future: Send + 'static,
+{
+    type Response = S::Response;
+    type Error = S::Error;
+    type Future = Pin<Box<dyn Future<Output = Result<Self::Response, Self::Error>> + Send>>;
+
+    fn poll_ready(&mut self, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        self.inner.poll_ready(cx)
+    }
+
+    fn call(&mut self, mut req: Request) -> Self::Future {
+        let id = req
+            .headers()
+            .get(&REQUEST_ID_HEADER)
+            .and_then(|v| v.to_str().ok())
+            .and_then(RequestId::parse)
+            .unwrap_or_else(RequestId::generate);
+        req.extensions_mut().insert(id.clone());
+        let header = HeaderValue::from_str(id.as_str()).ok();
+        let fut = CURRENT.sync_scope(id.clone(), || self.inner.call(req));
+        Box::pin(CURRENT.scope(id, async move {
+            let mut res = fut.await?;
+            if let Some(v) = header {
+                res.headers_mut().insert(REQUEST_ID_HEADER, v);
+            }
+            Ok(res)
+        }))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn uuid_v7_format() {
+        let a = uuid_v7();
+        let b = uuid_v7();
+        assert_ne!(a, b);
+        assert_eq!(a.len(), 36);
+        let parts: Vec<&str> = a.split('-').collect();
+        assert_eq!(
+            parts.iter().map(|p| p.len()).collect::<Vec<_>>(),
+            [8, 4, 4, 4, 12]
+        );
+        assert!(parts[2].starts_with('7'));
+        assert!(matches!(&parts[3][..1], "8" | "9" | "a" | "b"));
+        // 前 48 位为毫秒时间戳，按生成时间排序
+        assert!(a[..13] <= b[..13]);
+    }
+
+    #[test]
+    fn parse_rejects_invalid_ids() {
+        assert!(RequestId::parse("abc-123_x.y:z").is_some());
+        assert!(RequestId::parse("").is_none());
+        assert!(RequestId::parse("has space").is_none());
+        assert!(RequestId::parse(&"a".repeat(129)).is_none());
+    }
+
+    #[tokio::test]
+    async fn current_is_scoped_to_request() {
+        assert_eq!(current(), None);
+        let id = RequestId::parse("req-1").unwrap();
+        CURRENT
+            .scope(id.clone(), async {
+                assert_eq!(current(), Some(id.clone()));
+                let mut headers = HeaderMap::new();
+                inject(&mut headers);
+                assert_eq!(headers[REQUEST_ID_HEADER], "req-1");
+            })
+            .await;
+    }
+}
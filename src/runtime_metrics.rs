@@ -0,0 +1,194 @@
+// 运行时与进程指标（/metrics 输出）：
+// - RuntimeStats：当前 Tokio 运行时的工作线程数、存活任务数、全局队列深度、工作线程累计忙碌时间与 park 次数；
+//   阻塞线程池大小、阻塞队列深度、累计 spawn 任务数与 poll 次数需以 RUSTFLAGS="--cfg tokio_unstable" 编译，否则为 None
+// - ProcessStats：进程常驻内存、虚拟内存、线程数、打开的文件描述符数（Linux，读取 /proc/self）
+//   与 CPU 时间、文件描述符上限（Unix）；不支持的平台上为 None
+// 每次请求 /metrics 时采集，不在后台轮询
+use serde::Serialize;
+
+#[derive(Debug, Clone, Default, PartialEq, Serialize)]
+pub struct RuntimeStats {
+    pub workers: usize,
+    pub alive_tasks: usize,
+    pub global_queue_depth: usize,
+    pub busy_seconds: f64,
+    pub parks: u64,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub blocking_threads: Option<usize>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub idle_blocking_threads: Option<usize>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub blocking_queue_depth: Option<usize>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub spawned_tasks: Option<u64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub polls: Option<u64>,
+}
+
+impl RuntimeStats {
+    // 不在 Tokio 运行时中调用时返回 None
+    pub fn collect() -> Option<Self> {
+        let handle = tokio::runtime::Handle::try_current().ok()?;
+        let m = handle.metrics();
+        let workers = m.num_workers();
+        #[allow(unused_mut)]
+        let mut stats = Self {
+            workers,
+            alive_tasks: m.num_alive_tasks(),
+            global_queue_depth: m.global_queue_depth(),
+            busy_seconds: (0..workers)
+                .map(|w| m.worker_total_busy_duration(w).as_secs_f64())
+                .sum(),
+            parks: (0..workers).map(|w| m.worker_park_count(w)).sum(),
+            ..Self::default()
+        };
+        #[cfg(tokio_unstable)]
+        {
+            stats.blocking_threads = Some(m.num_blocking_threads());
+            stats.idle_blocking_threads = Some(m.num_idle_blocking_threads());
+            stats.blocking_queue_depth = Some(m.blocking_queue_depth());
+            stats.spawned_tasks = Some(m.spawned_tasks_count());
+            stats.polls = Some((0..workers).map(|w| m.worker_poll_count(w)).sum());
+        }
+        Some(stats)
+    }
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Serialize)]
+pub struct ProcessStats {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub resident_memory_bytes: Option<u64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub virtual_memory_bytes: Option<u64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub cpu_seconds: Option<f64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub threads: Option<u64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub open_fds: Option<u64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub max_fds: Option<u64>,
+}
+
+impl ProcessStats {
+    pub fn collect() -> Self {
+        #[allow(unused_mut)]
+        let mut stats = Self::default();
+        #[cfg(target_os = "linux")]
+        {
+            if let Some(stat) = std::fs::read_to_string("/proc/self/stat")
+                .ok()
+                .and_then(|s| parse_stat(&s))
+            {
+                stats.threads = Some(stat.threads);
+                stats.virtual_memory_bytes = Some(stat.vsize);
+                stats.resident_memory_bytes = Some(stat.rss_pages * page_size());
+            }
+            // 读取目录本身也占用一个描述符，不计入
+            stats.open_fds = std::fs::read_dir("/proc/self/fd")
+                .ok()
+                .map(|dir| dir.count().saturating_sub(1) as u64);
+        }
+        #[cfg(unix)]
+        {
+            stats.cpu_seconds = cpu_seconds();
+            stats.max_fds = max_fds();
+        }
+        stats
+    }
+}
+
+// /proc/self/stat 中用到的字段
+#[cfg_attr(not(target_os = "linux"), allow(dead_code))]
+#[derive(Debug, PartialEq)]
+struct Stat {
+    threads: u64,
+    vsize: u64,
+    rss_pages: u64,
+}
+
+// 进程名（第 2 项）可能含空格与括号，从最后一个 ')' 之后按空白拆分：
+// 其后第 1 项为第 3 项 state，num_threads、vsize、rss 分别为第 20、23、24 项
+#[cfg_attr(not(target_os = "linux"), allow(dead_code))]
+fn parse_stat(s: &str) -> Option<Stat> {
+    let fields: Vec<&str> = s[s.rfind(')')? + 1..].split_whitespace().collect();
+    let field = |n: usize| fields.get(n - 3)?.parse().ok();
+    Some(Stat {
+        threads: field(20)?,
+        vsize: field(23)?,
+        rss_pages: field(24)?,
+    })
+}
+
+#[cfg(target_os = "linux")]
+fn page_size() -> u64 {
+    // SAFETY: sysconf 仅读取系统配置
+    let size = unsafe { libc::sysconf(libc::_SC_PAGESIZE) };
+    u64::try_from(size).unwrap_or(4096)
+}
+
+// 用户态与内核态 CPU 时间之和
+#[cfg(unix)]
+fn cpu_seconds() -> Option<f64> {
+    // SAFETY: getrusage 写入调用方提供的 rusage 结构体
+    let usage = unsafe {
+        let mut usage = std::mem::zeroed::<libc::rusage>();
+        if libc::getrusage(libc::RUSAGE_SELF, &mut usage) != 0 {
+            return None;
+        }
+        usage
+    };
+    let secs = |t: libc::timeval| t.tv_sec as f64 + t.tv_usec as f64 / 1e6;
+    Some(secs(usage.ru_utime) + secs(usage.ru_stime))
+}
+
+// 文件描述符软上限（RLIMIT_NOFILE）；不限制时为 None
+#[cfg(unix)]
+fn max_fds() -> Option<u64> {
+    // SAFETY: getrlimit 写入调用方提供的 rlimit 结构体
+    let limit = unsafe {
+        let mut limit = std::mem::zeroed::<libc::rlimit>();
+        if libc::getrlimit(libc::RLIMIT_NOFILE, &mut limit) != 0 {
+            return None;
+        }
+        limit
+    };
+    (limit.rlim_cur != libc::RLIM_INFINITY).then_some(limit.rlim_cur as u64)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_proc_stat() {
+        let line = "4242 (rust demo) S 1 4242 4242 0 -1 4194560 1234 0 0 0 12 3 0 0 20 0 \
+                    9 0 987654 123456789 2048 18446744073709551615";
+        assert_eq!(
+            parse_stat(line),
+            Some(Stat {
+                threads: 9,
+                vsize: 123456789,
+                rss_pages: 2048,
+            })
+        );
+        assert_eq!(parse_stat("4242 (truncated) S 1"), None);
+    }
+
+    #[tokio::test]
+    async fn collects_runtime_and_process_stats() {
+        let stats = RuntimeStats::collect().unwrap();
+        assert_eq!(stats.workers, 1);
+        assert_eq!(stats.blocking_threads.is_some(), cfg!(tokio_unstable));
+
+        let process = ProcessStats::collect();
+        if cfg!(target_os = "linux") {
+            assert!(process.resident_memory_bytes.unwrap() > 0);
+            assert!(process.open_fds.unwrap() > 0);
+            assert!(process.threads.unwrap() >= 1);
+        }
+        if cfg!(unix) {
+            assert!(process.cpu_seconds.is_some());
+        }
+    }
+}
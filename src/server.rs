@@ -0,0 +1,356 @@
+// 监听与连接服务：
+// - Listener：统一封装 TCP 与 Unix socket 监听（Unix socket 支持设置文件权限）
+// - serve：接受连接并以 HTTP/1.1 服务 Router（连接参数与连接数上限见 ServeOptions），
+//   收到关闭信号后停止接受新连接，在期限内等待在途连接完成
+// - RemoteAddr：连接的对端地址，以 ConnectInfo<RemoteAddr> 写入请求扩展（access log 等使用）
+// - ShutdownSignal：优雅关闭通知，写入请求扩展；SSE 等长连接响应据此在关闭开始时结束，不必等到 drain_timeout
+// axum::serve 仅支持 TcpListener，这里基于 hyper / hyper-util 实现相同流程以支持多种监听方式
+use std::{
+    fmt,
+    future::Future,
+    io,
+    net::{IpAddr, SocketAddr},
+    sync::Arc,
+    time::Duration,
+};
+
+use axum::{extract::connect_info::Connected, Extension, Router};
+use hyper::server::conn::http1;
+use hyper_util::{
+    rt::{TokioIo, TokioTimer},
+    server::graceful::GracefulShutdown,
+    service::TowerToHyperService,
+};
+use tokio::{
+    io::{AsyncRead, AsyncWrite},
+    net::TcpListener,
+    sync::{watch, Semaphore},
+    task::JoinSet,
+};
+use tower::Service;
+use tracing::{debug, info, warn};
+
+use crate::config::{Config, HttpConfig, ListenAddr, MIN_HEADER_BYTES};
+
+// Unix socket 监听：cleanup 表示关闭时是否删除 socket 文件（继承自 systemd 的 socket 由 systemd 管理）
+pub enum Listener {
+    Tcp(TcpListener),
+    #[cfg(unix)]
+    Unix {
+        listener: tokio::net::UnixListener,
+        path: std::path::PathBuf,
+        cleanup: bool,
+    },
+}
+
+// 连接的对端地址；Unix socket 连接没有对端地址（为 None）
+// 处理器可通过 ConnectInfo<RemoteAddr> 提取
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct RemoteAddr(pub Option<SocketAddr>);
+
+impl RemoteAddr {
+    pub fn ip(&self) -> Option<IpAddr> {
+        self.0.map(|a| a.ip())
+    }
+}
+
+impl fmt::Display for RemoteAddr {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.0 {
+            Some(addr) => write!(f, "{addr}"),
+            None => f.write_str("-"),
+        }
+    }
+}
+
+impl Connected<RemoteAddr> for RemoteAddr {
+    fn connect_info(target: RemoteAddr) -> Self {
+        target
+    }
+}
+
+// 连接流：TCP 与 Unix socket 统一为 trait 对象
+trait Stream: AsyncRead + AsyncWrite + Send + Unpin {}
+impl<T: AsyncRead + AsyncWrite + Send + Unpin> Stream for T {}
+
+impl Listener {
+    // 绑定监听地址；Unix socket 会先删除遗留的 socket 文件，绑定后按 mode 设置权限
+    pub async fn bind(addr: &ListenAddr, unix_mode: u32) -> io::Result<Self> {
+        match addr {
+            ListenAddr::Tcp(addr) => TcpListener::bind(addr).await.map(Listener::Tcp),
+            #[cfg(unix)]
+            ListenAddr::Unix(path) => {
+                use std::os::unix::fs::{FileTypeExt, PermissionsExt};
+                if let Ok(meta) = std::fs::symlink_metadata(path) {
+                    if !meta.file_type().is_socket() {
+                        return Err(io::Error::new(
+                            io::ErrorKind::AlreadyExists,
+                            format!("{} 已存在且不是 socket 文件", path.display()),
+                        ));
+                    }
+                    std::fs::remove_file(path)?;
+                }
+                let listener = tokio::net::UnixListener::bind(path)?;
+                std::fs::set_permissions(path, std::fs::Permissions::from_mode(unix_mode))?;
+                Ok(Listener::Unix {
+                    listener,
+                    path: path.clone(),
+                    cleanup: true,
+                })
+            }
+            #[cfg(not(unix))]
+            ListenAddr::Unix(_) => {
+                let _ = unix_mode;
+                Err(io::Error::new(
+                    io::ErrorKind::Unsupported,
+                    "当前平台不支持 Unix socket",
+                ))
+            }
+        }
+    }
+
+    // 由已处于监听状态的 std 监听构造（用于继承的文件描述符）
+    pub fn from_std_tcp(listener: std::net::TcpListener) -> io::Result<Self> {
+        listener.set_nonblocking(true)?;
+        TcpListener::from_std(listener).map(Listener::Tcp)
+    }
+
+    #[cfg(unix)]
+    pub fn from_std_unix(
+        listener: std::os::unix::net::UnixListener,
+        path: std::path::PathBuf,
+    ) -> io::Result<Self> {
+        listener.set_nonblocking(true)?;
+        Ok(Listener::Unix {
+            listener: tokio::net::UnixListener::from_std(listener)?,
+            path,
+            cleanup: false,
+        })
+    }
+
+    // 实际监听地址（TCP 端口为 0 时可据此得到系统分配的端口）
+    pub fn local_addr(&self) -> io::Result<ListenAddr> {
+        match self {
+            Listener::Tcp(l) => l.local_addr().map(ListenAddr::Tcp),
+            #[cfg(unix)]
+            Listener::Unix { path, .. } => Ok(ListenAddr::Unix(path.clone())),
+        }
+    }
+
+    async fn accept(&self) -> io::Result<(Box<dyn Stream>, RemoteAddr)> {
+        match self {
+            Listener::Tcp(l) => {
+                let (stream, peer) = l.accept().await?;
+                let _ = stream.set_nodelay(true);
+                Ok((Box::new(stream), RemoteAddr(Some(peer))))
+            }
+            #[cfg(unix)]
+            Listener::Unix { listener, .. } => {
+                let (stream, _) = listener.accept().await?;
+                Ok((Box::new(stream), RemoteAddr(None)))
+            }
+        }
+    }
+}
+
+#[cfg(unix)]
+impl std::os::fd::AsRawFd for Listener {
+    fn as_raw_fd(&self) -> std::os::fd::RawFd {
+        match self {
+            Listener::Tcp(l) => l.as_raw_fd(),
+            Listener::Unix { listener, .. } => listener.as_raw_fd(),
+        }
+    }
+}
+
+// 关闭监听时清理 Unix socket 文件（已交接给新进程时保留）
+impl Drop for Listener {
+    fn drop(&mut self) {
+        #[cfg(unix)]
+        if let Listener::Unix {
+            path,
+            cleanup: true,
+            ..
+        } = self
+        {
+            if !crate::handoff::handed_off() {
+                let _ = std::fs::remove_file(path);
+            }
+        }
+    }
+}
+
+// 服务选项：
+// - drain_timeout：关闭时等待在途连接完成的最长时间，超时后强制断开（None 表示一直等待）
+// - http：HTTP/1.1 连接参数（keep-alive、请求头读取期限与大小限制）
+// - connection_limit：连接数上限；多个监听使用同一份 ServeOptions 的克隆时共享计数（None 表示不限制）
+#[derive(Debug, Clone)]
+pub struct ServeOptions {
+    pub drain_timeout: Option<Duration>,
+    pub http: HttpConfig,
+    pub connection_limit: Option<Arc<Semaphore>>,
+}
+
+impl ServeOptions {
+    pub fn from_config(cfg: &Config) -> Self {
+        Self {
+            drain_timeout: cfg.drain_timeout(),
+            http: cfg.http.clone(),
+            connection_limit: match cfg.http.max_connections {
+                0 => None,
+                n => Some(Arc::new(Semaphore::new(n))),
+            },
+        }
+    }
+
+    fn http1(&self) -> http1::Builder {
+        let mut builder = http1::Builder::new();
+        builder
+            .timer(TokioTimer::new())
+            .keep_alive(self.http.keep_alive)
+            .header_read_timeout(match self.http.header_read_timeout_secs {
+                0 => None,
+                secs => Some(Duration::from_secs(secs)),
+            })
+            .max_headers(self.http.max_headers.max(1))
+            .max_buf_size(self.http.max_header_bytes.max(MIN_HEADER_BYTES));
+        builder
+    }
+}
+
+impl Default for ServeOptions {
+    fn default() -> Self {
+        Self::from_config(&Config::default())
+    }
+}
+
+// 关闭统计：收到关闭信号时仍在处理的连接中，正常完成（drained）与被强制断开（cut_off）的数量
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct DrainStats {
+    pub drained: usize,
+    pub cut_off: usize,
+}
+
+// 优雅关闭通知（请求扩展）：serve 的关闭开始时完成；不经 serve 的请求中不存在
+#[derive(Debug, Clone)]
+pub struct ShutdownSignal(watch::Receiver<bool>);
+
+impl ShutdownSignal {
+    // 关闭开始时完成（已开始时立即完成）
+    pub fn wait(&self) -> impl Future<Output = ()> + Send + 'static {
+        let mut rx = self.0.clone();
+        async move {
+            let _ = rx.wait_for(|closing| *closing).await;
+        }
+    }
+}
+
+// 在单个监听上服务 Router：
+// 1) shutdown 完成后停止接受新连接，通知在途连接处理完当前请求后关闭（空闲 keep-alive 连接立即关闭），
+//    同时触发请求中的 ShutdownSignal，/metrics/stream 等 SSE 推送随之结束
+// 2) 在 drain_timeout 内等待在途连接结束；超时仍未结束的连接直接中止（其中的 /parallel 子任务随之取消）
+pub async fn serve<F>(
+    listener: Listener,
+    app: Router,
+    opts: ServeOptions,
+    shutdown: F,
+) -> io::Result<DrainStats>
+where
+    F: Future<Output = ()> + Send,
+{
+    let graceful = GracefulShutdown::new();
+    let builder = opts.http1();
+    let local = listener.local_addr()?;
+    let (closing, closing_rx) = watch::channel(false);
+    let app = app.layer(Extension(ShutdownSignal(closing_rx)));
+    let mut make_service = app.into_make_service_with_connect_info::<RemoteAddr>();
+    let mut connections = JoinSet::new();
+    tokio::pin!(shutdown);
+    loop {
+        // 达到连接数上限时先等待空位再 accept，期间新连接留在内核队列中
+        let next = async {
+            let permit = match &opts.connection_limit {
+                Some(limit) => {
+                    if limit.available_permits() == 0 {
+                        debug!("connection limit reached on {}, pausing accept", local);
+                    }
+                    limit.clone().acquire_owned().await.ok()
+                }
+                None => None,
+            };
+            (permit, listener.accept().await)
+        };
+        let (permit, (stream, remote)) = tokio::select! {
+            (permit, res) = next => match res {
+                Ok(conn) => (permit, conn),
+                // 接受失败（如文件描述符耗尽）时稍作等待，避免空转
+                Err(e) => {
+                    debug!("accept error on {}: {}", local, e);
+                    tokio::time::sleep(Duration::from_millis(50)).await;
+                    continue;
+                }
+            },
+            // 回收已结束的连接任务，避免 JoinSet 无限增长
+            Some(_) = connections.join_next(), if !connections.is_empty() => continue,
+            _ = &mut shutdown => break,
+        };
+        // Unix socket 连接以监听地址标识
+        let peer = match remote.0 {
+            Some(addr) => addr.to_string(),
+            None => local.to_string(),
+        };
+        debug!("accepted connection from {} on {}", peer, local);
+        let Ok(service) = make_service.call(remote).await;
+        let service = TowerToHyperService::new(service);
+        let conn = builder.serve_connection(TokioIo::new(stream), service);
+        let conn = graceful.watch(conn);
+        connections.spawn(async move {
+            if let Err(e) = conn.await {
+                debug!("connection from {} closed with error: {}", peer, e);
+            }
+            drop(permit);
+        });
+    }
+    drop(listener);
+    closing.send_replace(true);
+    while connections.try_join_next().is_some() {}
+    let in_flight = connections.len();
+    info!(
+        "stopped accepting on {}, draining {} connection(s)",
+        local, in_flight
+    );
+
+    let drained = match opts.drain_timeout {
+        Some(deadline) => tokio::time::timeout(deadline, graceful.shutdown())
+            .await
+            .is_ok(),
+        None => {
+            graceful.shutdown().await;
+            true
+        }
+    };
+    while connections.try_join_next().is_some() {}
+    let stats = if drained {
+        DrainStats {
+            drained: in_flight,
+            cut_off: 0,
+        }
+    } else {
+        let cut_off = connections.len();
+        connections.abort_all();
+        while connections.join_next().await.is_some() {}
+        DrainStats {
+            drained: in_flight.saturating_sub(cut_off),
+            cut_off,
+        }
+    };
+    if stats.cut_off > 0 {
+        warn!(
+            "{}: drain deadline exceeded, {} connection(s) drained, {} cut off",
+            local, stats.drained, stats.cut_off
+        );
+    } else {
+        info!("{}: {} connection(s) drained", local, stats.drained);
+    }
+    Ok(stats)
+}
@@ -0,0 +1,439 @@
+// 慢请求检测（[slow_requests]）：
+// - SlowRequestLayer：耗时超过路由阈值（可热加载）的请求计入 rustdemo_slow_requests_total，
+//   并输出一条 WARN 事件（target 为本模块），附带该请求的 span 树与各段耗时
+// - Diagnostics：单个请求的诊断记录，处理期间设为当前任务的诊断（current 读取）；
+//   spawn 出的子任务不继承 task-local，需显式携带并调用 record_task（如 /parallel 的每个任务）
+// - SpanTimingLayer：tracing 层，把请求期间创建的 span（含其子 span）的创建、首次进入、忙碌与关闭时间记入 Diagnostics；
+//   通过 SpanTimingLayer::layer() 挂载（只接收本 crate 的 span 的独立过滤，不受日志级别影响；
+//   hyper、tower、tokio 等依赖的 span 不记录，其调用点也不会因此被启用），
+//   日志过滤规则需作为输出层的过滤挂载，否则低于日志级别的 span 不会出现在诊断中
+// 任务的 queued（spawn 到首次被调度）偏大说明调度器繁忙，ran 明显超过 expected 说明任务本身慢或计时器被延迟
+use std::{
+    collections::BTreeMap,
+    fmt::{self, Write as _},
+    future::Future,
+    pin::Pin,
+    sync::{Arc, Mutex},
+    task::{Context, Poll},
+    time::{Duration, Instant},
+};
+
+use axum::{
+    extract::{MatchedPath, Request},
+    response::Response,
+};
+use tower::{Layer, Service};
+use tracing::{
+    field::{Field, Visit},
+    span::{Attributes, Id},
+    warn, Metadata, Subscriber,
+};
+use tracing_subscriber::{
+    filter::{FilterFn, Filtered},
+    layer::{Context as LayerContext, Layer as _},
+    registry::LookupSpan,
+};
+
+use crate::{
+    metrics::{RequestMetrics, FALLBACK_ROUTE},
+    reload::RuntimeConfig,
+    request_id::RequestId,
+};
+
+// 每个请求最多记录的 span 与任务数，超出部分只计数
+const MAX_SPANS: usize = 256;
+const MAX_TASKS: usize = 256;
+
+tokio::task_local! {
+    static CURRENT: Arc<Diagnostics>;
+}
+
+// 当前请求的诊断记录（未启用慢请求检测、不在请求处理期间或在 spawn 出的子任务中时为 None）
+pub fn current() -> Option<Arc<Diagnostics>> {
+    CURRENT.try_with(Arc::clone).ok()
+}
+
+pub struct Diagnostics {
+    start: Instant,
+    recorded: Mutex<Recorded>,
+}
+
+#[derive(Default)]
+struct Recorded {
+    spans: Vec<SpanTiming>,
+    tasks: Vec<TaskTiming>,
+    dropped: usize,
+}
+
+struct SpanTiming {
+    name: &'static str,
+    fields: String,
+    parent: Option<usize>,
+    created: Instant,
+    first_enter: Option<Instant>,
+    busy: Duration,
+    closed: Option<Instant>,
+}
+
+// 子任务耗时：spawned 为 spawn 时刻，started 为首次被调度执行的时刻，expected 为预期运行时长（已知时）
+#[derive(Debug, Clone)]
+pub struct TaskTiming {
+    pub name: String,
+    pub spawned: Instant,
+    pub started: Instant,
+    pub finished: Instant,
+    pub expected: Option<Duration>,
+}
+
+impl Default for Diagnostics {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Diagnostics {
+    pub fn new() -> Self {
+        Self {
+            start: Instant::now(),
+            recorded: Mutex::default(),
+        }
+    }
+
+    pub fn record_task(&self, task: TaskTiming) {
+        let mut recorded = self.recorded.lock().unwrap();
+        if recorded.tasks.len() < MAX_TASKS {
+            recorded.tasks.push(task);
+        } else {
+            recorded.dropped += 1;
+        }
+    }
+
+    fn open_span(
+        &self,
+        name: &'static str,
+        fields: String,
+        parent: Option<usize>,
+    ) -> Option<usize> {
+        let mut recorded = self.recorded.lock().unwrap();
+        if recorded.spans.len() == MAX_SPANS {
+            recorded.dropped += 1;
+            return None;
+        }
+        recorded.spans.push(SpanTiming {
+            name,
+            fields,
+            parent,
+            created: Instant::now(),
+            first_enter: None,
+            busy: Duration::ZERO,
+            closed: None,
+        });
+        Some(recorded.spans.len() - 1)
+    }
+
+    fn update_span(&self, index: usize, f: impl FnOnce(&mut SpanTiming)) {
+        if let Some(span) = self.recorded.lock().unwrap().spans.get_mut(index) {
+            f(span);
+        }
+    }
+
+    // 多行文本：span 树（按父子缩进）与子任务耗时，时间均相对请求开始；未关闭的 span 计到 now 为止
+    pub fn report(&self, now: Instant) -> String {
+        let recorded = self.recorded.lock().unwrap();
+        let ms = |d: Duration| d.as_secs_f64() * 1000.0;
+        let at = |t: Instant| ms(t.saturating_duration_since(self.start));
+        let mut children: BTreeMap<Option<usize>, Vec<usize>> = BTreeMap::new();
+        for (i, span) in recorded.spans.iter().enumerate() {
+            children.entry(span.parent).or_default().push(i);
+        }
+        let mut out = String::new();
+        let mut stack: Vec<(usize, usize)> = children.get(&None).map_or(Vec::new(), |roots| {
+            roots.iter().rev().map(|&i| (i, 0)).collect()
+        });
+        while let Some((i, depth)) = stack.pop() {
+            let span = &recorded.spans[i];
+            let end = span.closed.unwrap_or(now);
+            let _ = write!(
+                out,
+                "\n{:indent$}{}{{{}}} +{:.1}ms total {:.1}ms busy {:.1}ms",
+                "",
+                span.name,
+                span.fields,
+                at(span.created),
+                ms(end.saturating_duration_since(span.created)),
+                ms(span.busy),
+                indent = depth * 2,
+            );
+            if let Some(first) = span.first_enter {
+                let _ = write!(
+                    out,
+                    " first entered after {:.1}ms",
+                    ms(first.saturating_duration_since(span.created))
+                );
+            }
+            if span.closed.is_none() {
+                out.push_str(" (open)");
+            }
+            if let Some(kids) = children.get(&Some(i)) {
+                stack.extend(kids.iter().rev().map(|&k| (k, depth + 1)));
+            }
+        }
+        for task in &recorded.tasks {
+            let ran = task.finished.saturating_duration_since(task.started);
+            let _ = write!(
+                out,
+                "\n{} spawned +{:.1}ms queued {:.1}ms ran {:.1}ms",
+                task.name,
+                at(task.spawned),
+                ms(task.started.saturating_duration_since(task.spawned)),
+                ms(ran),
+            );
+            if let Some(expected) = task.expected {
+                let _ = write!(out, " expected {:.1}ms", ms(expected));
+            }
+        }
+        if recorded.dropped > 0 {
+            let _ = write!(out, "\n({} more not recorded)", recorded.dropped);
+        }
+        out
+    }
+}
+
+// 保存在 span 扩展中：所属请求的诊断记录与 span 序号
+struct SpanSlot {
+    diagnostics: Arc<Diagnostics>,
+    index: usize,
+    entered: Option<Instant>,
+}
+
+// 以 key=value 形式拼接 span 字段
+struct FieldsVisitor<'a>(&'a mut String);
+
+impl Visit for FieldsVisitor<'_> {
+    fn record_str(&mut self, field: &Field, value: &str) {
+        self.record_debug(field, &format_args!("{value}"));
+    }
+
+    fn record_debug(&mut self, field: &Field, value: &dyn fmt::Debug) {
+        if !self.0.is_empty() {
+            self.0.push(' ');
+        }
+        let _ = write!(self.0, "{}={value:?}", field.name());
+    }
+}
+
+pub struct SpanTimingLayer;
+
+// 只放行本 crate 的 span 的过滤
+type AppSpans = FilterFn<fn(&Metadata<'_>) -> bool>;
+
+fn is_app_span(meta: &Metadata<'_>) -> bool {
+    let target = meta.target();
+    meta.is_span()
+        && target
+            .strip_prefix(env!("CARGO_CRATE_NAME"))
+            .is_some_and(|rest| rest.is_empty() || rest.starts_with("::"))
+}
+
+impl SpanTimingLayer {
+    // 接收本 crate 各级别 span、不接收事件的 SpanTimingLayer（不会放行更多的事件）
+    pub fn layer<S>() -> Filtered<Self, AppSpans, S>
+    where
+        S: Subscriber + for<'a> LookupSpan<'a>,
+    {
+        Self.with_filter(FilterFn::new(is_app_span as fn(&Metadata<'_>) -> bool))
+    }
+}
+
+impl<S> tracing_subscriber::Layer<S> for SpanTimingLayer
+where
+    S: Subscriber + for<'a> LookupSpan<'a>,
+{
+    // 父 span 属于某个请求时沿用其诊断记录，否则使用当前任务的诊断记录（请求的根 span）
+    fn on_new_span(&self, attrs: &Attributes<'_>, id: &Id, ctx: LayerContext<'_, S>) {
+        let Some(span) = ctx.span(id) else { return };
+        let inherited = span.parent().and_then(|parent| {
+            let extensions = parent.extensions();
+            let slot = extensions.get::<SpanSlot>()?;
+            Some((slot.diagnostics.clone(), Some(slot.index)))
+        });
+        let Some((diagnostics, parent)) = inherited.or_else(|| Some((current()?, None))) else {
+            return;
+        };
+        let mut fields = String::new();
+        attrs.record(&mut FieldsVisitor(&mut fields));
+        let Some(index) = diagnostics.open_span(span.name(), fields, parent) else {
+            return;
+        };
+        span.extensions_mut().insert(SpanSlot {
+            diagnostics,
+            index,
+            entered: None,
+        });
+    }
+
+    fn on_enter(&self, id: &Id, ctx: LayerContext<'_, S>) {
+        let Some(span) = ctx.span(id) else { return };
+        let mut extensions = span.extensions_mut();
+        if let Some(slot) = extensions.get_mut::<SpanSlot>() {
+            let now = Instant::now();
+            slot.entered = Some(now);
+            slot.diagnostics.update_span(slot.index, |s| {
+                s.first_enter.get_or_insert(now);
+            });
+        }
+    }
+
+    fn on_exit(&self, id: &Id, ctx: LayerContext<'_, S>) {
+        let Some(span) = ctx.span(id) else { return };
+        let mut extensions = span.extensions_mut();
+        if let Some(slot) = extensions.get_mut::<SpanSlot>() {
+            if let Some(entered) = slot.entered.take() {
+                slot.diagnostics
+                    .update_span(slot.index, |s| s.busy += entered.elapsed());
+            }
+        }
+    }
+
+    fn on_close(&self, id: Id, ctx: LayerContext<'_, S>) {
+        let Some(span) = ctx.span(&id) else { return };
+        let extensions = span.extensions();
+        if let Some(slot) = extensions.get::<SpanSlot>() {
+            let now = Instant::now();
+            slot.diagnostics
+                .update_span(slot.index, |s| s.closed = Some(now));
+        }
+    }
+}
+
+// 慢请求中间件；需通过 Router::layer 挂载（逐路由包裹，才能读取到 MatchedPath），并位于 TraceLayer 之外以记录请求 span
+#[derive(Clone)]
+pub struct SlowRequestLayer {
+    config: Arc<RuntimeConfig>,
+    metrics: Arc<RequestMetrics>,
+}
+
+impl SlowRequestLayer {
+    pub fn new(config: Arc<RuntimeConfig>, metrics: Arc<RequestMetrics>) -> Self {
+        Self { config, metrics }
+    }
+}
+
+impl<S> Layer<S> for SlowRequestLayer {
+    type Service = SlowRequestService<S>;
+
+    fn layer(&self, inner: S) -> Self::Service {
+        SlowRequestService {
+            inner,
+            config: self.config.clone(),
+            metrics: self.metrics.clone(),
+        }
+    }
+}
+
+#[derive(Clone)]
+pub struct SlowRequestService<S> {
+    inner: S,
+    config: Arc<RuntimeConfig>,
+    metrics: Arc<RequestMetrics>,
+}
+
+impl<S, B> Service<Request> for SlowRequestService<S>
+where
+    S: Service<Request, Response = Response<B>> + Send + 'static,
+    S::Future: Send + 'static,
+{
+    type Response = S::Response;
+    type Error = S::Error;
+    type Future = Pin<Box<dyn Future<Output = Result<Self::Response, Self::Error>> + Send>>;
+
+    fn poll_ready(&mut self, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        self.inner.poll_ready(cx)
+    }
+
+    fn call(&mut self, req: Request) -> Self::Future {
+        let route = req
+            .extensions()
+            .get::<MatchedPath>()
+            .map_or(FALLBACK_ROUTE, MatchedPath::as_str)
+            .to_string();
+        let Some(threshold) = self.config.read(|c| c.slow_requests.threshold(&route)) else {
+            return Box::pin(self.inner.call(req));
+        };
+        let method = req.method().clone();
+        let path = req.uri().path().to_string();
+        let request_id = req.extensions().get::<RequestId>().cloned();
+        let diagnostics = Arc::new(Diagnostics::new());
+        let metrics = self.metrics.clone();
+        let fut = CURRENT.sync_scope(diagnostics.clone(), || self.inner.call(req));
+        Box::pin(CURRENT.scope(diagnostics.clone(), async move {
+            let res = fut.await?;
+            let now = Instant::now();
+            let elapsed = now.duration_since(diagnostics.start);
+            if elapsed > threshold {
+                metrics.route(&route).observe_slow();
+                warn!(
+                    route = %route,
+                    method = %method,
+                    path = %path,
+                    status = res.status().as_u16(),
+                    latency_ms = elapsed.as_secs_f64() * 1000.0,
+                    threshold_ms = threshold.as_millis() as u64,
+                    request_id = %request_id.as_ref().map_or("", RequestId::as_str),
+                    diagnostics = %diagnostics.report(now),
+                    "slow request"
+                );
+            }
+            Ok(res)
+        }))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use tracing_subscriber::layer::SubscriberExt;
+
+    use super::*;
+
+    // 请求期间本 crate 的 span（任意级别）按父子关系记录；不在请求期间创建的 span 与依赖的 span 不记录
+    #[test]
+    fn records_span_tree_and_tasks() {
+        let subscriber = tracing_subscriber::registry().with(SpanTimingLayer::layer());
+        let diagnostics = Arc::new(Diagnostics::new());
+        tracing::subscriber::with_default(subscriber, || {
+            let _outside = tracing::info_span!("outside").entered();
+            let rt = tokio::runtime::Builder::new_current_thread()
+                .build()
+                .unwrap();
+            rt.block_on(CURRENT.scope(diagnostics.clone(), async {
+                let root = tracing::info_span!("request", method = "GET", path = "/x");
+                let _root = root.enter();
+                tracing::debug_span!("query", table = "users").in_scope(|| {
+                    std::thread::sleep(Duration::from_millis(5));
+                });
+                tracing::trace_span!(target: "hyper::proto", "parse").in_scope(|| {});
+            }));
+        });
+        let spawned = Instant::now();
+        diagnostics.record_task(TaskTiming {
+            name: "task{index=0}".into(),
+            spawned,
+            started: spawned + Duration::from_millis(2),
+            finished: spawned + Duration::from_millis(12),
+            expected: Some(Duration::from_millis(10)),
+        });
+
+        let report = diagnostics.report(Instant::now());
+        let lines: Vec<&str> = report.lines().skip(1).collect();
+        assert_eq!(lines.len(), 3, "{report}");
+        assert!(lines[0].starts_with("request{method=GET path=/x} +"));
+        assert!(lines[1].starts_with("  query{table=users} +"));
+        let busy = lines[1].split("busy ").nth(1).unwrap();
+        let busy: f64 = busy[..busy.find("ms").unwrap()].parse().unwrap();
+        assert!(busy >= 5.0, "{report}");
+        assert!(!lines[1].ends_with("(open)"));
+        assert!(lines[2].contains("queued 2.0ms ran 10.0ms expected 10.0ms"));
+        assert!(!report.contains("outside"));
+        assert!(!report.contains("parse"));
+    }
+}
@@ -0,0 +1,61 @@
+// 应用状态（用于指标与观测）
+// - start：服务启动时间（计算运行时长）
+// - config：运行时配置（支持热加载）
+// - metrics：按路由模板统计的请求指标（由 MetricsLayer 自动记录，/metrics 输出）
+// - recent：最近完成的请求（由 RecentRequestsLayer 记录，/debug/requests 输出）
+// - stream：指标增量推送（/metrics/stream）
+use std::{
+    sync::Arc,
+    time::{Duration, Instant},
+};
+
+use crate::{
+    metrics::RequestMetrics, metrics_stream::MetricsStream, recent::RecentRequests,
+    reload::RuntimeConfig,
+};
+
+pub struct AppState {
+    pub(crate) start: Instant,
+    pub(crate) config: Arc<RuntimeConfig>,
+    pub(crate) metrics: Arc<RequestMetrics>,
+    pub(crate) recent: Arc<RecentRequests>,
+    pub(crate) stream: Arc<MetricsStream>,
+}
+
+impl AppState {
+    pub fn new(config: Arc<RuntimeConfig>) -> Self {
+        let (metrics, recent) = config.read(|c| {
+            (
+                RequestMetrics::new(&c.metrics),
+                RecentRequests::new(c.metrics.recent_requests),
+            )
+        });
+        let metrics = Arc::new(metrics);
+        Self {
+            start: Instant::now(),
+            stream: Arc::new(MetricsStream::new(metrics.clone(), config.clone())),
+            config,
+            metrics,
+            recent: Arc::new(recent),
+        }
+    }
+
+    // 运行时配置（自定义路由可据此读取当前生效配置）
+    pub fn config(&self) -> &RuntimeConfig {
+        &self.config
+    }
+
+    // 请求指标（导出到外部系统时读取）
+    pub fn metrics(&self) -> Arc<RequestMetrics> {
+        self.metrics.clone()
+    }
+
+    // 最近请求记录
+    pub fn recent(&self) -> Arc<RecentRequests> {
+        self.recent.clone()
+    }
+
+    pub fn uptime(&self) -> Duration {
+        self.start.elapsed()
+    }
+}
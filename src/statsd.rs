@@ -0,0 +1,433 @@
+// StatsD / DogStatsD 推送（[statsd] 配置了 address 时启用）：
+// - 每 flush_interval_ms 向 agent 发送一批指标（UDP 或 Unix datagram socket，多行合并为不超过 max_packet_bytes 的数据报）：
+//   http.requests（计数，上次发送以来的增量，按路由 / 方法 / 状态码）、http.slow_requests（计数，按路由）、
+//   http.requests_in_flight（gauge，按路由）、http.request.duration（耗时，毫秒，每个请求一个样本）
+// - dogstatsd 开启时路由、方法、状态码与固定 tags 以 DogStatsD 标签发送（|#route:/sum,method:GET,status:200）；
+//   否则编入指标名（rustdemo.http.requests.sum.GET.200），固定 tags 不发送
+// - StatsdLayer：记录每个请求的耗时；每个路由每个周期最多保留 MAX_TIMINGS 个样本（水塘抽样），超出时附带 @采样率
+// - 数据报不重发；发送失败只在开始失败与恢复时各记录一次日志
+use std::{
+    collections::BTreeMap,
+    fmt::Display,
+    future::Future,
+    io, mem,
+    pin::Pin,
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc, Mutex,
+    },
+    task::{Context, Poll},
+    time::{Duration, Instant},
+};
+
+use axum::{
+    extract::{MatchedPath, Request},
+    response::Response,
+};
+use tokio::{net::UdpSocket, task::JoinHandle};
+use tower::{Layer, Service};
+use tracing::{info, warn};
+
+use crate::{
+    config::{StatsdAddr, StatsdConfig},
+    metrics::{RequestMetrics, FALLBACK_ROUTE},
+    persist::RouteTotals,
+    request_id::random_u64,
+};
+
+// 每个路由每个周期保留的耗时样本数上限
+const MAX_TIMINGS: usize = 1000;
+
+enum Socket {
+    Udp(UdpSocket),
+    #[cfg(unix)]
+    Unix(tokio::net::UnixDatagram, std::path::PathBuf),
+}
+
+impl Socket {
+    async fn connect(addr: &StatsdAddr) -> io::Result<Self> {
+        match addr {
+            StatsdAddr::Udp(addr) => {
+                let target = tokio::net::lookup_host(addr.as_str())
+                    .await?
+                    .next()
+                    .ok_or_else(|| io::Error::new(io::ErrorKind::NotFound, "地址解析无结果"))?;
+                let local = if target.is_ipv4() {
+                    "0.0.0.0:0"
+                } else {
+                    "[::]:0"
+                };
+                let socket = UdpSocket::bind(local).await?;
+                socket.connect(target).await?;
+                Ok(Socket::Udp(socket))
+            }
+            #[cfg(unix)]
+            StatsdAddr::Unix(path) => Ok(Socket::Unix(
+                tokio::net::UnixDatagram::unbound()?,
+                path.clone(),
+            )),
+            #[cfg(not(unix))]
+            StatsdAddr::Unix(_) => Err(io::Error::new(
+                io::ErrorKind::Unsupported,
+                "unix socket 仅在 Unix 上支持",
+            )),
+        }
+    }
+
+    async fn send(&self, packet: &[u8]) -> io::Result<()> {
+        match self {
+            Socket::Udp(socket) => socket.send(packet).await.map(drop),
+            #[cfg(unix)]
+            Socket::Unix(socket, path) => socket.send_to(packet, path).await.map(drop),
+        }
+    }
+}
+
+// 一个周期内某路由的耗时样本（毫秒）；seen 为该周期内的请求数
+#[derive(Default)]
+struct Timings {
+    seen: u64,
+    samples: Vec<f64>,
+}
+
+impl Timings {
+    fn record(&mut self, ms: f64) {
+        self.seen += 1;
+        if self.samples.len() < MAX_TIMINGS {
+            self.samples.push(ms);
+        } else {
+            let j = (random_u64() % self.seen) as usize;
+            if j < MAX_TIMINGS {
+                self.samples[j] = ms;
+            }
+        }
+    }
+
+    // 抽样时的采样率（样本数 / 请求数）；未抽样时为 None
+    fn rate(&self) -> Option<f64> {
+        (self.seen > self.samples.len() as u64)
+            .then(|| self.samples.len() as f64 / self.seen as f64)
+    }
+}
+
+pub struct Statsd {
+    addr: StatsdAddr,
+    cfg: StatsdConfig,
+    socket: Socket,
+    // 上次发送时各路由的计数（用于计算增量）
+    last: Mutex<BTreeMap<String, RouteTotals>>,
+    timings: Mutex<BTreeMap<String, Timings>>,
+    failing: AtomicBool,
+}
+
+impl Statsd {
+    // 未配置 address 时返回 None；地址无法解析或 socket 创建失败时返回错误
+    pub async fn connect(cfg: &StatsdConfig) -> io::Result<Option<Arc<Self>>> {
+        let Some(addr) = &cfg.address else {
+            return Ok(None);
+        };
+        Ok(Some(Arc::new(Self {
+            addr: addr.clone(),
+            cfg: cfg.clone(),
+            socket: Socket::connect(addr).await?,
+            last: Mutex::default(),
+            timings: Mutex::default(),
+            failing: AtomicBool::new(false),
+        })))
+    }
+
+    pub fn address(&self) -> &StatsdAddr {
+        &self.addr
+    }
+
+    pub fn record(&self, route: &str, elapsed: Duration) {
+        let mut timings = self.timings.lock().unwrap();
+        match timings.get_mut(route) {
+            Some(t) => t.record(elapsed.as_secs_f64() * 1000.0),
+            None => {
+                let mut t = Timings::default();
+                t.record(elapsed.as_secs_f64() * 1000.0);
+                timings.insert(route.to_string(), t);
+            }
+        }
+    }
+
+    // 定期发送，直到任务被取消
+    pub fn spawn(self: &Arc<Self>, metrics: Arc<RequestMetrics>) -> JoinHandle<()> {
+        let statsd = self.clone();
+        tokio::spawn(async move {
+            let mut ticker =
+                tokio::time::interval(Duration::from_millis(statsd.cfg.flush_interval_ms));
+            ticker.tick().await;
+            loop {
+                ticker.tick().await;
+                statsd.flush_logged(&metrics).await;
+            }
+        })
+    }
+
+    // 优雅关闭时发送最后一批
+    pub async fn shutdown(&self, metrics: &RequestMetrics) {
+        self.flush_logged(metrics).await;
+    }
+
+    async fn flush_logged(&self, metrics: &RequestMetrics) {
+        match self.flush(metrics).await {
+            Ok(()) => {
+                if self.failing.swap(false, Ordering::Relaxed) {
+                    info!("statsd export to {} recovered", self.addr);
+                }
+            }
+            Err(e) => {
+                if !self.failing.swap(true, Ordering::Relaxed) {
+                    warn!("failed to send metrics to statsd at {}: {}", self.addr, e);
+                }
+            }
+        }
+    }
+
+    // 发送上次以来的增量、当前在途数与耗时样本；首个发送失败的数据报之后的数据报被丢弃
+    pub async fn flush(&self, metrics: &RequestMetrics) -> io::Result<()> {
+        let lines = self.lines(metrics);
+        for packet in pack(&lines, self.cfg.max_packet_bytes) {
+            self.socket.send(packet.as_bytes()).await?;
+        }
+        Ok(())
+    }
+
+    fn lines(&self, metrics: &RequestMetrics) -> Vec<String> {
+        let mut lines = Vec::new();
+        let mut last = self.last.lock().unwrap();
+        let empty = RouteTotals::default();
+        for (route, m) in metrics.snapshot() {
+            let totals = m.totals();
+            let delta = totals.since(last.get(&route).unwrap_or(&empty));
+            for (method, statuses) in &delta.requests {
+                for (status, n) in statuses {
+                    let status = status.to_string();
+                    let tags = [("route", &*route), ("method", method), ("status", &status)];
+                    lines.push(self.line("http.requests", n, "c", None, &tags));
+                }
+            }
+            if delta.slow_requests > 0 {
+                let tags = [("route", &*route)];
+                lines.push(self.line("http.slow_requests", delta.slow_requests, "c", None, &tags));
+            }
+            let tags = [("route", &*route)];
+            lines.push(self.line("http.requests_in_flight", m.in_flight(), "g", None, &tags));
+            last.insert(route, totals);
+        }
+        drop(last);
+        let timings = mem::take(&mut *self.timings.lock().unwrap());
+        for (route, t) in &timings {
+            let tags = [("route", &**route)];
+            for ms in &t.samples {
+                let value = format!("{ms:.3}");
+                lines.push(self.line("http.request.duration", value, "ms", t.rate(), &tags));
+            }
+        }
+        lines
+    }
+
+    // 一行指标：<前缀.名称>:<值>|<类型>[|@采样率][|#标签]
+    fn line(
+        &self,
+        name: &str,
+        value: impl Display,
+        kind: &str,
+        rate: Option<f64>,
+        tags: &[(&str, &str)],
+    ) -> String {
+        let mut line = String::new();
+        if !self.cfg.prefix.is_empty() {
+            line.push_str(&self.cfg.prefix);
+            line.push('.');
+        }
+        line.push_str(name);
+        if !self.cfg.dogstatsd {
+            for (key, v) in tags {
+                line.push('.');
+                line.push_str(&name_segment(key, v));
+            }
+        }
+        line.push_str(&format!(":{value}|{kind}"));
+        if let Some(rate) = rate {
+            line.push_str(&format!("|@{rate:.4}"));
+        }
+        if self.cfg.dogstatsd {
+            let tags: Vec<String> = tags
+                .iter()
+                .map(|(k, v)| format!("{k}:{}", tag_value(v)))
+                .chain(self.cfg.tags.iter().cloned())
+                .collect();
+            if !tags.is_empty() {
+                line.push_str("|#");
+                line.push_str(&tags.join(","));
+            }
+        }
+        line
+    }
+}
+
+// 编入指标名的标签值："/" -> root，"/debug/requests" -> debug_requests，其余非字母数字字符替换为 "_"
+fn name_segment(key: &str, value: &str) -> String {
+    if key == "route" && value == "/" {
+        return "root".into();
+    }
+    let value = if key == "route" {
+        value.trim_start_matches('/')
+    } else {
+        value
+    };
+    value
+        .chars()
+        .map(|c| {
+            if c.is_ascii_alphanumeric() || c == '-' {
+                c
+            } else {
+                '_'
+            }
+        })
+        .collect()
+}
+
+// DogStatsD 标签值中不能出现 "," "|" "#" 与换行
+fn tag_value(value: &str) -> String {
+    value.replace([',', '|', '#', '\n'], "_")
+}
+
+// 按换行合并为不超过 max 字节的数据报（单行超过 max 时单独发送）
+fn pack(lines: &[String], max: usize) -> Vec<String> {
+    let mut packets: Vec<String> = Vec::new();
+    for line in lines {
+        match packets.last_mut() {
+            Some(p) if p.len() + 1 + line.len() <= max => {
+                p.push('\n');
+                p.push_str(line);
+            }
+            _ => packets.push(line.clone()),
+        }
+    }
+    packets
+}
+
+// 耗时记录中间件（与 MetricsLayer 一样逐路由挂载，以读取 MatchedPath）
+#[derive(Clone)]
+pub struct StatsdLayer {
+    statsd: Arc<Statsd>,
+}
+
+impl StatsdLayer {
+    pub fn new(statsd: Arc<Statsd>) -> Self {
+        Self { statsd }
+    }
+}
+
+impl<S> Layer<S> for StatsdLayer {
+    type Service = StatsdService<S>;
+
+    fn layer(&self, inner: S) -> Self::Service {
+        StatsdService {
+            inner,
+            statsd: self.statsd.clone(),
+        }
+    }
+}
+
+#[derive(Clone)]
+pub struct StatsdService<S> {
+    inner: S,
+    statsd: Arc<Statsd>,
+}
+
+impl<S, B> Service<Request> for StatsdService<S>
+where
+    S: Service<Request, Response = Response<B>> + Send + 'static,
+    S::Future: Send + 'static,
+{
+    type Response = S::Response;
+    type Error = S::Error;
+    type Future = Pin<Box<dyn Future<Output = Result<Self::Response, Self::Error>> + Send>>;
+
+    fn poll_ready(&mut self, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        self.inner.poll_ready(cx)
+    }
+
+    fn call(&mut self, req: Request) -> Self::Future {
+        let route = req
+            .extensions()
+            .get::<MatchedPath>()
+            .map_or(FALLBACK_ROUTE, MatchedPath::as_str)
+            .to_string();
+        let start = Instant::now();
+        let statsd = self.statsd.clone();
+        let fut = self.inner.call(req);
+        Box::pin(async move {
+            let res = fut.await?;
+            statsd.record(&route, start.elapsed());
+            Ok(res)
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::Config;
+
+    async fn statsd(dogstatsd: bool) -> Arc<Statsd> {
+        let mut cfg = Config::default().statsd;
+        cfg.address = Some(StatsdAddr::Udp("127.0.0.1:9".into()));
+        cfg.dogstatsd = dogstatsd;
+        cfg.tags = vec!["env:test".into()];
+        Statsd::connect(&cfg).await.unwrap().unwrap()
+    }
+
+    #[tokio::test]
+    async fn formats_lines() {
+        let tags = [
+            ("route", "/users/:id"),
+            ("method", "GET"),
+            ("status", "200"),
+        ];
+        let plain = statsd(false).await;
+        assert_eq!(
+            plain.line("http.requests", 3, "c", None, &tags),
+            "rustdemo.http.requests.users__id.GET.200:3|c"
+        );
+        assert_eq!(
+            plain.line("http.requests_in_flight", 0, "g", None, &[("route", "/")]),
+            "rustdemo.http.requests_in_flight.root:0|g"
+        );
+        let dog = statsd(true).await;
+        assert_eq!(
+            dog.line("http.request.duration", "1.500", "ms", Some(0.25), &tags),
+            "rustdemo.http.request.duration:1.500|ms|@0.2500|#route:/users/:id,method:GET,status:200,env:test"
+        );
+    }
+
+    // 超过 MAX_TIMINGS 时保留固定数量的样本，并按采样率发送
+    #[test]
+    fn samples_timings() {
+        let mut t = Timings::default();
+        for i in 0..MAX_TIMINGS as u64 * 4 {
+            t.record(i as f64);
+        }
+        assert_eq!(t.samples.len(), MAX_TIMINGS);
+        assert_eq!(t.rate(), Some(0.25));
+        let mut small = Timings::default();
+        small.record(1.0);
+        assert_eq!(small.rate(), None);
+    }
+
+    #[test]
+    fn packs_lines() {
+        let lines: Vec<String> = ["a:1|c", "b:2|c", "c:3|c", "a-very-long-line:1|c"]
+            .map(String::from)
+            .into();
+        assert_eq!(
+            pack(&lines, 12),
+            ["a:1|c\nb:2|c", "c:3|c", "a-very-long-line:1|c"]
+        );
+    }
+}
@@ -0,0 +1,288 @@
+// 极简 TOML 解析器（仅覆盖配置文件所需的子集）：
+// - 支持：注释、[table] / [a.b] 表头、key = value、点号键
+// - 取值：字符串（"..." 带转义 / '...' 字面量）、整数、浮点、布尔、数组（可跨行）
+// - 不支持：内联表、多行字符串、日期时间（遇到时返回带行号的错误）
+// 解析结果为扁平映射："server.port" -> Integer(3000)
+use std::collections::BTreeMap;
+
+use thiserror::Error;
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Value {
+    String(String),
+    Integer(i64),
+    Float(f64),
+    Boolean(bool),
+    Array(Vec<Value>),
+}
+
+#[derive(Error, Debug, PartialEq)]
+#[error("第 {line} 行: {msg}")]
+pub struct ParseError {
+    pub line: usize,
+    pub msg: String,
+}
+
+impl Value {
+    // 转为字符串形式，数组以逗号连接（与环境变量/命令行的写法保持一致）
+    pub fn to_plain_string(&self) -> String {
+        match self {
+            Value::String(s) => s.clone(),
+            Value::Integer(i) => i.to_string(),
+            Value::Float(f) => f.to_string(),
+            Value::Boolean(b) => b.to_string(),
+            Value::Array(items) => items
+                .iter()
+                .map(Value::to_plain_string)
+                .collect::<Vec<_>>()
+                .join(","),
+        }
+    }
+}
+
+pub fn parse(input: &str) -> Result<BTreeMap<String, Value>, ParseError> {
+    let mut out = BTreeMap::new();
+    let mut table = String::new();
+    let mut lines = input.lines().enumerate();
+    while let Some((idx, raw)) = lines.next() {
+        let line_no = idx + 1;
+        let err = |msg: &str| ParseError {
+            line: line_no,
+            msg: msg.to_string(),
+        };
+        let line = strip_comment(raw).trim().to_string();
+        if line.is_empty() {
+            continue;
+        }
+        if let Some(rest) = line.strip_prefix('[') {
+            let name = rest
+                .strip_suffix(']')
+                .ok_or_else(|| err("表头缺少 ]"))?
+                .trim();
+            if name.is_empty() || name.starts_with('[') {
+                return Err(err("不支持的表头"));
+            }
+            table = parse_key(name).ok_or_else(|| err("非法的表名"))?;
+            continue;
+        }
+        let (key, value) = line.split_once('=').ok_or_else(|| err("缺少 ="))?;
+        let key = parse_key(key.trim()).ok_or_else(|| err("非法的键名"))?;
+        // 数组可跨行：括号未闭合时继续拼接后续行
+        let mut value = value.trim().to_string();
+        while value.starts_with('[') && !brackets_closed(&value) {
+            let (_, next) = lines.next().ok_or_else(|| err("数组未闭合"))?;
+            value.push(' ');
+            value.push_str(strip_comment(next).trim());
+        }
+        let full = if table.is_empty() {
+            key
+        } else {
+            format!("{table}.{key}")
+        };
+        let mut cursor = Cursor {
+            chars: value.chars().collect(),
+            pos: 0,
+        };
+        let parsed = cursor.value().map_err(|m| err(&m))?;
+        cursor.skip_ws();
+        if cursor.pos != cursor.chars.len() {
+            return Err(err("取值后存在多余内容"));
+        }
+        if out.insert(full.clone(), parsed).is_some() {
+            return Err(err(&format!("重复的键: {full}")));
+        }
+    }
+    Ok(out)
+}
+
+// 去掉行尾注释（忽略字符串内部的 #）
+fn strip_comment(line: &str) -> &str {
+    let mut quote: Option<char> = None;
+    let mut escaped = false;
+    for (i, c) in line.char_indices() {
+        match quote {
+            Some('"') if escaped => escaped = false,
+            Some('"') if c == '\\' => escaped = true,
+            Some(q) if c == q => quote = None,
+            Some(_) => {}
+            None if c == '"' || c == '\'' => quote = Some(c),
+            None if c == '#' => return &line[..i],
+            None => {}
+        }
+    }
+    line
+}
+
+fn brackets_closed(s: &str) -> bool {
+    let mut depth = 0i32;
+    let mut quote: Option<char> = None;
+    for c in s.chars() {
+        match quote {
+            Some(q) if c == q => quote = None,
+            Some(_) => {}
+            None if c == '"' || c == '\'' => quote = Some(c),
+            None if c == '[' => depth += 1,
+            None if c == ']' => depth -= 1,
+            None => {}
+        }
+    }
+    depth <= 0
+}
+
+// 键名：裸键（字母/数字/-/_）以点号连接，各段去除空白
+fn parse_key(s: &str) -> Option<String> {
+    let parts: Vec<&str> = s.split('.').map(str::trim).collect();
+    let ok = parts.iter().all(|p| {
+        !p.is_empty()
+            && p.chars()
+                .all(|c| c.is_ascii_alphanumeric() || c == '_' || c == '-')
+    });
+    ok.then(|| parts.join("."))
+}
+
+struct Cursor {
+    chars: Vec<char>,
+    pos: usize,
+}
+
+impl Cursor {
+    fn peek(&self) -> Option<char> {
+        self.chars.get(self.pos).copied()
+    }
+
+    fn skip_ws(&mut self) {
+        while matches!(self.peek(), Some(c) if c.is_whitespace()) {
+            self.pos += 1;
+        }
+    }
+
+    fn value(&mut self) -> Result<Value, String> {
+        self.skip_ws();
+        match self.peek() {
+            Some('"') => self.basic_string().map(Value::String),
+            Some('\'') => self.literal_string().map(Value::String),
+            Some('[') => self.array(),
+            Some('{') => Err("不支持内联表".into()),
+            Some(_) => self.scalar(),
+            None => Err("缺少取值".into()),
+        }
+    }
+
+    fn basic_string(&mut self) -> Result<String, String> {
+        self.pos += 1;
+        let mut s = String::new();
+        while let Some(c) = self.peek() {
+            self.pos += 1;
+            match c {
+                '"' => return Ok(s),
+                '\\' => {
+                    let e = self.peek().ok_or("字符串转义不完整")?;
+                    self.pos += 1;
+                    s.push(match e {
+                        'n' => '\n',
+                        't' => '\t',
+                        'r' => '\r',
+                        '"' => '"',
+                        '\\' => '\\',
+                        other => return Err(format!("不支持的转义: \\{other}")),
+                    });
+                }
+                c => s.push(c),
+            }
+        }
+        Err("字符串未闭合".into())
+    }
+
+    fn literal_string(&mut self) -> Result<String, String> {
+        self.pos += 1;
+        let start = self.pos;
+        while let Some(c) = self.peek() {
+            self.pos += 1;
+            if c == '\'' {
+                return Ok(self.chars[start..self.pos - 1].iter().collect());
+            }
+        }
+        Err("字符串未闭合".into())
+    }
+
+    fn array(&mut self) -> Result<Value, String> {
+        self.pos += 1;
+        let mut items = Vec::new();
+        loop {
+            self.skip_ws();
+            if self.peek() == Some(']') {
+                self.pos += 1;
+                return Ok(Value::Array(items));
+            }
+            items.push(self.value()?);
+            self.skip_ws();
+            match self.peek() {
+                Some(',') => self.pos += 1,
+                Some(']') => {}
+                _ => return Err("数组元素之间缺少 ,".into()),
+            }
+        }
+    }
+
+    fn scalar(&mut self) -> Result<Value, String> {
+        let start = self.pos;
+        while matches!(self.peek(), Some(c) if !c.is_whitespace() && c != ',' && c != ']') {
+            self.pos += 1;
+        }
+        let raw: String = self.chars[start..self.pos].iter().collect();
+        match raw.as_str() {
+            "true" => return Ok(Value::Boolean(true)),
+            "false" => return Ok(Value::Boolean(false)),
+            _ => {}
+        }
+        let digits = raw.replace('_', "");
+        if let Ok(i) = digits.parse::<i64>() {
+            return Ok(Value::Integer(i));
+        }
+        if let Ok(f) = digits.parse::<f64>() {
+            return Ok(Value::Float(f));
+        }
+        Err(format!("无法识别的取值: {raw}"))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse_tables_and_values() {
+        let doc = r#"
+            # 顶层键
+            log_level = "debug" # 行尾注释
+            [server]
+            port = 8_080
+            bind = '0.0.0.0'
+            [middleware]
+            trace = false
+            cors_origins = [
+                "http://a.example", # 注释
+                "http://b.example",
+            ]
+            ratio = 0.5
+        "#;
+        let map = parse(doc).unwrap();
+        assert_eq!(map["log_level"], Value::String("debug".into()));
+        assert_eq!(map["server.port"], Value::Integer(8080));
+        assert_eq!(map["server.bind"], Value::String("0.0.0.0".into()));
+        assert_eq!(map["middleware.trace"], Value::Boolean(false));
+        assert_eq!(map["middleware.ratio"], Value::Float(0.5));
+        assert_eq!(
+            map["middleware.cors_origins"].to_plain_string(),
+            "http://a.example,http://b.example"
+        );
+    }
+
+    #[test]
+    fn parse_errors_report_line() {
+        assert_eq!(parse("a = 1\nb").unwrap_err().line, 2);
+        assert_eq!(parse("a = 1\na = 2").unwrap_err().line, 2);
+        assert!(parse("x = { y = 1 }").is_err());
+        assert!(parse("x = \"open").is_err());
+    }
+}
@@ -0,0 +1,653 @@
+// 集成测试：通过库接口构建 Router，使用 tower::ServiceExt::oneshot 直接驱动（无需监听端口）
+use std::{
+    sync::Arc,
+    time::{Duration, UNIX_EPOCH},
+};
+
+use axum::{
+    body::Body,
+    extract::State,
+    http::{header, HeaderValue, Request, StatusCode},
+    middleware::map_response,
+    response::Response,
+    routing::get,
+    Router,
+};
+use http_body_util::BodyExt;
+use rustdemo::{
+    build_app,
+    logging::JsonLayer,
+    persist::{RouteTotals, Totals},
+    reload::RuntimeConfig,
+    slow::SpanTimingLayer,
+    AppBuilder, AppState, Config,
+};
+use serde_json::Value;
+use tower::ServiceExt;
+use tracing_subscriber::{layer::SubscriberExt, reload, EnvFilter, Layer as _};
+
+async fn send(app: &Router, req: Request<Body>) -> (StatusCode, Value) {
+    let res = app.clone().oneshot(req).await.unwrap();
+    let status = res.status();
+    let bytes = res.into_body().collect().await.unwrap().to_bytes();
+    let body = serde_json::from_slice(&bytes).unwrap_or(Value::Null);
+    (status, body)
+}
+
+fn get_req(uri: &str) -> Request<Body> {
+    Request::get(uri).body(Body::empty()).unwrap()
+}
+
+#[tokio::test]
+async fn sum_and_errors() {
+    let app = build_app(Config::default());
+    let (status, body) = send(&app, get_req("/sum?nums=1,2,3")).await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(body["total"], 6);
+
+    let (status, body) = send(&app, get_req("/sum?nums=1,x,3")).await;
+    assert_eq!(status, StatusCode::BAD_REQUEST);
+    assert!(body["error"].as_str().unwrap().contains("不是有效整数"));
+}
+
+// 查询串与 JSON 请求体无法解析时同样返回统一的 JSON 错误体（含 request_id），状态码沿用 axum 的判断
+#[tokio::test]
+async fn extractor_rejections_use_error_body() {
+    let app = build_app(Config::default());
+    let req = Request::get("/sum?a=x")
+        .header("x-request-id", "req-query")
+        .body(Body::empty())
+        .unwrap();
+    let (status, body) = send(&app, req).await;
+    assert_eq!(status, StatusCode::BAD_REQUEST);
+    assert!(body["error"].as_str().unwrap().contains("nums"), "{body}");
+    assert_eq!(body["request_id"], "req-query");
+
+    let post = |content_type: &str, body: &'static str| {
+        Request::post("/echo")
+            .header(header::CONTENT_TYPE, content_type)
+            .header("x-request-id", "req-json")
+            .body(Body::from(body))
+            .unwrap()
+    };
+    for (req, expected) in [
+        (post("application/json", "{"), StatusCode::BAD_REQUEST),
+        (
+            post("application/json", r#"{"msg":1}"#),
+            StatusCode::UNPROCESSABLE_ENTITY,
+        ),
+        (
+            post("text/plain", r#"{"message":"hi"}"#),
+            StatusCode::UNSUPPORTED_MEDIA_TYPE,
+        ),
+    ] {
+        let (status, body) = send(&app, req).await;
+        assert_eq!(status, expected);
+        assert!(body["error"].is_string(), "{body}");
+        assert_eq!(body["request_id"], "req-json");
+    }
+}
+
+#[tokio::test]
+async fn echo_rejects_empty_message() {
+    let app = build_app(Config::default());
+    let post = |msg: &str| {
+        Request::post("/echo")
+            .header(header::CONTENT_TYPE, "application/json")
+            .body(Body::from(
+                serde_json::json!({ "message": msg }).to_string(),
+            ))
+            .unwrap()
+    };
+    let (status, body) = send(&app, post("hello")).await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(body["message"], "hello");
+    let (status, _) = send(&app, post("  ")).await;
+    assert_eq!(status, StatusCode::BAD_REQUEST);
+}
+
+#[tokio::test]
+async fn parallel_respects_configured_limit() {
+    let mut cfg = Config::default();
+    cfg.parallel.max_tasks = 2;
+    let app = build_app(cfg);
+    let (status, body) = send(&app, get_req("/parallel?n=10")).await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(body.as_array().unwrap().len(), 2);
+}
+
+// 自定义路由可访问 AppState，自定义中间件包裹全部路由
+#[tokio::test]
+async fn builder_accepts_custom_routes_and_layers() {
+    async fn version(State(app): State<Arc<AppState>>) -> String {
+        format!("port={}", app.config().read(|c| c.server.port))
+    }
+    async fn server_header(mut res: Response) -> Response {
+        let value = HeaderValue::from_static("rustdemo-test");
+        res.headers_mut().insert(header::SERVER, value);
+        res
+    }
+    let app = AppBuilder::new(Config::default())
+        .route("/version", get(version))
+        .layer(map_response(server_header))
+        .static_files(false)
+        .build();
+
+    let res = app.clone().oneshot(get_req("/version")).await.unwrap();
+    assert_eq!(res.headers()[header::SERVER], "rustdemo-test");
+    let bytes = res.into_body().collect().await.unwrap().to_bytes();
+    assert_eq!(&bytes[..], b"port=3000");
+
+    let (status, body) = send(&app, get_req("/health")).await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(body["status"], "healthy");
+    let (status, _) = send(&app, get_req("/missing.txt")).await;
+    assert_eq!(status, StatusCode::NOT_FOUND);
+
+    // 自定义路由与静态文件兜底（未匹配路由）同样被统计
+    let (_, body) = send(&app, get_req("/metrics")).await;
+    assert_eq!(body["routes"]["/health"]["requests"], 1);
+    assert_eq!(body["routes"]["/version"]["requests"], 1);
+    assert_eq!(body["routes"]["fallback"]["status"]["4xx"], 1);
+}
+
+// 拆分监听：管理端点只在管理 Router 上，业务端点只在公共 Router 上
+#[tokio::test]
+async fn split_public_and_admin_routes() {
+    let (public, admin) = AppBuilder::new(Config::default())
+        .static_files(false)
+        .build_split();
+    assert_eq!(
+        send(&public, get_req("/sum?nums=2")).await.0,
+        StatusCode::OK
+    );
+    assert_eq!(
+        send(&public, get_req("/metrics")).await.0,
+        StatusCode::NOT_FOUND
+    );
+    assert_eq!(
+        send(&admin, get_req("/sum?nums=2")).await.0,
+        StatusCode::NOT_FOUND
+    );
+    let (status, body) = send(&admin, get_req("/metrics")).await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(body["routes"]["/sum"]["requests"], 1);
+}
+
+// /metrics 内容协商：默认 JSON，Accept: text/plain 返回带标签的 Prometheus 文本格式
+#[tokio::test]
+async fn metrics_prometheus_exposition() {
+    let app = build_app(Config::default());
+    send(&app, get_req("/sum?nums=1,2")).await;
+    send(&app, get_req("/sum?nums=x")).await;
+
+    let (status, body) = send(&app, get_req("/metrics")).await;
+    assert_eq!(status, StatusCode::OK);
+    let sum = &body["routes"]["/sum"];
+    assert_eq!(sum["requests"], 2);
+    assert_eq!(sum["status"]["2xx"], 1);
+    assert_eq!(sum["status"]["4xx"], 1);
+    assert_eq!(sum["in_flight"], 0);
+    assert_eq!(sum["latency"]["count"], 2);
+    assert_eq!(sum["latency"]["window_seconds"], 60);
+    assert_eq!(sum["latency"]["window_count"], 2);
+    assert!(
+        sum["latency"]["p99_ms"].as_f64().unwrap() <= sum["latency"]["max_ms"].as_f64().unwrap()
+    );
+    // 正在处理的 /metrics 请求本身计入在途数
+    assert_eq!(body["routes"]["/metrics"]["in_flight"], 1);
+    assert_eq!(body["runtime"]["workers"], 1);
+    assert!(body["runtime"]["alive_tasks"].is_u64());
+
+    let req = Request::get("/metrics")
+        .header(header::ACCEPT, "text/plain")
+        .body(Body::empty())
+        .unwrap();
+    let res = app.clone().oneshot(req).await.unwrap();
+    assert_eq!(
+        res.headers()[header::CONTENT_TYPE],
+        "text/plain; version=0.0.4; charset=utf-8"
+    );
+    let bytes = res.into_body().collect().await.unwrap().to_bytes();
+    let text = String::from_utf8(bytes.to_vec()).unwrap();
+    assert!(text.contains("# TYPE rustdemo_http_requests_total counter\n"));
+    assert!(text.contains(
+        "rustdemo_http_requests_total{route=\"/sum\",method=\"GET\",status=\"200\"} 1\n"
+    ));
+    assert!(text.contains(
+        "rustdemo_http_requests_total{route=\"/sum\",method=\"GET\",status=\"400\"} 1\n"
+    ));
+    assert!(text.contains("# TYPE rustdemo_http_request_duration_seconds histogram\n"));
+    assert!(text
+        .contains("rustdemo_http_request_duration_seconds_bucket{route=\"/sum\",le=\"+Inf\"} 2\n"));
+    assert!(text.contains("rustdemo_http_request_duration_seconds_count{route=\"/sum\"} 2\n"));
+    assert!(text.contains("# TYPE rustdemo_tokio_alive_tasks gauge\n"));
+    assert!(text.contains("rustdemo_tokio_workers 1\n"));
+    if cfg!(unix) {
+        assert!(text.contains("# TYPE process_cpu_seconds_total counter\n"));
+        assert!(text.contains("process_cpu_seconds_total "));
+    }
+
+    let req = Request::get("/metrics")
+        .header(
+            header::ACCEPT,
+            "application/openmetrics-text; version=1.0.0",
+        )
+        .body(Body::empty())
+        .unwrap();
+    let res = app.clone().oneshot(req).await.unwrap();
+    let bytes = res.into_body().collect().await.unwrap().to_bytes();
+    assert!(bytes.ends_with(b"# EOF\n"));
+}
+
+// 请求 ID：沿用合法的 x-request-id，否则生成 UUIDv7；回显在响应头并写入错误体
+#[tokio::test]
+async fn request_id_propagates_to_errors() {
+    let app = build_app(Config::default());
+    let req = Request::get("/sum?nums=1,x")
+        .header("x-request-id", "client-42")
+        .body(Body::empty())
+        .unwrap();
+    let res = app.clone().oneshot(req).await.unwrap();
+    assert_eq!(res.status(), StatusCode::BAD_REQUEST);
+    assert_eq!(res.headers()["x-request-id"], "client-42");
+    let bytes = res.into_body().collect().await.unwrap().to_bytes();
+    let body: Value = serde_json::from_slice(&bytes).unwrap();
+    assert_eq!(body["request_id"], "client-42");
+
+    let req = Request::get("/sum?nums=x")
+        .header("x-request-id", "bad id")
+        .body(Body::empty())
+        .unwrap();
+    let res = app.clone().oneshot(req).await.unwrap();
+    let id = res.headers()["x-request-id"].to_str().unwrap().to_string();
+    assert_eq!(id.len(), 36);
+    let bytes = res.into_body().collect().await.unwrap().to_bytes();
+    let body: Value = serde_json::from_slice(&bytes).unwrap();
+    assert_eq!(body["request_id"], id.as_str());
+
+    let res = app.clone().oneshot(get_req("/health")).await.unwrap();
+    assert!(res.headers().contains_key("x-request-id"));
+}
+
+// /admin/log-level：PUT 临时覆盖（含 TTL），GET 查看，DELETE 恢复为配置的 log_level
+#[tokio::test]
+async fn admin_log_level_override() {
+    let (layer, handle) = reload::Layer::new(EnvFilter::new("info"));
+    let _subscriber = tracing_subscriber::registry().with(layer);
+    let mut cfg = Config::default();
+    cfg.admin.allow_log_level_changes = true;
+    let runtime = RuntimeConfig::new(cfg, None).with_log_handle(handle.clone());
+    let app = AppBuilder::with_runtime_config(Arc::new(runtime))
+        .static_files(false)
+        .build();
+    let put = |body: Value| {
+        Request::put("/admin/log-level")
+            .header(header::CONTENT_TYPE, "application/json")
+            .body(Body::from(body.to_string()))
+            .unwrap()
+    };
+
+    let (status, body) = send(
+        &app,
+        put(serde_json::json!({ "level": "info,rustdemo::server=trace", "ttl_secs": 600 })),
+    )
+    .await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(body["level"], "info,rustdemo::server=trace");
+    assert_eq!(body["configured"], "info");
+    assert!(body["override"]["expires_in_secs"].as_u64().unwrap() > 590);
+    let filter = handle.with_current(|f| f.to_string()).unwrap();
+    assert!(filter.contains("rustdemo::server=trace"));
+
+    let (_, body) = send(&app, get_req("/admin/log-level")).await;
+    assert_eq!(body["override"]["level"], "info,rustdemo::server=trace");
+
+    let (status, body) = send(&app, put(serde_json::json!({ "level": "rustdemo=nope" }))).await;
+    assert_eq!(status, StatusCode::BAD_REQUEST);
+    assert!(body["error"].as_str().unwrap().contains("日志过滤规则无效"));
+    let (status, _) = send(
+        &app,
+        put(serde_json::json!({ "level": "debug", "ttl_secs": 0 })),
+    )
+    .await;
+    assert_eq!(status, StatusCode::BAD_REQUEST);
+
+    let req = Request::delete("/admin/log-level")
+        .body(Body::empty())
+        .unwrap();
+    let (status, body) = send(&app, req).await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(body["level"], "info");
+    assert_eq!(body["override"], Value::Null);
+    assert_eq!(handle.with_current(|f| f.to_string()).unwrap(), "info");
+}
+
+// 未配置管理监听且未开启 admin.allow_log_level_changes 时，公共监听上只能查看日志级别；管理监听上可修改
+#[tokio::test]
+async fn log_level_changes_require_admin_listener_or_opt_in() {
+    let put = || {
+        Request::put("/admin/log-level")
+            .header(header::CONTENT_TYPE, "application/json")
+            .body(Body::from(r#"{"level":"debug"}"#))
+            .unwrap()
+    };
+    let app = AppBuilder::new(Config::default())
+        .static_files(false)
+        .build();
+    let (status, _) = send(&app, get_req("/admin/log-level")).await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(send(&app, put()).await.0, StatusCode::METHOD_NOT_ALLOWED);
+    let req = Request::delete("/admin/log-level")
+        .body(Body::empty())
+        .unwrap();
+    assert_eq!(send(&app, req).await.0, StatusCode::METHOD_NOT_ALLOWED);
+
+    let (layer, handle) = reload::Layer::new(EnvFilter::new("info"));
+    let _subscriber = tracing_subscriber::registry().with(layer);
+    let runtime = RuntimeConfig::new(Config::default(), None).with_log_handle(handle);
+    let (_, admin) = AppBuilder::with_runtime_config(Arc::new(runtime))
+        .static_files(false)
+        .build_split();
+    assert_eq!(send(&admin, put()).await.0, StatusCode::OK);
+}
+
+// /debug/requests：最近请求（最新在前）含 AppError 错误信息，可按状态与路由筛选；stream 以 SSE 推送新请求
+#[tokio::test]
+async fn debug_recent_requests() {
+    let app = AppBuilder::new(Config::default())
+        .static_files(false)
+        .build();
+    let req = Request::get("/sum?nums=1,x")
+        .header("x-request-id", "req-bad")
+        .body(Body::empty())
+        .unwrap();
+    send(&app, req).await;
+    send(&app, get_req("/sum?nums=1,2")).await;
+    send(&app, get_req("/missing")).await;
+
+    let (status, body) = send(&app, get_req("/debug/requests")).await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(body["capacity"], 100);
+    let list = body["requests"].as_array().unwrap();
+    assert_eq!(list.len(), 3);
+    assert_eq!(list[0]["route"], "fallback");
+    assert_eq!(list[0]["status"], 404);
+
+    let (_, body) = send(&app, get_req("/debug/requests?status=4xx&route=/sum")).await;
+    let list = body["requests"].as_array().unwrap();
+    assert_eq!(list.len(), 1);
+    let bad = &list[0];
+    assert_eq!(bad["method"], "GET");
+    assert_eq!(bad["path"], "/sum");
+    assert_eq!(bad["query"], "nums=1,x");
+    assert_eq!(bad["status"], 400);
+    assert_eq!(bad["request_id"], "req-bad");
+    assert!(bad["error"].as_str().unwrap().contains("不是有效整数"));
+    assert!(bad["latency_ms"].as_f64().is_some());
+
+    let (_, body) = send(&app, get_req("/debug/requests?limit=1")).await;
+    assert_eq!(body["requests"][0]["path"], "/debug/requests");
+    let (status, _) = send(&app, get_req("/debug/requests?status=abc")).await;
+    assert_eq!(status, StatusCode::BAD_REQUEST);
+
+    let res = app
+        .clone()
+        .oneshot(get_req("/debug/requests/stream?route=/sum"))
+        .await
+        .unwrap();
+    assert_eq!(res.headers()[header::CONTENT_TYPE], "text/event-stream");
+    let mut body = res.into_body();
+    send(&app, get_req("/health")).await;
+    send(&app, get_req("/sum?nums=5")).await;
+    let frame = body.frame().await.unwrap().unwrap().into_data().unwrap();
+    let text = String::from_utf8(frame.to_vec()).unwrap();
+    assert!(text.starts_with("event: request\ndata: {"), "{text}");
+    let data: Value =
+        serde_json::from_str(text.lines().nth(1).unwrap().trim_start_matches("data: ")).unwrap();
+    assert_eq!(data["query"], "nums=5");
+}
+
+#[derive(Clone, Default)]
+struct Buf(Arc<std::sync::Mutex<Vec<u8>>>);
+
+impl std::io::Write for Buf {
+    fn write(&mut self, data: &[u8]) -> std::io::Result<usize> {
+        self.0.lock().unwrap().extend_from_slice(data);
+        Ok(data.len())
+    }
+
+    fn flush(&mut self) -> std::io::Result<()> {
+        Ok(())
+    }
+}
+
+// 默认 info 级别下，JSON 日志的响应事件带有请求 span 的 method、path、request_id 与 status、latency_ms
+#[tokio::test]
+async fn json_logs_requests_at_default_level() {
+    let buf = Buf::default();
+    let out = buf.clone();
+    let subscriber = tracing_subscriber::registry()
+        .with(JsonLayer::new(move || out.clone()).with_filter(EnvFilter::new("info")));
+    let _guard = tracing::subscriber::set_default(subscriber);
+
+    let app = AppBuilder::new(Config::default())
+        .static_files(false)
+        .build();
+    let req = Request::get("/sum?nums=1,2")
+        .header("x-request-id", "req-log")
+        .body(Body::empty())
+        .unwrap();
+    send(&app, req).await;
+
+    let text = String::from_utf8(buf.0.lock().unwrap().clone()).unwrap();
+    let e: Value = text
+        .lines()
+        .map(|l| serde_json::from_str(l).unwrap())
+        .find(|e: &Value| e["message"] == "finished processing request")
+        .unwrap_or_else(|| panic!("no response event in {text}"));
+    assert_eq!(e["level"], "INFO");
+    assert_eq!(e["span"], "request");
+    assert_eq!(e["method"], "GET");
+    assert_eq!(e["path"], "/sum");
+    assert_eq!(e["request_id"], "req-log");
+    assert_eq!(e["status"], 200);
+    assert!(e["latency_ms"].as_f64().is_some());
+}
+
+// 慢请求：超过路由阈值时输出 WARN（含请求 span 与 /parallel 各任务耗时）并计入 slow_requests；
+// 与 main 相同的订阅器结构，日志级别为 warn 时请求 span 仍出现在诊断中
+#[tokio::test]
+async fn slow_requests_are_reported() {
+    let buf = Buf::default();
+    let out = buf.clone();
+    let subscriber = tracing_subscriber::registry()
+        .with(JsonLayer::new(move || out.clone()).with_filter(EnvFilter::new("warn")))
+        .with(SpanTimingLayer::layer());
+    let _guard = tracing::subscriber::set_default(subscriber);
+
+    let mut cfg = Config::default();
+    cfg.slow_requests.routes.insert("/parallel".into(), 1);
+    cfg.slow_requests.routes.insert("/sum".into(), 0);
+    let app = AppBuilder::new(cfg).static_files(false).build();
+    send(&app, get_req("/parallel?n=2")).await;
+    send(&app, get_req("/sum?nums=1")).await;
+
+    let text = String::from_utf8(buf.0.lock().unwrap().clone()).unwrap();
+    let events: Vec<Value> = text
+        .lines()
+        .map(|l| serde_json::from_str(l).unwrap())
+        .filter(|e: &Value| e["message"] == "slow request")
+        .collect();
+    assert_eq!(events.len(), 1, "{text}");
+    let e = &events[0];
+    assert_eq!(e["level"], "WARN");
+    assert_eq!(e["route"], "/parallel");
+    assert_eq!(e["threshold_ms"], 1);
+    assert!(e["latency_ms"].as_f64().unwrap() >= 80.0);
+    let diag = e["diagnostics"].as_str().unwrap();
+    assert!(diag.contains("request{method=GET path=/parallel"), "{diag}");
+    assert!(diag.contains("task{index=0} spawned +"), "{diag}");
+    assert!(diag.contains("expected 80.0ms"), "{diag}");
+
+    let (_, body) = send(&app, get_req("/metrics")).await;
+    assert_eq!(body["routes"]["/parallel"]["slow_requests"], 1);
+    assert_eq!(body["routes"]["/sum"]["slow_requests"], 0);
+    let req = Request::get("/metrics")
+        .header(header::ACCEPT, "text/plain")
+        .body(Body::empty())
+        .unwrap();
+    let res = app.clone().oneshot(req).await.unwrap();
+    let bytes = res.into_body().collect().await.unwrap().to_bytes();
+    let text = String::from_utf8(bytes.to_vec()).unwrap();
+    assert!(text.contains("rustdemo_slow_requests_total{route=\"/parallel\"} 1"));
+}
+
+// 累计计数：恢复的基线加上本次启动以来的计数；本次启动以来的值仍单独输出
+#[tokio::test]
+async fn lifetime_totals_include_restored_counts() {
+    let builder = AppBuilder::new(Config::default()).static_files(false);
+    let metrics = builder.state().metrics();
+    let mut totals = Totals::new(UNIX_EPOCH + Duration::from_secs(1_700_000_000));
+    totals.add_route(
+        "/sum",
+        &RouteTotals {
+            requests: [("GET".to_string(), [(200, 10)].into())].into(),
+            slow_requests: 2,
+            latency_count: 10,
+            latency_sum_seconds: 1.5,
+        },
+    );
+    metrics.restore(totals);
+    let app = builder.build();
+    send(&app, get_req("/sum?nums=1")).await;
+
+    let (_, body) = send(&app, get_req("/metrics")).await;
+    assert_eq!(body["routes"]["/sum"]["requests"], 1);
+    let lifetime = &body["lifetime"];
+    assert_eq!(lifetime["since"], "2023-11-14T22:13:20.000Z");
+    assert_eq!(lifetime["routes"]["/sum"]["requests"], 11);
+    assert_eq!(lifetime["routes"]["/sum"]["by_status"]["GET"]["200"], 11);
+    assert_eq!(lifetime["routes"]["/sum"]["slow_requests"], 2);
+    assert_eq!(lifetime["routes"]["/sum"]["latency_count"], 11);
+
+    let req = Request::get("/metrics")
+        .header(header::ACCEPT, "text/plain")
+        .body(Body::empty())
+        .unwrap();
+    let res = app.clone().oneshot(req).await.unwrap();
+    let bytes = res.into_body().collect().await.unwrap().to_bytes();
+    let text = String::from_utf8(bytes.to_vec()).unwrap();
+    assert!(text.contains(
+        "rustdemo_http_requests_total{route=\"/sum\",method=\"GET\",status=\"200\"} 1\n"
+    ));
+    assert!(text.contains(
+        "rustdemo_http_requests_lifetime_total{route=\"/sum\",method=\"GET\",status=\"200\"} 11\n"
+    ));
+    assert!(text.contains("rustdemo_lifetime_start_time_seconds 1700000000\n"));
+    assert!(text.contains("# TYPE rustdemo_http_request_duration_lifetime_seconds summary"));
+
+    let saved = metrics.totals();
+    assert_eq!(saved.since_unix, 1_700_000_000);
+    assert_eq!(saved.routes["/sum"].total_requests(), 11);
+}
+
+// 时间序列：按路由与分辨率返回覆盖整个保留期的数据点
+#[tokio::test]
+async fn metrics_history_series() {
+    let app = build_app(Config::default());
+    send(&app, get_req("/sum?nums=1")).await;
+    send(&app, get_req("/sum?nums=1,2")).await;
+    send(&app, get_req("/sum?nums=x")).await;
+
+    let (status, body) = send(&app, get_req("/metrics/history?route=/sum&res=1s")).await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(body["resolution"], "1s");
+    assert_eq!(body["retention_secs"], 300);
+    let points = body["points"].as_array().unwrap();
+    assert_eq!(points.len(), 300);
+    let total: u64 = points.iter().map(|p| p["requests"].as_u64().unwrap()).sum();
+    assert_eq!(total, 3);
+    assert!(points.iter().all(|p| p["errors"] == 0));
+    let last = points.last().unwrap();
+    assert!(last["time"].as_str().unwrap().ends_with(".000Z"));
+
+    let (_, body) = send(&app, get_req("/metrics/history?route=/sum")).await;
+    assert_eq!(body["resolution"], "1m");
+    assert_eq!(body["points"].as_array().unwrap().len(), 1440);
+
+    let (_, body) = send(&app, get_req("/metrics/history?route=/nope&res=1h")).await;
+    let points = body["points"].as_array().unwrap();
+    assert!(points.iter().all(|p| p["requests"] == 0));
+
+    let (status, body) = send(&app, get_req("/metrics/history?res=5m")).await;
+    assert_eq!(status, StatusCode::BAD_REQUEST);
+    assert!(body["error"].as_str().unwrap().contains("分辨率"));
+}
+
+// 读取一帧 SSE 事件，返回 (id, event, data)
+async fn next_event(body: &mut Body) -> (String, String, Value) {
+    let frame = body.frame().await.unwrap().unwrap().into_data().unwrap();
+    let text = String::from_utf8(frame.to_vec()).unwrap();
+    let field = |name: &str| {
+        text.lines()
+            .find_map(|l| l.strip_prefix(name))
+            .unwrap_or_default()
+            .to_string()
+    };
+    let data = serde_json::from_str(&field("data: ")).unwrap();
+    (field("id: "), field("event: "), data)
+}
+
+// 指标推送：先发 snapshot，之后按间隔推送增量；Last-Event-ID 续传；超过订阅者上限返回 503
+#[tokio::test]
+async fn metrics_stream_deltas() {
+    let mut cfg = Config::default();
+    cfg.metrics.stream_interval_ms = 100;
+    cfg.metrics.stream_max_subscribers = 1;
+    let app = build_app(cfg);
+    send(&app, get_req("/sum?nums=1")).await;
+
+    let res = app
+        .clone()
+        .oneshot(get_req("/metrics/stream"))
+        .await
+        .unwrap();
+    assert_eq!(res.headers()[header::CONTENT_TYPE], "text/event-stream");
+    let mut body = res.into_body();
+    let (_, event, data) = next_event(&mut body).await;
+    assert_eq!(event, "snapshot");
+    assert_eq!(data["routes"]["/sum"]["requests"], 1);
+
+    let (status, body_json) = send(&app, get_req("/metrics/stream")).await;
+    assert_eq!(status, StatusCode::SERVICE_UNAVAILABLE);
+    assert!(body_json["error"].as_str().unwrap().contains("上限"));
+
+    send(&app, get_req("/sum?nums=2")).await;
+    send(&app, get_req("/sum?nums=x")).await;
+    // 两个请求可能落在相邻的两帧里，累加到全部出现为止
+    let (mut ok, mut bad) = (0, 0);
+    let (id, data) = loop {
+        let (id, event, data) = next_event(&mut body).await;
+        assert_eq!(event, "delta");
+        assert!(data["interval_ms"].as_u64().unwrap() >= 100);
+        let sum = &data["routes"]["/sum"];
+        ok += sum["by_status"]["GET"]["200"].as_u64().unwrap_or(0);
+        bad += sum["by_status"]["GET"]["400"].as_u64().unwrap_or(0);
+        if ok + bad == 2 {
+            break (id, data);
+        }
+    };
+    assert_eq!((ok, bad), (1, 1));
+    drop(body);
+
+    // 从上一帧续传：不再发送 snapshot，下一帧为序号更大的 delta
+    let req = Request::get("/metrics/stream")
+        .header("last-event-id", &id)
+        .body(Body::empty())
+        .unwrap();
+    let mut body = app.clone().oneshot(req).await.unwrap().into_body();
+    let (_, event, next) = next_event(&mut body).await;
+    assert_eq!(event, "delta");
+    assert_eq!(next["seq"], data["seq"].as_u64().unwrap() + 1);
+}
@@ -0,0 +1,151 @@
+// 集成测试（otel 特性）：进程内的 collector 替身接收 OTLP/HTTP JSON，校验导出的 span 与指标
+#![cfg(feature = "otel")]
+
+use std::sync::{Arc, Mutex};
+
+use axum::{
+    body::Body,
+    extract::State,
+    http::{Request, StatusCode, Uri},
+    routing::post,
+    Json, Router,
+};
+use rustdemo::{
+    otel::{OtelConfig, Telemetry},
+    AppBuilder, Config,
+};
+use serde_json::Value;
+use tower::ServiceExt;
+
+type Received = Arc<Mutex<Vec<(String, Value)>>>;
+
+// collector 替身：记录收到的 (路径, 请求体)，均返回 200
+async fn start_collector() -> (String, Received) {
+    async fn collect(
+        State(received): State<Received>,
+        uri: Uri,
+        Json(body): Json<Value>,
+    ) -> StatusCode {
+        received
+            .lock()
+            .unwrap()
+            .push((uri.path().to_string(), body));
+        StatusCode::OK
+    }
+    let received = Received::default();
+    let app = Router::new()
+        .route("/v1/traces", post(collect))
+        .route("/v1/metrics", post(collect))
+        .with_state(received.clone());
+    let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let addr = listener.local_addr().unwrap();
+    tokio::spawn(async move { axum::serve(listener, app).await });
+    (format!("http://{addr}"), received)
+}
+
+fn spans(received: &Received) -> Vec<Value> {
+    received
+        .lock()
+        .unwrap()
+        .iter()
+        .filter(|(path, _)| path == "/v1/traces")
+        .flat_map(|(_, body)| {
+            body["resourceSpans"][0]["scopeSpans"][0]["spans"]
+                .as_array()
+                .unwrap()
+                .clone()
+        })
+        .collect()
+}
+
+fn attr<'a>(item: &'a Value, key: &str) -> &'a Value {
+    let attrs = item["attributes"].as_array().unwrap();
+    &attrs.iter().find(|a| a["key"] == key).unwrap()["value"]
+}
+
+#[tokio::test]
+async fn exports_spans_and_metrics() {
+    let (endpoint, received) = start_collector().await;
+    let cfg = OtelConfig::from_env(|k| match k {
+        "OTEL_EXPORTER_OTLP_ENDPOINT" => Some(endpoint.clone()),
+        "OTEL_SERVICE_NAME" => Some("otel-test".into()),
+        _ => None,
+    })
+    .unwrap()
+    .unwrap();
+    let telemetry = Telemetry::new(cfg);
+    let builder = AppBuilder::new(Config::default())
+        .static_files(false)
+        .otel(telemetry.clone());
+    let metrics = builder.state().metrics();
+    let app = builder.build();
+
+    let send = |uri: &str, traceparent: Option<&str>| {
+        let mut req = Request::get(uri).header("x-request-id", "req-7");
+        if let Some(tp) = traceparent {
+            req = req.header("traceparent", tp).header("tracestate", "k=v");
+        }
+        app.clone().oneshot(req.body(Body::empty()).unwrap())
+    };
+    let res = send(
+        "/sum?nums=1,2",
+        Some("00-4bf92f3577b34da6a3ce929d0e0e4736-00f067aa0ba902b7-01"),
+    )
+    .await
+    .unwrap();
+    assert_eq!(res.status(), StatusCode::OK);
+    send("/health", None).await.unwrap();
+    // 上游未采样的 trace 不导出
+    send(
+        "/sum?nums=3",
+        Some("00-0af7651916cd43dd8448eb211c80319c-b7ad6b7169203331-00"),
+    )
+    .await
+    .unwrap();
+
+    telemetry.flush_traces().await.unwrap();
+    telemetry.export_metrics(&metrics).await.unwrap();
+
+    let spans = spans(&received);
+    assert_eq!(spans.len(), 2);
+    let sum = spans.iter().find(|s| s["name"] == "GET /sum").unwrap();
+    assert_eq!(sum["traceId"], "4bf92f3577b34da6a3ce929d0e0e4736");
+    assert_eq!(sum["parentSpanId"], "00f067aa0ba902b7");
+    assert_eq!(sum["traceState"], "k=v");
+    assert_eq!(sum["kind"], 2);
+    assert_eq!(attr(sum, "http.route")["stringValue"], "/sum");
+    assert_eq!(attr(sum, "http.response.status_code")["intValue"], "200");
+    assert_eq!(
+        attr(sum, "http.request.header.x-request-id")["arrayValue"]["values"][0]["stringValue"],
+        "req-7"
+    );
+    let health = spans.iter().find(|s| s["name"] == "GET /health").unwrap();
+    assert!(health.get("parentSpanId").is_none());
+    assert_ne!(health["traceId"], sum["traceId"]);
+
+    let received = received.lock().unwrap();
+    let (_, body) = received
+        .iter()
+        .find(|(path, _)| path == "/v1/metrics")
+        .unwrap();
+    let resource = &body["resourceMetrics"][0]["resource"];
+    assert_eq!(attr(resource, "service.name")["stringValue"], "otel-test");
+    let list = body["resourceMetrics"][0]["scopeMetrics"][0]["metrics"]
+        .as_array()
+        .unwrap();
+    let duration = list
+        .iter()
+        .find(|m| m["name"] == "http.server.request.duration")
+        .unwrap();
+    let point = duration["histogram"]["dataPoints"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .find(|p| attr(p, "http.route")["stringValue"] == "/sum")
+        .unwrap();
+    assert_eq!(point["count"], "2");
+    assert_eq!(
+        point["bucketCounts"].as_array().unwrap().len(),
+        point["explicitBounds"].as_array().unwrap().len() + 1
+    );
+}
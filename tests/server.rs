@@ -0,0 +1,301 @@
+// 集成测试：真实监听（TCP / Unix socket）上的请求处理与优雅关闭
+use std::{io, net::SocketAddr, time::Duration};
+
+use axum::Router;
+use rustdemo::{
+    access_log::AccessLog,
+    build_app,
+    config::{AccessLogConfig, AccessLogFormat, ListenAddr},
+    healthcheck,
+    server::{self, DrainStats, Listener, ServeOptions},
+    AppBuilder, Config,
+};
+use tokio::{
+    io::{AsyncRead, AsyncReadExt, AsyncWrite, AsyncWriteExt},
+    sync::oneshot,
+    task::JoinHandle,
+};
+
+// 发送一个 HTTP/1.1 请求（Connection: close），返回完整响应文本
+async fn roundtrip<S: AsyncRead + AsyncWrite + Unpin>(mut stream: S, path: &str) -> String {
+    let req = format!("GET {path} HTTP/1.1\r\nHost: test\r\nConnection: close\r\n\r\n");
+    stream.write_all(req.as_bytes()).await.unwrap();
+    let mut buf = String::new();
+    let _ = stream.read_to_string(&mut buf).await;
+    buf
+}
+
+type ServerTask = JoinHandle<io::Result<DrainStats>>;
+
+// 在随机端口上启动服务，返回 (地址, 关闭触发器, 服务任务)
+async fn start_tcp(opts: ServeOptions) -> (SocketAddr, oneshot::Sender<()>, ServerTask) {
+    start_app(build_app(Config::default()), opts).await
+}
+
+async fn start_app(
+    app: Router,
+    opts: ServeOptions,
+) -> (SocketAddr, oneshot::Sender<()>, ServerTask) {
+    let listener = Listener::bind(&"127.0.0.1:0".parse().unwrap(), 0o660)
+        .await
+        .unwrap();
+    let ListenAddr::Tcp(addr) = listener.local_addr().unwrap() else {
+        panic!("expected tcp listener");
+    };
+    let (tx, rx) = oneshot::channel::<()>();
+    let task = tokio::spawn(server::serve(listener, app, opts, async {
+        let _ = rx.await;
+    }));
+    (addr, tx, task)
+}
+
+#[tokio::test]
+async fn serves_over_tcp_until_shutdown() {
+    let (addr, tx, task) = start_tcp(ServeOptions::default()).await;
+
+    let stream = tokio::net::TcpStream::connect(addr).await.unwrap();
+    let res = roundtrip(stream, "/sum?nums=1,2,3").await;
+    assert!(res.starts_with("HTTP/1.1 200"));
+    assert!(res.ends_with(r#"{"total":6}"#));
+
+    tx.send(()).unwrap();
+    let stats = tokio::time::timeout(Duration::from_secs(5), task)
+        .await
+        .unwrap()
+        .unwrap()
+        .unwrap();
+    assert_eq!(stats, DrainStats::default());
+    assert!(tokio::net::TcpStream::connect(addr).await.is_err());
+}
+
+// 访问日志记录真实连接的对端地址与请求 ID
+#[tokio::test]
+async fn writes_access_log() {
+    let dir = std::env::temp_dir().join(format!("rustdemo-access-it-{}", std::process::id()));
+    std::fs::create_dir_all(&dir).unwrap();
+    let path = dir.join("access.log");
+    let _ = std::fs::remove_file(&path);
+    let log = AccessLog::open(&AccessLogConfig {
+        path: Some(path.clone()),
+        format: AccessLogFormat::Combined,
+        ..Config::default().access_log
+    })
+    .unwrap()
+    .unwrap();
+    let app = AppBuilder::new(Config::default())
+        .access_log(log.clone())
+        .build();
+    let (addr, tx, task) = start_app(app, ServeOptions::default()).await;
+
+    let stream = tokio::net::TcpStream::connect(addr).await.unwrap();
+    let res = roundtrip(stream, "/sum?nums=1,2").await;
+    assert!(res.ends_with(r#"{"total":3}"#));
+    tx.send(()).unwrap();
+    task.await.unwrap().unwrap();
+
+    assert!(log.flush());
+    let text = std::fs::read_to_string(&path).unwrap();
+    let line = text.lines().next().unwrap();
+    assert!(line.starts_with("127.0.0.1 - - ["), "{line}");
+    assert!(
+        line.ends_with(r#""GET /sum?nums=1,2 HTTP/1.1" 200 11 "-" "-""#),
+        "{line}"
+    );
+    std::fs::remove_dir_all(&dir).unwrap();
+}
+
+// 期限内完成的在途请求正常返回，计入 drained
+#[tokio::test]
+async fn drains_in_flight_requests() {
+    let opts = ServeOptions {
+        drain_timeout: Some(Duration::from_secs(5)),
+        ..ServeOptions::default()
+    };
+    let (addr, tx, task) = start_tcp(opts).await;
+    let stream = tokio::net::TcpStream::connect(addr).await.unwrap();
+    let client = tokio::spawn(roundtrip(stream, "/parallel?n=3"));
+    tokio::time::sleep(Duration::from_millis(30)).await;
+
+    tx.send(()).unwrap();
+    let stats = task.await.unwrap().unwrap();
+    assert_eq!(
+        stats,
+        DrainStats {
+            drained: 1,
+            cut_off: 0
+        }
+    );
+    assert!(client.await.unwrap().starts_with("HTTP/1.1 200"));
+}
+
+// 超过期限的在途请求（长耗时 /parallel）被强制断开，计入 cut_off
+#[tokio::test]
+async fn cuts_off_requests_after_deadline() {
+    let opts = ServeOptions {
+        drain_timeout: Some(Duration::from_millis(100)),
+        ..ServeOptions::default()
+    };
+    let (addr, tx, task) = start_tcp(opts).await;
+    let stream = tokio::net::TcpStream::connect(addr).await.unwrap();
+    let client = tokio::spawn(roundtrip(stream, "/parallel?n=32"));
+    tokio::time::sleep(Duration::from_millis(30)).await;
+
+    tx.send(()).unwrap();
+    let stats = tokio::time::timeout(Duration::from_secs(2), task)
+        .await
+        .unwrap()
+        .unwrap()
+        .unwrap();
+    assert_eq!(
+        stats,
+        DrainStats {
+            drained: 0,
+            cut_off: 1
+        }
+    );
+    assert_eq!(client.await.unwrap(), "");
+}
+
+// SSE 推送在 serve 的优雅关闭开始时结束（无需额外通知），订阅中的连接不会拖到 drain 超时
+async fn drains_sse_subscriber(path: &str) {
+    let app = AppBuilder::new(Config::default())
+        .static_files(false)
+        .build();
+    let opts = ServeOptions {
+        drain_timeout: Some(Duration::from_secs(5)),
+        ..ServeOptions::default()
+    };
+    let (addr, tx, task) = start_app(app, opts).await;
+    let mut stream = tokio::net::TcpStream::connect(addr).await.unwrap();
+    let req = format!("GET {path} HTTP/1.1\r\nHost: test\r\n\r\n");
+    stream.write_all(req.as_bytes()).await.unwrap();
+    let mut buf = vec![0u8; 4096];
+    let n = stream.read(&mut buf).await.unwrap();
+    assert!(buf[..n].starts_with(b"HTTP/1.1 200"));
+
+    tx.send(()).unwrap();
+    let stats = tokio::time::timeout(Duration::from_secs(2), task)
+        .await
+        .unwrap()
+        .unwrap()
+        .unwrap();
+    assert_eq!(
+        stats,
+        DrainStats {
+            drained: 1,
+            cut_off: 0
+        }
+    );
+    // 响应正常结束（chunked 结束块）后连接关闭
+    let mut rest = Vec::new();
+    stream.read_to_end(&mut rest).await.unwrap();
+    let text = String::from_utf8_lossy(&buf[..n]).into_owned() + &String::from_utf8_lossy(&rest);
+    assert!(text.ends_with("0\r\n\r\n"), "{text}");
+}
+
+#[tokio::test]
+async fn drains_metrics_stream_subscribers() {
+    drains_sse_subscriber("/metrics/stream").await;
+}
+
+#[tokio::test]
+async fn drains_debug_requests_stream_subscribers() {
+    drains_sse_subscriber("/debug/requests/stream").await;
+}
+
+// healthcheck 子命令使用的探测：服务运行时得到 200，关闭后连接失败
+#[tokio::test]
+async fn healthcheck_probe() {
+    let (addr, tx, task) = start_tcp(ServeOptions::default()).await;
+    let target = ListenAddr::Tcp(addr);
+    let probe = |t: ListenAddr| {
+        tokio::task::spawn_blocking(move || healthcheck::probe(&t, Duration::from_secs(2)))
+    };
+    assert_eq!(probe(target.clone()).await.unwrap().unwrap(), 200);
+
+    tx.send(()).unwrap();
+    task.await.unwrap().unwrap();
+    assert!(probe(target).await.unwrap().is_err());
+}
+
+// 连接数上限：第一个 keep-alive 连接占用唯一名额时，第二个连接要等它关闭后才被服务
+#[tokio::test]
+async fn connection_limit_defers_extra_connections() {
+    let mut cfg = Config::default();
+    cfg.http.max_connections = 1;
+    let (addr, tx, task) = start_tcp(ServeOptions::from_config(&cfg)).await;
+
+    let mut first = tokio::net::TcpStream::connect(addr).await.unwrap();
+    first
+        .write_all(b"GET /health HTTP/1.1\r\nHost: test\r\n\r\n")
+        .await
+        .unwrap();
+    let mut buf = [0u8; 512];
+    let n = first.read(&mut buf).await.unwrap();
+    assert!(buf[..n].starts_with(b"HTTP/1.1 200"));
+
+    let second = tokio::net::TcpStream::connect(addr).await.unwrap();
+    let pending = tokio::spawn(roundtrip(second, "/health"));
+    tokio::time::sleep(Duration::from_millis(100)).await;
+    assert!(!pending.is_finished());
+
+    drop(first);
+    let res = tokio::time::timeout(Duration::from_secs(2), pending)
+        .await
+        .unwrap()
+        .unwrap();
+    assert!(res.starts_with("HTTP/1.1 200"));
+
+    tx.send(()).unwrap();
+    task.await.unwrap().unwrap();
+}
+
+// 请求头超过 max_header_bytes 时返回 431
+#[tokio::test]
+async fn rejects_oversized_headers() {
+    let mut cfg = Config::default();
+    cfg.http.max_header_bytes = 8192;
+    let (addr, tx, task) = start_tcp(ServeOptions::from_config(&cfg)).await;
+
+    let mut stream = tokio::net::TcpStream::connect(addr).await.unwrap();
+    let big = "a".repeat(16 * 1024);
+    let req = format!("GET /health HTTP/1.1\r\nHost: test\r\nX-Big: {big}\r\n\r\n");
+    let _ = stream.write_all(req.as_bytes()).await;
+    let mut res = String::new();
+    let _ = stream.read_to_string(&mut res).await;
+    assert!(res.starts_with("HTTP/1.1 431"), "{res}");
+
+    tx.send(()).unwrap();
+    task.await.unwrap().unwrap();
+}
+
+#[cfg(unix)]
+#[tokio::test]
+async fn serves_over_unix_socket_with_mode() {
+    use std::os::unix::fs::PermissionsExt;
+
+    let path = std::env::temp_dir().join(format!("rustdemo-test-{}.sock", std::process::id()));
+    let addr = ListenAddr::Unix(path.clone());
+    let listener = Listener::bind(&addr, 0o600).await.unwrap();
+    let mode = std::fs::metadata(&path).unwrap().permissions().mode();
+    assert_eq!(mode & 0o777, 0o600);
+
+    let (tx, rx) = oneshot::channel::<()>();
+    let app = build_app(Config::default());
+    let task = tokio::spawn(server::serve(
+        listener,
+        app,
+        ServeOptions::default(),
+        async {
+            let _ = rx.await;
+        },
+    ));
+    let stream = tokio::net::UnixStream::connect(&path).await.unwrap();
+    let res = roundtrip(stream, "/health").await;
+    assert!(res.contains(r#"{"status":"healthy"}"#));
+
+    tx.send(()).unwrap();
+    task.await.unwrap().unwrap();
+    // 关闭后清理 socket 文件
+    assert!(!path.exists());
+}
@@ -0,0 +1,114 @@
+// 集成测试：本地 UDP / Unix datagram socket 充当 StatsD agent，校验推送的计数、gauge 与耗时
+use std::{sync::Arc, time::Duration};
+
+use axum::{body::Body, http::Request, Router};
+use rustdemo::{config::StatsdAddr, metrics::RequestMetrics, statsd::Statsd, AppBuilder, Config};
+use tower::ServiceExt;
+
+async fn setup(cfg: Config) -> (Router, Arc<Statsd>, Arc<RequestMetrics>) {
+    let statsd = Statsd::connect(&cfg.statsd).await.unwrap().unwrap();
+    let builder = AppBuilder::new(cfg)
+        .static_files(false)
+        .statsd(statsd.clone());
+    let metrics = builder.state().metrics();
+    (builder.build(), statsd, metrics)
+}
+
+async fn get(app: &Router, uri: &str) {
+    let req = Request::get(uri).body(Body::empty()).unwrap();
+    app.clone().oneshot(req).await.unwrap();
+}
+
+// 收取 agent 替身上已到达的全部行（短时间内无新数据报即结束）
+async fn receive(recv: impl Fn(&mut [u8]) -> std::io::Result<usize>) -> Vec<String> {
+    let mut lines = Vec::new();
+    let mut buf = vec![0; 65536];
+    for _ in 0..50 {
+        match recv(&mut buf) {
+            Ok(n) => {
+                let packet = std::str::from_utf8(&buf[..n]).unwrap();
+                lines.extend(packet.lines().map(String::from));
+            }
+            Err(e) if e.kind() == std::io::ErrorKind::WouldBlock => {
+                tokio::time::sleep(Duration::from_millis(10)).await
+            }
+            Err(e) => panic!("{e}"),
+        }
+    }
+    lines
+}
+
+#[tokio::test]
+async fn pushes_dogstatsd_over_udp() {
+    let agent = std::net::UdpSocket::bind("127.0.0.1:0").unwrap();
+    agent.set_nonblocking(true).unwrap();
+    let mut cfg = Config::default();
+    cfg.statsd.address = Some(StatsdAddr::Udp(agent.local_addr().unwrap().to_string()));
+    cfg.statsd.dogstatsd = true;
+    cfg.statsd.tags = vec!["env:test".into()];
+    cfg.statsd.max_packet_bytes = 256;
+    let (app, statsd, metrics) = setup(cfg).await;
+
+    get(&app, "/sum?nums=1").await;
+    get(&app, "/sum?nums=2").await;
+    get(&app, "/sum?nums=x").await;
+    statsd.flush(&metrics).await.unwrap();
+    let lines = receive(|buf| agent.recv(buf)).await;
+    for expected in [
+        "rustdemo.http.requests:2|c|#route:/sum,method:GET,status:200,env:test",
+        "rustdemo.http.requests:1|c|#route:/sum,method:GET,status:400,env:test",
+        "rustdemo.http.requests_in_flight:0|g|#route:/sum,env:test",
+    ] {
+        assert!(
+            lines.iter().any(|l| l == expected),
+            "{expected} not in {lines:?}"
+        );
+    }
+    let durations: Vec<_> = lines
+        .iter()
+        .filter(|l| l.starts_with("rustdemo.http.request.duration:"))
+        .collect();
+    assert_eq!(durations.len(), 3, "{lines:?}");
+    assert!(durations
+        .iter()
+        .all(|l| l.ends_with("|ms|#route:/sum,env:test")));
+
+    // 计数为增量：没有新请求时只发送 gauge
+    statsd.flush(&metrics).await.unwrap();
+    let lines = receive(|buf| agent.recv(buf)).await;
+    assert_eq!(
+        lines,
+        ["rustdemo.http.requests_in_flight:0|g|#route:/sum,env:test"]
+    );
+}
+
+#[cfg(unix)]
+#[tokio::test]
+async fn pushes_plain_statsd_over_unix_socket() {
+    let dir = std::env::temp_dir().join(format!("rustdemo-statsd-{}", std::process::id()));
+    std::fs::create_dir_all(&dir).unwrap();
+    let path = dir.join("agent.sock");
+    let agent = std::os::unix::net::UnixDatagram::bind(&path).unwrap();
+    agent.set_nonblocking(true).unwrap();
+    let mut cfg = Config::default();
+    cfg.statsd.address = Some(StatsdAddr::Unix(path));
+    cfg.statsd.prefix = "app".into();
+    let (app, statsd, metrics) = setup(cfg).await;
+
+    get(&app, "/sum?nums=1").await;
+    statsd.flush(&metrics).await.unwrap();
+    let lines = receive(|buf| agent.recv(buf)).await;
+    assert!(
+        lines
+            .iter()
+            .any(|l| l == "app.http.requests.sum.GET.200:1|c"),
+        "{lines:?}"
+    );
+    assert!(lines
+        .iter()
+        .any(|l| l == "app.http.requests_in_flight.sum:0|g"));
+    assert!(lines
+        .iter()
+        .any(|l| l.starts_with("app.http.request.duration.sum:") && l.ends_with("|ms")));
+    std::fs::remove_dir_all(&dir).unwrap();
+}